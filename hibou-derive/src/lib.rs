@@ -0,0 +1,261 @@
+//! 構造体のフィールドからhibouの `Table` 実装を生成するderiveマクロ
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::quote;
+use syn::{
+    parse_macro_input, Attribute, Data, DeriveInput, Error, Fields, GenericArgument, Lit, Meta,
+    NestedMeta, PathArguments, Type,
+};
+
+/// `crate::external::gtfsdb::Table` を実装する
+///
+/// - 構造体の `#[table(name = "trips")]` でテーブル名を指定する (必須)
+/// - 構造体の `#[table(index = "route_id")]` で登録後にインデックスを作成するカラムを指定する (複数可)
+/// - 構造体の `#[table(constraint = "PRIMARY KEY(trip_id, stop_sequence)")]` で表制約を指定する (複数可)
+/// - 各フィールドの `#[table(sql = "text not null")]` でカラムの型と制約を指定する (必須)
+/// - 区分値のフィールドは `#[table(sql = "int", enum_values)]` で取りうる値をcolumnsに含める
+///   (フィールドの型 (Optionなら中の型) に `EnumValues` の実装が必要)
+///
+/// カラムはフィールドの宣言順に並ぶため, column_namesとcreate_sqlが構造体とずれることがない
+/// columnsのNULL許容はフィールドがOptionかどうかで決まる
+#[proc_macro_derive(Table, attributes(table))]
+pub fn derive_table(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let ident = &input.ident;
+
+    let mut name = None;
+    let mut indexes = Vec::new();
+    let mut constraints = Vec::new();
+    for (key, value) in table_attributes(&input.attrs)? {
+        match key.as_str() {
+            "name" => name = Some(value),
+            "index" => indexes.push(value),
+            "constraint" => constraints.push(value),
+            _ => {
+                return Err(Error::new(
+                    Span::call_site(),
+                    format!("unknown key: {}", key),
+                ))
+            }
+        }
+    }
+    let name =
+        name.ok_or_else(|| Error::new_spanned(ident, "#[table(name = \"...\")] is required"))?;
+
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => return Err(Error::new_spanned(ident, "Table requires named fields")),
+        },
+        _ => {
+            return Err(Error::new_spanned(
+                ident,
+                "Table can only be derived for structs",
+            ))
+        }
+    };
+
+    let mut columns = Vec::new();
+    let mut definitions = Vec::new();
+    let mut descriptions = Vec::new();
+    for field in fields {
+        let column = field.ident.as_ref().expect("named field").to_string();
+        let attributes = table_attributes(&field.attrs)?;
+        let sql = attributes
+            .iter()
+            .find(|(key, _)| key == "sql")
+            .map(|(_, value)| value.clone())
+            .ok_or_else(|| Error::new_spanned(field, "#[table(sql = \"...\")] is required"))?;
+        let sql_type = sql
+            .split_whitespace()
+            .next()
+            .unwrap_or_default()
+            .to_string();
+        let (inner, nullable) = match option_inner(&field.ty) {
+            Some(x) => (x, true),
+            None => (&field.ty, false),
+        };
+        let values = if attributes.iter().any(|(key, _)| key == "enum_values") {
+            quote! { Some(<#inner as crate::external::gtfsdb::EnumValues>::values()) }
+        } else {
+            quote! { None }
+        };
+        descriptions.push(quote! {
+            crate::external::gtfsdb::ColumnDescription {
+                name: #column,
+                sql_type: #sql_type.to_string(),
+                nullable: #nullable,
+                values: #values,
+            }
+        });
+        definitions.push(format!("{} {}", column, sql));
+        columns.push(column);
+    }
+    definitions.extend(constraints);
+    let create_sql = definitions.join(",\n");
+
+    let index_columns = if indexes.is_empty() {
+        quote! {}
+    } else {
+        quote! {
+            fn index_columns() -> &'static [&'static str] {
+                &[#(#indexes),*]
+            }
+        }
+    };
+
+    Ok(quote! {
+        impl crate::external::gtfsdb::Table for #ident {
+            fn table_name() -> &'static str {
+                #name
+            }
+
+            fn column_names() -> &'static [&'static str] {
+                &[#(#columns),*]
+            }
+
+            fn create_sql() -> &'static str {
+                #create_sql
+            }
+
+            fn columns() -> Vec<crate::external::gtfsdb::ColumnDescription> {
+                vec![#(#descriptions),*]
+            }
+
+            #index_columns
+        }
+    })
+}
+
+/// `crate::external::gtfsdb::EnumValues` を実装する
+///
+/// - 値を持たないバリアントだけの列挙型に使う
+/// - 値はserdeでシリアライズした結果になるため, `Serialize_repr` やrenameの指定がそのまま反映される
+/// - 各バリアントのdocコメントを説明にする
+#[proc_macro_derive(EnumValues)]
+pub fn derive_enum_values(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_enum_values(&input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
+fn expand_enum_values(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let ident = &input.ident;
+    let variants = match &input.data {
+        Data::Enum(data) => &data.variants,
+        _ => {
+            return Err(Error::new_spanned(
+                ident,
+                "EnumValues can only be derived for enums",
+            ))
+        }
+    };
+
+    let mut values = Vec::new();
+    for variant in variants {
+        if !matches!(variant.fields, Fields::Unit) {
+            return Err(Error::new_spanned(
+                variant,
+                "EnumValues requires unit variants",
+            ));
+        }
+        let name = &variant.ident;
+        let label = name.to_string();
+        let description = doc_comment(&variant.attrs);
+        values.push(quote! {
+            crate::external::gtfsdb::EnumValue::new(&#ident::#name, #label, #description)
+        });
+    }
+
+    Ok(quote! {
+        impl crate::external::gtfsdb::EnumValues for #ident {
+            fn values() -> Vec<crate::external::gtfsdb::EnumValue> {
+                vec![#(#values),*]
+            }
+        }
+    })
+}
+
+/// `#[doc = "..."]` (docコメント) を1行につなげる
+fn doc_comment(attrs: &[Attribute]) -> String {
+    let lines: Vec<String> = attrs
+        .iter()
+        .filter(|a| a.path.is_ident("doc"))
+        .filter_map(|a| match a.parse_meta() {
+            Ok(Meta::NameValue(nv)) => match nv.lit {
+                Lit::Str(value) => Some(value.value().trim().to_string()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect();
+    lines.join(" ")
+}
+
+/// `Option<T>` ならTを返す
+fn option_inner(ty: &Type) -> Option<&Type> {
+    let segment = match ty {
+        Type::Path(path) => path.path.segments.last()?,
+        _ => return None,
+    };
+    if segment.ident != "Option" {
+        return None;
+    }
+    match &segment.arguments {
+        PathArguments::AngleBracketed(args) => match args.args.first()? {
+            GenericArgument::Type(inner) => Some(inner),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// `#[table(key = "value", ...)]` の (key, value) を宣言順に返す
+/// 値のない `#[table(key)]` は空文字列を値にする
+fn table_attributes(attrs: &[Attribute]) -> syn::Result<Vec<(String, String)>> {
+    let mut results = Vec::new();
+    for attr in attrs.iter().filter(|a| a.path.is_ident("table")) {
+        let list = match attr.parse_meta()? {
+            Meta::List(list) => list,
+            meta => {
+                return Err(Error::new_spanned(
+                    meta,
+                    "expected #[table(key = \"value\")]",
+                ))
+            }
+        };
+        for nested in list.nested {
+            match nested {
+                NestedMeta::Meta(Meta::NameValue(nv)) => {
+                    let key =
+                        nv.path.get_ident().map(|x| x.to_string()).ok_or_else(|| {
+                            Error::new_spanned(&nv.path, "expected an identifier")
+                        })?;
+                    match nv.lit {
+                        Lit::Str(value) => results.push((key, value.value())),
+                        lit => return Err(Error::new_spanned(lit, "expected a string literal")),
+                    }
+                }
+                NestedMeta::Meta(Meta::Path(path)) => {
+                    let key = path
+                        .get_ident()
+                        .map(|x| x.to_string())
+                        .ok_or_else(|| Error::new_spanned(&path, "expected an identifier"))?;
+                    results.push((key, String::new()));
+                }
+                nested => {
+                    return Err(Error::new_spanned(nested, "expected key = \"value\""));
+                }
+            }
+        }
+    }
+    Ok(results)
+}
@@ -1,4 +1,28 @@
+pub mod agency;
+pub mod attribution;
+pub mod block;
+pub mod calendar;
+pub mod export;
+pub mod extract;
+pub mod fare;
+pub mod feed_check;
+pub mod frequency;
 pub mod gtfs;
+pub mod headway;
+pub mod interpolate_times;
+pub mod merge;
+pub mod office;
 pub mod route;
+pub mod service_calendar;
+pub mod shape;
+pub mod shape_dist;
+pub mod stats;
+pub mod stop;
+pub mod stop_time;
 pub mod test;
+pub mod time_zone;
+pub mod timetable;
+pub mod transfer;
+pub mod translation;
 pub mod trip;
+pub mod validate;
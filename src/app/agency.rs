@@ -0,0 +1,24 @@
+use anyhow::Result;
+
+use crate::external::gtfs::agency::Agency;
+use crate::external::gtfs::{Gtfs, Query};
+use crate::external::gtfsdb::{GtfsDb, Table};
+
+pub struct AgencyService {
+    gtfs: GtfsDb,
+}
+
+impl AgencyService {
+    pub fn new(gtfs: GtfsDb) -> Self {
+        Self { gtfs }
+    }
+
+    pub fn fetch(&mut self, query: &Query) -> Result<Vec<Agency>> {
+        self.gtfs.select_agencies(query)
+    }
+
+    /// fetchと同じ条件に当てはまる件数
+    pub fn count(&mut self, query: &Query) -> Result<u32> {
+        self.gtfs.count_matches(Agency::table_name(), query)
+    }
+}
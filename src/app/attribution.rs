@@ -0,0 +1,24 @@
+use anyhow::Result;
+
+use crate::external::gtfs::attributions::Attribution;
+use crate::external::gtfs::{Gtfs, Query};
+use crate::external::gtfsdb::{GtfsDb, Table};
+
+pub struct AttributionService {
+    gtfs: GtfsDb,
+}
+
+impl AttributionService {
+    pub fn new(gtfs: GtfsDb) -> Self {
+        Self { gtfs }
+    }
+
+    pub fn fetch(&mut self, query: &Query) -> Result<Vec<Attribution>> {
+        self.gtfs.select_attributions(query)
+    }
+
+    /// fetchと同じ条件に当てはまる件数
+    pub fn count(&mut self, query: &Query) -> Result<u32> {
+        self.gtfs.count_matches(Attribution::table_name(), query)
+    }
+}
@@ -0,0 +1,24 @@
+use anyhow::Result;
+
+use crate::external;
+use crate::external::gtfs::block_trips::BlockTrip;
+use crate::external::gtfs::trips::{BlockId, TripId};
+
+pub struct BlockService {
+    gtfs: Box<dyn external::gtfs::Gtfs>,
+}
+
+impl BlockService {
+    pub fn new(gtfs: Box<dyn external::gtfs::Gtfs>) -> Self {
+        Self { gtfs }
+    }
+
+    pub fn fetch(&mut self, block_id: &BlockId) -> Result<Vec<BlockTrip>> {
+        self.gtfs.select_block_trips(block_id)
+    }
+
+    /// 便が属するblockの全便. block_idのない便なら空
+    pub fn fetch_by_trip_id(&mut self, trip_id: &TripId) -> Result<Vec<BlockTrip>> {
+        self.gtfs.select_block_trips_by_trip_id(trip_id)
+    }
+}
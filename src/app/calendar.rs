@@ -0,0 +1,35 @@
+use anyhow::Result;
+
+use crate::external::gtfs::calendar::{Calendar, ServiceId};
+use crate::external::gtfs::{Gtfs, Query};
+use crate::external::gtfsdb::{GtfsDb, Table};
+
+pub struct CalendarService {
+    gtfs: GtfsDb,
+}
+
+impl CalendarService {
+    pub fn new(gtfs: GtfsDb) -> Self {
+        Self { gtfs }
+    }
+
+    pub fn fetch(
+        &mut self,
+        service_id: Option<&ServiceId>,
+        query: &Query,
+    ) -> Result<Vec<Calendar>> {
+        match service_id {
+            Some(id) => self.gtfs.select_calendars_by_service_id(id, query),
+            None => self.gtfs.select_calendars(query),
+        }
+    }
+
+    /// fetchと同じ条件に当てはまる件数
+    pub fn count(&mut self, service_id: Option<&ServiceId>, query: &Query) -> Result<u32> {
+        let query = match service_id {
+            Some(id) => query.clone().and_eq("service_id", id),
+            None => query.clone(),
+        };
+        self.gtfs.count_matches(Calendar::table_name(), &query)
+    }
+}
@@ -0,0 +1,63 @@
+use anyhow::Result;
+use log::info;
+use serde::Serialize;
+
+use crate::external;
+use crate::external::gtfs::Query;
+use crate::external::gtfsdb::Table;
+use crate::external::gtfszip::GtfsZip;
+
+pub struct ExportService {
+    gtfs: Box<dyn external::gtfs::Gtfs>,
+    zip: GtfsZip,
+}
+
+/// データベースの内容をGTFSのzipファイルとして書き出すアプリケーションサービス
+impl ExportService {
+    pub fn new(gtfs: Box<dyn external::gtfs::Gtfs>, zip: GtfsZip) -> Self {
+        Self { gtfs, zip }
+    }
+
+    pub fn export(mut self) -> Result<()> {
+        let all = Query::default();
+
+        write(&mut self.zip, self.gtfs.select_agencies(&all)?)?;
+        write(&mut self.zip, self.gtfs.select_stops(&all)?)?;
+        write(&mut self.zip, self.gtfs.select_routes(&all)?)?;
+        write(&mut self.zip, self.gtfs.select_trips(&all)?)?;
+        write(&mut self.zip, self.gtfs.select_stop_times(&all)?)?;
+        write(&mut self.zip, self.gtfs.select_calendars(&all)?)?;
+        write(&mut self.zip, self.gtfs.select_calendar_dates(&all)?)?;
+        write(&mut self.zip, self.gtfs.select_fare_attributes(&all)?)?;
+        write(&mut self.zip, self.gtfs.select_fare_rules(&all)?)?;
+        write(&mut self.zip, self.gtfs.select_shapes(&all)?)?;
+        write(&mut self.zip, self.gtfs.select_frequencies(&all)?)?;
+        write(&mut self.zip, self.gtfs.select_transfers(&all)?)?;
+        write(&mut self.zip, self.gtfs.select_levels(&all)?)?;
+        write(&mut self.zip, self.gtfs.select_pathways(&all)?)?;
+        write(&mut self.zip, self.gtfs.select_attributions(&all)?)?;
+        write(
+            &mut self.zip,
+            self.gtfs.select_feed_info()?.into_iter().collect(),
+        )?;
+        write(&mut self.zip, self.gtfs.select_offices(&all)?)?;
+        write(&mut self.zip, self.gtfs.select_translations(&all)?)?;
+
+        self.zip.finish()
+    }
+}
+
+/// 空のテーブルはファイル自体を作らない
+fn write<T>(zip: &mut GtfsZip, records: Vec<T>) -> Result<()>
+where
+    T: Serialize + Table,
+{
+    if records.is_empty() {
+        info!("ℹ️ [{}] skip (no records)", T::table_name());
+        return Ok(());
+    }
+    info!("ℹ️ [{}] {} records", T::table_name(), records.len());
+    zip.write(&records)?;
+    info!("  ✨ Success");
+    Ok(())
+}
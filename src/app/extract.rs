@@ -0,0 +1,205 @@
+use std::collections::HashSet;
+
+use anyhow::{bail, Result};
+use log::info;
+
+use crate::external;
+use crate::external::gtfs::routes::RouteId;
+use crate::external::gtfs::stops::Stop;
+use crate::external::gtfs::{Gtfs, Query};
+use crate::external::gtfsdb::Table;
+
+/// 指定した経路と, それが参照するレコードだけを別のデータベースへ書き出すアプリケーションサービス
+pub struct ExtractService {
+    source: Box<dyn external::gtfs::Gtfs>,
+    target: Box<dyn external::gtfs::Gtfs>,
+}
+
+impl ExtractService {
+    pub fn new(
+        source: Box<dyn external::gtfs::Gtfs>,
+        target: Box<dyn external::gtfs::Gtfs>,
+    ) -> Self {
+        Self { source, target }
+    }
+
+    /// 参照される側のテーブルから順に登録し, 書き出した先を単独のfeedとして使えるようにする
+    pub fn extract(mut self, route_ids: &[RouteId]) -> Result<()> {
+        let all = Query::default();
+
+        let mut routes = Vec::new();
+        for id in route_ids {
+            let found = self.source.select_routes_by_route_id(id, &all)?;
+            if found.is_empty() {
+                bail!("経路が見つかりません: {}", id);
+            }
+            routes.extend(found);
+        }
+        let agency_ids: HashSet<_> = routes.iter().map(|x| x.agency_id.clone()).collect();
+
+        let mut trips = Vec::new();
+        for id in route_ids {
+            trips.extend(
+                self.source
+                    .select_trips_by_route_and_direction(Some(id), None, &all)?,
+            );
+        }
+        let trip_ids: HashSet<_> = trips.iter().map(|x| x.trip_id.clone()).collect();
+        let service_ids: HashSet<_> = trips.iter().map(|x| x.service_id.clone()).collect();
+        let shape_ids: HashSet<_> = trips.iter().filter_map(|x| x.shape_id.clone()).collect();
+        let office_ids: HashSet<_> = trips
+            .iter()
+            .filter_map(|x| x.jp_office_id.clone())
+            .collect();
+
+        let mut stop_times = Vec::new();
+        for trip in &trips {
+            stop_times.extend(
+                self.source
+                    .select_stop_times_by_trip_id(&trip.trip_id, &all)?,
+            );
+        }
+        let stops = with_parents(
+            self.source.select_stops(&all)?,
+            stop_times.iter().map(|x| x.stop_id.clone()).collect(),
+        );
+        let stop_ids: HashSet<_> = stops.iter().map(|x| x.stop_id.clone()).collect();
+
+        let fare_rules: Vec<_> = self
+            .source
+            .select_fare_rules(&all)?
+            .into_iter()
+            .filter(|x| x.route_id.as_ref().is_some_and(|id| route_ids.contains(id)))
+            .collect();
+        let fare_ids: HashSet<_> = fare_rules.iter().map(|x| x.fare_id.clone()).collect();
+
+        let mut shapes = Vec::new();
+        for id in &shape_ids {
+            shapes.extend(self.source.select_shapes_by_shape_id(id, &all)?);
+        }
+
+        info!("ℹ️ Create all tables.");
+        self.target.create_all()?;
+        info!("  ✨ Success");
+
+        let agencies = self.source.select_agencies(&all)?;
+        let offices = self.source.select_offices(&all)?;
+        let calendars = self.source.select_calendars(&all)?;
+        let calendar_dates = self.source.select_calendar_dates(&all)?;
+        let fare_attributes = self.source.select_fare_attributes(&all)?;
+        let frequencies = self.source.select_frequencies(&all)?;
+        let transfers = self.source.select_transfers(&all)?;
+        let target = self.target.as_mut();
+
+        copy(
+            target,
+            agencies
+                .into_iter()
+                .filter(|x| agency_ids.contains(&x.agency_id))
+                .collect(),
+            |db, x| db.insert_agencies(x),
+        )?;
+        copy(target, stops, |db, x| db.insert_stops(x))?;
+        copy(target, routes, |db, x| db.insert_routes(x))?;
+        copy(
+            target,
+            offices
+                .into_iter()
+                .filter(|x| office_ids.contains(&x.office_id))
+                .collect(),
+            |db, x| db.insert_offices(x),
+        )?;
+        copy(target, trips, |db, x| db.insert_trips(x))?;
+        copy(target, stop_times, |db, x| db.insert_stop_times(x))?;
+        copy(
+            target,
+            calendars
+                .into_iter()
+                .filter(|x| service_ids.contains(&x.service_id))
+                .collect(),
+            |db, x| db.insert_calendars(x),
+        )?;
+        copy(
+            target,
+            calendar_dates
+                .into_iter()
+                .filter(|x| service_ids.contains(&x.service_id))
+                .collect(),
+            |db, x| db.insert_calendar_dates(x),
+        )?;
+        copy(
+            target,
+            fare_attributes
+                .into_iter()
+                .filter(|x| fare_ids.contains(&x.fare_id))
+                .collect(),
+            |db, x| db.insert_fare_attributes(x),
+        )?;
+        copy(target, fare_rules, |db, x| db.insert_fare_rules(x))?;
+        copy(target, shapes, |db, x| db.insert_shapes(x))?;
+        copy(
+            target,
+            frequencies
+                .into_iter()
+                .filter(|x| trip_ids.contains(&x.trip_id))
+                .collect(),
+            |db, x| db.insert_frequencies(x),
+        )?;
+        copy(
+            target,
+            transfers
+                .into_iter()
+                .filter(|x| stop_ids.contains(&x.from_stop_id) && stop_ids.contains(&x.to_stop_id))
+                .collect(),
+            |db, x| db.insert_transfers(x),
+        )?;
+        if let Some(feed_info) = self.source.select_feed_info()? {
+            info!("ℹ️ [feed_info] 1 records");
+            target.insert_feed_info(&feed_info)?;
+            info!("  ✨ Success");
+        }
+
+        info!("ℹ️ Create indexes.");
+        target.index_all()?;
+        info!("  ✨ Success");
+        Ok(())
+    }
+}
+
+/// stop_idsのstopと, その親駅を辿ったstop. 親駅を参照するstopより先に並べる
+fn with_parents(stops: Vec<Stop>, mut stop_ids: HashSet<String>) -> Vec<Stop> {
+    loop {
+        let parents: Vec<_> = stops
+            .iter()
+            .filter(|x| stop_ids.contains(&x.stop_id))
+            .filter_map(|x| x.parent_station.clone())
+            .filter(|x| !stop_ids.contains(x))
+            .collect();
+        if parents.is_empty() {
+            break;
+        }
+        stop_ids.extend(parents);
+    }
+    let mut stops: Vec<_> = stops
+        .into_iter()
+        .filter(|x| stop_ids.contains(&x.stop_id))
+        .collect();
+    stops.sort_by_key(|x| x.parent_station.is_some());
+    stops
+}
+
+/// 空のテーブルは登録しない
+fn copy<T, F>(target: &mut dyn Gtfs, records: Vec<T>, insert: F) -> Result<()>
+where
+    T: Table,
+    F: FnOnce(&mut dyn Gtfs, &[T]) -> Result<()>,
+{
+    if records.is_empty() {
+        info!("ℹ️ [{}] skip (no records)", T::table_name());
+        return Ok(());
+    }
+    info!("ℹ️ [{}] {} records", T::table_name(), records.len());
+    insert(target, &records)?;
+    info!("  ✨ Success");
+    Ok(())
+}
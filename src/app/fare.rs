@@ -0,0 +1,88 @@
+use std::collections::{BTreeSet, HashMap};
+
+use anyhow::Result;
+
+use crate::external;
+use crate::external::gtfs::fare_attributes::{FareAttribute, FareId};
+use crate::external::gtfs::fare_rules::FareRule;
+use crate::external::gtfs::routes::RouteId;
+use crate::external::gtfs::{Query, ZoneId};
+
+/// 運賃を求める乗車条件
+#[derive(Debug, Default)]
+pub struct Ride {
+    pub route_id: Option<RouteId>,
+    /// 乗車地ゾーン
+    pub origin_id: Option<ZoneId>,
+    /// 降車地ゾーン
+    pub destination_id: Option<ZoneId>,
+    /// 通過ゾーン
+    pub contains_ids: Vec<ZoneId>,
+}
+
+impl Ride {
+    /// contains_id以外の条件が一致するか. ルール側が空の項目はどの値にも一致する
+    fn matches(&self, rule: &FareRule) -> bool {
+        fn eq(rule: &Option<String>, ride: &Option<String>) -> bool {
+            rule.is_none() || rule == ride
+        }
+        eq(&rule.route_id, &self.route_id)
+            && eq(&rule.origin_id, &self.origin_id)
+            && eq(&rule.destination_id, &self.destination_id)
+    }
+}
+
+pub struct FareService {
+    gtfs: Box<dyn external::gtfs::Gtfs>,
+}
+
+impl FareService {
+    pub fn new(gtfs: Box<dyn external::gtfs::Gtfs>) -> Self {
+        Self { gtfs }
+    }
+
+    /// rideに当てはまる運賃をすべて取得する
+    pub fn fetch_applicable(&mut self, ride: &Ride, query: &Query) -> Result<Vec<FareAttribute>> {
+        let all = Query::default();
+        let rules = self.gtfs.select_fare_rules(&all)?;
+        let fare_ids = applicable_fare_ids(&rules, ride);
+
+        // 件数の条件は運賃を絞り込んでから適用する
+        let condition = Query {
+            condition: query.condition.clone(),
+            ..Query::default()
+        };
+        let fares: Vec<FareAttribute> = self
+            .gtfs
+            .select_fare_attributes(&condition)?
+            .into_iter()
+            .filter(|x| fare_ids.contains(&x.fare_id))
+            .collect();
+        Ok(query.paginate(fares))
+    }
+}
+
+/// contains_idのないルールは1つでも一致すれば適用する.
+/// contains_idのあるルールは, 一致したルールの通過ゾーンが乗車時の通過ゾーンと過不足なく同じ場合に適用する
+fn applicable_fare_ids(rules: &[FareRule], ride: &Ride) -> BTreeSet<FareId> {
+    let ride_zones: BTreeSet<&ZoneId> = ride.contains_ids.iter().collect();
+
+    let mut fare_ids = BTreeSet::new();
+    let mut contains: HashMap<&FareId, BTreeSet<&ZoneId>> = HashMap::new();
+    for rule in rules.iter().filter(|x| ride.matches(x)) {
+        match &rule.contains_id {
+            Some(zone) => {
+                contains.entry(&rule.fare_id).or_default().insert(zone);
+            }
+            None => {
+                fare_ids.insert(rule.fare_id.clone());
+            }
+        }
+    }
+    for (fare_id, zones) in contains {
+        if zones == ride_zones {
+            fare_ids.insert(fare_id.clone());
+        }
+    }
+    fare_ids
+}
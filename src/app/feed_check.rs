@@ -0,0 +1,113 @@
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::external;
+use crate::external::gtfs::agency::Agency;
+use crate::external::gtfs::attributions::Attribution;
+use crate::external::gtfs::calendar::Calendar;
+use crate::external::gtfs::calendar_dates::CalendarDate;
+use crate::external::gtfs::fare_attributes::FareAttribute;
+use crate::external::gtfs::fare_rules::FareRule;
+use crate::external::gtfs::feed_info::FeedInfo;
+use crate::external::gtfs::frequencies::Frequency;
+use crate::external::gtfs::levels::Level;
+use crate::external::gtfs::office_jp::OfficeJp;
+use crate::external::gtfs::pathways::Pathway;
+use crate::external::gtfs::routes::Route;
+use crate::external::gtfs::shapes::Shape;
+use crate::external::gtfs::stop_times::StopTime;
+use crate::external::gtfs::stops::Stop;
+use crate::external::gtfs::transfers::Transfer;
+use crate::external::gtfs::translations::Translation;
+use crate::external::gtfs::trips::Trip;
+use crate::external::gtfs::{Gtfs, Query};
+use crate::external::gtfsdb::Table;
+
+/// 件数を数える際に1度に読み込むレコード数
+const COUNT_BATCH: usize = 50000;
+
+/// ファイルごとの読み込み結果
+#[derive(Debug, Serialize)]
+pub struct FileCheck {
+    file: String,
+    records: Option<usize>,
+    error: Option<String>,
+}
+
+impl FileCheck {
+    pub fn is_ok(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// データベースを作らずにGTFSファイルを読み込めるか確かめるサービス
+pub struct FeedCheckService {
+    gtfs: Box<dyn external::gtfs::Gtfs>,
+}
+
+impl FeedCheckService {
+    pub fn new(gtfs: Box<dyn external::gtfs::Gtfs>) -> Self {
+        Self { gtfs }
+    }
+
+    /// 存在するファイルをすべて読み込み, 件数かパースエラーを返す
+    pub fn check(&mut self) -> Result<Vec<FileCheck>> {
+        let mut checks = vec![];
+        self.check_table::<Agency>(&mut checks, |x| Ok(x.select_agencies(&all())?.len()))?;
+        self.check_table::<Stop>(&mut checks, |x| Ok(x.select_stops(&all())?.len()))?;
+        self.check_table::<Route>(&mut checks, |x| Ok(x.select_routes(&all())?.len()))?;
+        self.check_table::<Trip>(&mut checks, |x| Ok(x.select_trips(&all())?.len()))?;
+        self.check_table::<StopTime>(&mut checks, |x| {
+            x.for_each_stop_times(COUNT_BATCH, &mut |_| Ok(()))
+        })?;
+        self.check_table::<Calendar>(&mut checks, |x| Ok(x.select_calendars(&all())?.len()))?;
+        self.check_table::<CalendarDate>(&mut checks, |x| {
+            Ok(x.select_calendar_dates(&all())?.len())
+        })?;
+        self.check_table::<FareAttribute>(&mut checks, |x| {
+            Ok(x.select_fare_attributes(&all())?.len())
+        })?;
+        self.check_table::<FareRule>(&mut checks, |x| Ok(x.select_fare_rules(&all())?.len()))?;
+        self.check_table::<Shape>(&mut checks, |x| {
+            x.for_each_shapes(COUNT_BATCH, &mut |_| Ok(()))
+        })?;
+        self.check_table::<Frequency>(&mut checks, |x| Ok(x.select_frequencies(&all())?.len()))?;
+        self.check_table::<Transfer>(&mut checks, |x| Ok(x.select_transfers(&all())?.len()))?;
+        self.check_table::<Level>(&mut checks, |x| Ok(x.select_levels(&all())?.len()))?;
+        self.check_table::<Pathway>(&mut checks, |x| Ok(x.select_pathways(&all())?.len()))?;
+        self.check_table::<Attribution>(&mut checks, |x| Ok(x.select_attributions(&all())?.len()))?;
+        self.check_table::<FeedInfo>(&mut checks, |x| Ok(x.select_feed_info()?.map_or(0, |_| 1)))?;
+        self.check_table::<OfficeJp>(&mut checks, |x| Ok(x.select_offices(&all())?.len()))?;
+        self.check_table::<Translation>(&mut checks, |x| Ok(x.select_translations(&all())?.len()))?;
+        Ok(checks)
+    }
+
+    /// ファイルがなければ何もしない. 読み込めなかった場合は次のファイルに進む
+    fn check_table<T: Table>(
+        &mut self,
+        checks: &mut Vec<FileCheck>,
+        count: fn(&mut dyn Gtfs) -> Result<usize>,
+    ) -> Result<()> {
+        if !self.gtfs.exists(T::table_name())? {
+            return Ok(());
+        }
+        let file = format!("{}.txt", T::table_name());
+        checks.push(match count(self.gtfs.as_mut()) {
+            Ok(records) => FileCheck {
+                file,
+                records: Some(records),
+                error: None,
+            },
+            Err(err) => FileCheck {
+                file,
+                records: None,
+                error: Some(format!("{:#}", err)),
+            },
+        });
+        Ok(())
+    }
+}
+
+fn all() -> Query {
+    Query::default()
+}
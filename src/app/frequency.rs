@@ -0,0 +1,32 @@
+use anyhow::Result;
+
+use crate::external::gtfs::frequencies::Frequency;
+use crate::external::gtfs::trips::TripId;
+use crate::external::gtfs::{Gtfs, Query};
+use crate::external::gtfsdb::{GtfsDb, Table};
+
+pub struct FrequencyService {
+    gtfs: GtfsDb,
+}
+
+impl FrequencyService {
+    pub fn new(gtfs: GtfsDb) -> Self {
+        Self { gtfs }
+    }
+
+    pub fn fetch(&mut self, trip_id: Option<&TripId>, query: &Query) -> Result<Vec<Frequency>> {
+        match trip_id {
+            Some(id) => self.gtfs.select_frequencies_by_trip_id(id, query),
+            None => self.gtfs.select_frequencies(query),
+        }
+    }
+
+    /// fetchと同じ条件に当てはまる件数
+    pub fn count(&mut self, trip_id: Option<&TripId>, query: &Query) -> Result<u32> {
+        let query = match trip_id {
+            Some(id) => query.clone().and_eq("trip_id", id),
+            None => query.clone(),
+        };
+        self.gtfs.count_matches(Frequency::table_name(), &query)
+    }
+}
@@ -1,23 +1,138 @@
-use anyhow::Result;
-use log::info;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 
+use anyhow::{bail, Context, Result};
+use indicatif::{ProgressBar, ProgressStyle};
+use log::{info, warn};
+
+use crate::app::validate;
 use crate::external;
-use std::path::PathBuf;
+use crate::external::gtfs::feed_info::FeedInfo;
+use crate::external::gtfs::stops::Stop;
+use crate::external::gtfs::{Gtfs, OnConflict, Query};
+use crate::external::gtfscsv::GtfsCsv;
+use crate::external::gtfsdb;
+use crate::external::gtfsdb::meta::Meta;
+use crate::external::gtfsdb::{GtfsDb, Table};
+use crate::io::InvalidRow;
+
+/// テーブルごとの登録件数
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    counts: Vec<(&'static str, usize)>,
+}
+
+impl ImportReport {
+    fn add(&mut self, table_name: &'static str, count: usize) {
+        self.counts.push((table_name, count));
+    }
+
+    fn count(&self, table_name: &str) -> Option<usize> {
+        self.counts
+            .iter()
+            .find(|(x, _)| *x == table_name)
+            .map(|(_, count)| *count)
+    }
+}
+
+/// ex: agency: 3, stops: 1500, trips: 40000
+impl fmt::Display for ImportReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let counts: Vec<String> = self
+            .counts
+            .iter()
+            .map(|(table, count)| format!("{}: {}", table, count))
+            .collect();
+        write!(f, "{}", counts.join(", "))
+    }
+}
+
+/// 進捗表示する場合に1度に登録するレコード数
+const PROGRESS_CHUNK: usize = 10000;
+
+/// 件数の多いテーブルを読み込みながら登録する場合に1度に登録するレコード数
+const STREAM_BATCH: usize = 50000;
+
+type Stream<T> =
+    fn(&mut dyn external::gtfs::Gtfs, usize, &mut dyn FnMut(&[T]) -> Result<()>) -> Result<usize>;
 
 pub struct GtfsService {
-    gtfs_db: Box<dyn external::gtfs::Gtfs>,
-    gtfs_csv: Box<dyn external::gtfs::Gtfs>,
+    gtfs_db: GtfsDb,
+    gtfs_csv: GtfsCsv,
+    progress: bool,
+    /// テーブル名 → 取り込み元ファイルのハッシュ値 (ファイルがあるものだけ)
+    hashes: HashMap<&'static str, String>,
+    /// 前回から取り込み元ファイルが変わっていないため作り直さないテーブル
+    unchanged: HashSet<&'static str>,
+    /// 取り込むテーブル (Noneなら全テーブル). 選ばれていないテーブルはそのまま残す
+    selected: Option<HashSet<&'static str>>,
+    /// SpatiaLite拡張を読み込めたか
+    spatialite: bool,
 }
 
 /// GTFS全体を横断するアプリケーションサービス
 impl GtfsService {
-    pub fn new(
-        gtfs_db: Box<dyn external::gtfs::Gtfs>,
-        gtfs_csv: Box<dyn external::gtfs::Gtfs>,
-    ) -> Self {
-        Self { gtfs_db, gtfs_csv }
+    pub fn new(gtfs_db: GtfsDb, gtfs_csv: GtfsCsv) -> Self {
+        Self {
+            gtfs_db,
+            gtfs_csv,
+            progress: false,
+            hashes: HashMap::new(),
+            unchanged: HashSet::new(),
+            selected: None,
+            spatialite: false,
+        }
     }
 
+    /// 登録中に進捗バーを表示する
+    pub fn set_progress(&mut self, enabled: bool) {
+        self.progress = enabled;
+    }
+
+    /// 取り込むテーブルを限定する. 参照先のテーブルを含まない場合は警告する
+    pub fn select_tables(&mut self, table_names: &[String]) -> Result<()> {
+        let schema = gtfsdb::schema();
+        let mut selected = HashSet::new();
+        for name in table_names {
+            match schema.iter().find(|(x, _)| x == name) {
+                Some((x, _)) => selected.insert(*x),
+                None => bail!(
+                    "テーブルが見つかりません: {} (指定できるテーブル: {})",
+                    name,
+                    schema
+                        .iter()
+                        .map(|(x, _)| *x)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+            };
+        }
+        for (table_name, _) in &schema {
+            if !selected.contains(table_name) {
+                continue;
+            }
+            for (column, parents) in validate::referenced_tables(table_name) {
+                if !parents.iter().any(|x| selected.contains(x)) {
+                    warn!(
+                        "[{}] {}の参照先 ({}) を取り込みません. データベースになければ参照先のないレコードになります",
+                        table_name,
+                        column,
+                        parents.join(", ")
+                    );
+                }
+            }
+        }
+        self.selected = Some(selected);
+        Ok(())
+    }
+
+    fn is_selected(&self, table_name: &str) -> bool {
+        self.selected
+            .as_ref()
+            .is_none_or(|x| x.contains(table_name))
+    }
+
+    /// 選ばれていないテーブルの参照先になることがあるため, 全テーブルを (なければ) 作成する
     pub fn create_tables(&mut self) -> Result<()> {
         info!("ℹ️ Create all tables.");
         self.gtfs_db.create_all()?;
@@ -25,120 +140,417 @@ impl GtfsService {
         Ok(())
     }
 
-    pub fn insert_tables(&mut self, gtfs_dir: &PathBuf) -> Result<()> {
-        let agencies = self.gtfs_csv.select_agencies()?;
-        info!("ℹ️ [agencies] {} records", agencies.len());
-        self.gtfs_db.insert_agencies(&agencies)?;
-        info!("  ✨ Success");
+    pub fn insert_tables(&mut self) -> Result<ImportReport> {
+        let all = Query::default();
+        let mut report = ImportReport::default();
 
-        let stops = self.gtfs_csv.select_stops()?;
-        info!("ℹ️ [stops] {} records", stops.len());
-        self.gtfs_db.insert_stops(&stops)?;
-        info!("  ✨ Success");
+        if self.wants("agency")? {
+            let agencies = self.gtfs_csv.select_agencies(&all)?;
+            self.insert(&mut report, &agencies, |db, x| db.insert_agencies(x))?;
+        }
 
-        let routes = self.gtfs_csv.select_routes()?;
-        info!("ℹ️ [routes] {} records", routes.len());
-        self.gtfs_db.insert_routes(&routes)?;
-        info!("  ✨ Success");
+        if self.wants("stops")? {
+            let mut stops = self.gtfs_csv.select_stops(&all)?;
+            stops.sort_by_key(Stop::depth);
+            self.insert(&mut report, &stops, |db, x| db.insert_stops(x))?;
+        }
 
-        let trips = self.gtfs_csv.select_trips()?;
-        info!("ℹ️ [trips] {} records", trips.len());
-        self.gtfs_db.insert_trips(&trips)?;
-        info!("  ✨ Success");
+        if self.wants("routes")? {
+            let routes = self.gtfs_csv.select_routes(&all)?;
+            self.insert(&mut report, &routes, |db, x| db.insert_routes(x))?;
+        }
 
-        let stop_times = self.gtfs_csv.select_stop_times()?;
-        info!("ℹ️ [stop_times] {} records", stop_times.len());
-        self.gtfs_db.insert_stop_times(&stop_times)?;
-        info!("  ✨ Success");
+        if self.wants("trips")? {
+            let trips = self.gtfs_csv.select_trips(&all)?;
+            self.insert(&mut report, &trips, |db, x| db.insert_trips(x))?;
+        }
 
-        // TODO: 判定処理をgtfsのIFで
-        let path = gtfs_dir.join("calendar.txt");
-        if path.exists() {
-            let calendars = self.gtfs_csv.select_calendars()?;
-            info!("ℹ️ [calendar] {} records", calendars.len());
-            self.gtfs_db.insert_calendars(&calendars)?;
-            info!("  ✨ Success");
+        if self.wants("stop_times")? {
+            self.insert_stream(
+                &mut report,
+                |csv, size, each| csv.for_each_stop_times(size, each),
+                |db, x| db.insert_stop_times(x),
+            )?;
         }
 
-        // TODO: 判定処理をgtfsのIFで
-        let path = gtfs_dir.join("calendar_dates.txt");
-        if path.exists() {
-            let calendar_dates = self.gtfs_csv.select_calendar_dates()?;
-            info!("ℹ️ [calendar_dates] {} records", calendar_dates.len());
-            self.gtfs_db.insert_calendar_dates(&calendar_dates)?;
-            info!("  ✨ Success");
+        if self.wants("calendar")? {
+            let calendars = self.gtfs_csv.select_calendars(&all)?;
+            self.insert(&mut report, &calendars, |db, x| db.insert_calendars(x))?;
         }
 
-        // TODO: 判定処理をgtfsのIFで
-        let path = gtfs_dir.join("fare_attributes.txt");
-        if path.exists() {
-            let fare_attributes = self.gtfs_csv.select_fare_attributes()?;
-            info!("ℹ️ [fare_attributes] {} records", fare_attributes.len());
-            self.gtfs_db.insert_fare_attributes(&fare_attributes)?;
-            info!("  ✨ Success");
+        if self.wants("calendar_dates")? {
+            let calendar_dates = self.gtfs_csv.select_calendar_dates(&all)?;
+            self.insert(&mut report, &calendar_dates, |db, x| {
+                db.insert_calendar_dates(x)
+            })?;
         }
 
-        // TODO: 判定処理をgtfsのIFで
-        let path = gtfs_dir.join("fare_rules.txt");
-        if path.exists() {
-            let fare_rules = self.gtfs_csv.select_fare_rules()?;
-            info!("ℹ️ [fare_rules] {} records", fare_rules.len());
-            self.gtfs_db.insert_fare_rules(&fare_rules)?;
-            info!("  ✨ Success");
+        if self.wants("fare_attributes")? {
+            let fare_attributes = self.gtfs_csv.select_fare_attributes(&all)?;
+            self.insert(&mut report, &fare_attributes, |db, x| {
+                db.insert_fare_attributes(x)
+            })?;
         }
 
-        // TODO: 判定処理をgtfsのIFで
-        let path = gtfs_dir.join("shapes.txt");
-        if path.exists() {
-            let shapes = self.gtfs_csv.select_shapes()?;
-            info!("ℹ️ [shapes] {} records", shapes.len());
-            self.gtfs_db.insert_shapes(&shapes)?;
-            info!("  ✨ Success");
+        if self.wants("fare_rules")? {
+            let fare_rules = self.gtfs_csv.select_fare_rules(&all)?;
+            self.insert(&mut report, &fare_rules, |db, x| db.insert_fare_rules(x))?;
         }
 
-        // TODO: 判定処理をgtfsのIFで
-        let path = gtfs_dir.join("frequencies.txt");
-        if path.exists() {
-            let frequencies = self.gtfs_csv.select_frequencies()?;
-            info!("ℹ️ [frequencies] {} records", frequencies.len());
-            self.gtfs_db.insert_frequencies(&frequencies)?;
-            info!("  ✨ Success");
+        if self.wants("shapes")? {
+            self.insert_stream(
+                &mut report,
+                |csv, size, each| csv.for_each_shapes(size, each),
+                |db, x| db.insert_shapes(x),
+            )?;
         }
 
-        // TODO: 判定処理をgtfsのIFで
-        let path = gtfs_dir.join("transfers.txt");
-        if path.exists() {
-            let transfers = self.gtfs_csv.select_transfers()?;
-            info!("ℹ️ [transfers] {} records", transfers.len());
-            self.gtfs_db.insert_transfers(&transfers)?;
-            info!("  ✨ Success");
+        if self.wants("frequencies")? {
+            let frequencies = self.gtfs_csv.select_frequencies(&all)?;
+            self.insert(&mut report, &frequencies, |db, x| db.insert_frequencies(x))?;
+        }
+
+        if self.wants("transfers")? {
+            let transfers = self.gtfs_csv.select_transfers(&all)?;
+            self.insert(&mut report, &transfers, |db, x| db.insert_transfers(x))?;
+        }
+
+        if self.wants("levels")? {
+            let levels = self.gtfs_csv.select_levels(&all)?;
+            self.insert(&mut report, &levels, |db, x| db.insert_levels(x))?;
+        }
+
+        if self.wants("pathways")? {
+            let pathways = self.gtfs_csv.select_pathways(&all)?;
+            self.insert(&mut report, &pathways, |db, x| db.insert_pathways(x))?;
+        }
+
+        if self.wants("attributions")? {
+            let attributions = self.gtfs_csv.select_attributions(&all)?;
+            self.insert(&mut report, &attributions, |db, x| {
+                db.insert_attributions(x)
+            })?;
+        }
+
+        if self.wants("feed_info")? {
+            if let Some(feed_info) = self.gtfs_csv.select_feed_info()? {
+                info!("ℹ️ [feed_info] 1 records");
+                self.gtfs_db.insert_feed_info(&feed_info)?;
+                info!("  ✨ Success");
+                report.add(FeedInfo::table_name(), 1);
+            }
+        }
+
+        if self.wants("office_jp")? {
+            let offices = self.gtfs_csv.select_offices(&all)?;
+            self.insert(&mut report, &offices, |db, x| db.insert_offices(x))?;
+        }
+
+        if self.wants("translations")? {
+            let translations = self.gtfs_csv.select_translations(&all)?;
+            self.insert(&mut report, &translations, |db, x| {
+                db.insert_translations(x)
+            })?;
+        }
+
+        Ok(report)
+    }
+
+    /// ファイルがあり, 変更がないため取り込みを省くテーブルでなければ取り込む
+    fn wants(&self, table_name: &str) -> Result<bool> {
+        if !self.is_selected(table_name) {
+            return Ok(false);
+        }
+        if !self.gtfs_csv.exists(table_name)? {
+            return Ok(false);
+        }
+        if self.unchanged.contains(table_name) {
+            info!("ℹ️ [{}] Skip unchanged file", table_name);
+            return Ok(false);
         }
+        Ok(true)
+    }
+
+    /// 取り込み元ファイルのハッシュ値を求める. 取り込み後にsave_metaで記録する
+    pub fn hash_files(&mut self) -> Result<()> {
+        info!("ℹ️ Hash source files.");
+        for (table_name, _) in gtfsdb::schema() {
+            if self.gtfs_csv.exists(table_name)? {
+                let hash = self.gtfs_csv.file_hash(table_name)?;
+                self.hashes.insert(table_name, hash);
+            }
+        }
+        info!("  ✨ Success");
+        Ok(())
+    }
+
+    /// 前回の取り込みからファイルが変わっていないテーブルはdrop_tables, insert_tablesの対象から外す
+    /// 作り直すテーブルを参照するテーブルも, 外部キーを保つため作り直す
+    pub fn skip_unchanged_tables(&mut self) -> Result<()> {
+        let previous: HashMap<String, String> = self
+            .gtfs_db
+            .select_meta()?
+            .into_iter()
+            .map(|x| (x.table_name, x.file_hash))
+            .collect();
+
+        let schema = gtfsdb::schema();
+        let mut changed: HashSet<&'static str> = schema
+            .iter()
+            .map(|(table_name, _)| *table_name)
+            .filter(|x| self.hashes.get(x) != previous.get(*x))
+            .collect();
+        // 作成順に並んでいるため, 参照先は参照元より先に判定される
+        for (table_name, ddl) in &schema {
+            if referenced_tables(table_name, ddl)
+                .iter()
+                .any(|x| changed.contains(x))
+            {
+                changed.insert(table_name);
+            }
+        }
+
+        self.unchanged = schema
+            .iter()
+            .map(|(table_name, _)| *table_name)
+            .filter(|x| !changed.contains(x))
+            .collect();
+        Ok(())
+    }
+
+    /// 作り直すテーブルの記録を消す. 取り込みが途中で失敗しても次回に変更なしと判定されないようにする
+    pub fn invalidate_meta(&mut self) -> Result<()> {
+        let meta: Vec<Meta> = self
+            .gtfs_db
+            .select_meta()?
+            .into_iter()
+            .filter(|x| {
+                self.unchanged.contains(x.table_name.as_str()) || !self.is_selected(&x.table_name)
+            })
+            .collect();
+        self.gtfs_db.replace_meta(&meta)
+    }
+
+    /// 取り込んだファイルのハッシュ値と件数を記録する. hash_filesを呼んでいなければ記録を消す
+    /// 取り込むテーブルを限定した場合, 選ばれていないテーブルは前回の記録を残す
+    pub fn save_meta(&mut self, report: &ImportReport) -> Result<()> {
+        let mut previous: HashMap<String, Meta> = self
+            .gtfs_db
+            .select_meta()?
+            .into_iter()
+            .map(|x| (x.table_name.clone(), x))
+            .collect();
+        let meta: Vec<Meta> = gtfsdb::schema()
+            .into_iter()
+            .filter_map(|(table_name, _)| {
+                if !self.is_selected(table_name) {
+                    return previous.remove(table_name);
+                }
+                let hash = self.hashes.get(table_name)?;
+                Some(Meta {
+                    table_name: table_name.to_string(),
+                    file_hash: hash.clone(),
+                    records: report
+                        .count(table_name)
+                        .map(|x| x as u32)
+                        .or_else(|| previous.get(table_name).map(|x| x.records))
+                        .unwrap_or(0),
+                })
+            })
+            .collect();
+        self.gtfs_db.replace_meta(&meta)
+    }
 
-        // TODO: 判定処理をgtfsのIFで
-        let path = gtfs_dir.join("feed_info.txt");
-        if path.exists() {
-            let feeds = self.gtfs_csv.select_feeds()?;
-            info!("ℹ️ [feed_info] {} records", feeds.len());
-            self.gtfs_db.insert_feeds(&feeds)?;
+    /// 進捗表示が有効ならPROGRESS_CHUNK件ずつ分けて登録し, その都度バーを進める
+    fn insert<T>(
+        &mut self,
+        report: &mut ImportReport,
+        records: &[T],
+        insert: fn(&mut dyn external::gtfs::Gtfs, &[T]) -> Result<()>,
+    ) -> Result<()>
+    where
+        T: Table,
+    {
+        let table_name = T::table_name();
+        report.add(table_name, records.len());
+        info!("ℹ️ [{}] {} records", table_name, records.len());
+        if !self.progress {
+            insert(&mut self.gtfs_db, records)?;
             info!("  ✨ Success");
+            return Ok(());
+        }
+
+        let bar = ProgressBar::new(records.len() as u64);
+        bar.set_style(
+            ProgressStyle::default_bar()
+                .template("  {msg} [{bar:40}] {pos}/{len} ({eta})")
+                .progress_chars("=> "),
+        );
+        bar.set_message(table_name);
+        for (i, chunk) in records.chunks(PROGRESS_CHUNK).enumerate() {
+            insert(&mut self.gtfs_db, chunk).with_context(|| {
+                format!(
+                    "[{}] {}件目以降の登録に失敗しました",
+                    table_name,
+                    i * PROGRESS_CHUNK + 1
+                )
+            })?;
+            bar.inc(chunk.len() as u64);
         }
+        bar.finish_and_clear();
+        info!("  ✨ Success");
+        Ok(())
+    }
+
+    /// 全件をメモリに載せず, STREAM_BATCH件読み込むごとに登録する
+    fn insert_stream<T>(
+        &mut self,
+        report: &mut ImportReport,
+        stream: Stream<T>,
+        insert: fn(&mut dyn external::gtfs::Gtfs, &[T]) -> Result<()>,
+    ) -> Result<()>
+    where
+        T: Table,
+    {
+        let table_name = T::table_name();
+        info!("ℹ️ [{}] {} records per batch", table_name, STREAM_BATCH);
+
+        // 総件数は読み終えるまで分からないため, 登録済みの件数だけを表示する
+        let bar = if self.progress {
+            let bar = ProgressBar::new_spinner();
+            bar.set_style(
+                ProgressStyle::default_spinner().template("  {msg} {spinner} {pos} ({elapsed})"),
+            );
+            bar.set_message(table_name);
+            Some(bar)
+        } else {
+            None
+        };
+
+        let gtfs_db = &mut self.gtfs_db;
+        let mut inserted = 0;
+        let count = stream(&mut self.gtfs_csv, STREAM_BATCH, &mut |batch| {
+            insert(gtfs_db, batch).with_context(|| {
+                format!(
+                    "[{}] {}件目以降の登録に失敗しました",
+                    table_name,
+                    inserted + 1
+                )
+            })?;
+            inserted += batch.len();
+            if let Some(bar) = &bar {
+                bar.inc(batch.len() as u64);
+            }
+            Ok(())
+        })?;
+        if let Some(bar) = bar {
+            bar.finish_and_clear();
+        }
+
+        report.add(table_name, count);
+        info!("  ✨ Success ({} records)", count);
+        Ok(())
+    }
+
+    pub fn create_indexes(&mut self) -> Result<()> {
+        info!("ℹ️ Create indexes.");
+        self.gtfs_db.index_all()?;
+        info!("  ✨ Success");
+        Ok(())
+    }
 
-        // TODO: 判定処理をgtfsのIFで
-        let path = gtfs_dir.join("translations.txt");
-        if path.exists() {
-            let translations = self.gtfs_csv.select_translations()?;
-            info!("ℹ️ [translations] {} records", translations.len());
-            self.gtfs_db.insert_translations(&translations)?;
+    /// SpatiaLite拡張を読み込む. 見つからなければ警告し, geometryカラムを作らずに続ける
+    pub fn enable_spatialite(&mut self) -> Result<()> {
+        info!("ℹ️ Load SpatiaLite extension.");
+        self.spatialite = self.gtfs_db.load_spatialite()?;
+        if self.spatialite {
             info!("  ✨ Success");
+        } else {
+            warn!("SpatiaLite拡張 (mod_spatialite) が見つからないため, stopsにgeometryカラムを作りません");
         }
+        Ok(())
+    }
 
+    /// SpatiaLite拡張を読み込めた場合のみ, stopsにgeometryカラムと空間インデックスを作る
+    pub fn create_stop_geometries(&mut self) -> Result<()> {
+        if !self.spatialite {
+            return Ok(());
+        }
+        info!("ℹ️ Create stop geometries.");
+        self.gtfs_db.create_stop_geometries()?;
+        info!("  ✨ Success");
+        Ok(())
+    }
+
+    pub fn set_fast_import(&mut self, enabled: bool) -> Result<()> {
+        if enabled {
+            info!("ℹ️ Enable fast import mode.");
+        } else {
+            info!("ℹ️ Disable fast import mode.");
+        }
+        self.gtfs_db.set_fast_import(enabled)?;
+        info!("  ✨ Success");
+        Ok(())
+    }
+
+    /// 外部キーを検査して参照先のないレコードを登録できなくする
+    pub fn set_enforce_fk(&mut self, enabled: bool) -> Result<()> {
+        if enabled {
+            info!("ℹ️ Enable foreign key enforcement.");
+        } else {
+            info!("ℹ️ Disable foreign key enforcement.");
+        }
+        self.gtfs_db.set_enforce_fk(enabled)?;
+        info!("  ✨ Success");
         Ok(())
     }
 
+    /// 有効にするとパースできない行を読み飛ばして登録を続ける
+    pub fn set_skip_invalid(&mut self, enabled: bool) {
+        self.gtfs_csv.set_skip_invalid(enabled);
+    }
+
+    /// 登録中に読み飛ばした行
+    pub fn take_invalid_rows(&mut self) -> Vec<InvalidRow> {
+        self.gtfs_csv.take_invalid_rows()
+    }
+
+    pub fn set_on_conflict(&mut self, on_conflict: OnConflict) {
+        self.gtfs_db.set_on_conflict(on_conflict);
+    }
+
+    /// 1つのINSERT文にまとめる行数
+    pub fn set_batch_size(&mut self, rows: usize) -> Result<()> {
+        self.gtfs_db.set_batch_size(rows)
+    }
+
+    /// skip_unchanged_tablesで外したテーブルは残す
     pub fn drop_tables(&mut self) -> Result<()> {
-        info!("ℹ️ Drop all tables.");
-        self.gtfs_db.drop_all()?;
+        if self.unchanged.is_empty() && self.selected.is_none() {
+            info!("ℹ️ Drop all tables.");
+            self.gtfs_db.drop_all()?;
+            info!("  ✨ Success");
+            return Ok(());
+        }
+
+        info!("ℹ️ Drop changed tables.");
+        // 外部キーで参照する側から削除する
+        for (table_name, _) in gtfsdb::schema().iter().rev() {
+            if !self.unchanged.contains(table_name) && self.is_selected(table_name) {
+                self.gtfs_db.drop_table(table_name)?;
+            }
+        }
         info!("  ✨ Success");
         Ok(())
     }
 }
+
+/// DDLのREFERENCES句と--enforce-fkで加える外部キーで参照するテーブル
+fn referenced_tables<'a>(table_name: &str, ddl: &'a str) -> Vec<&'a str> {
+    ddl.split("REFERENCES ")
+        .skip(1)
+        .filter_map(|x| x.split('(').next())
+        .map(str::trim)
+        .chain(gtfsdb::foreign_key_tables(table_name))
+        .collect()
+}
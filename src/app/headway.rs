@@ -0,0 +1,155 @@
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::Result;
+use chrono::{Duration, NaiveDate};
+use serde::Serialize;
+
+use crate::app::service_calendar::ServiceCalendar;
+use crate::external;
+use crate::external::gtfs::routes::RouteId;
+use crate::external::gtfs::stops::StopId;
+use crate::external::gtfs::trips::TripId;
+use crate::external::gtfs::{GtfsTime, Query, Second};
+
+/// 1日の秒数. これ以上の時刻は翌日の運行になる
+const DAY: Second = 24 * 60 * 60;
+
+/// 経路ごとの運行間隔 (分)
+#[derive(Debug, Serialize)]
+pub struct Headway {
+    route_id: RouteId,
+    stop_id: StopId,
+    date: String,
+    min_headway: Option<f64>,
+    median_headway: Option<f64>,
+    max_headway: Option<f64>,
+    departures: Vec<Departure>,
+}
+
+/// 1便分の発車
+#[derive(Debug, Serialize)]
+pub struct Departure {
+    trip_id: TripId,
+    /// dateの0時を起点にした時刻 (前日の運行日の便は24時間戻す)
+    departure_time: GtfsTime,
+    /// 直前の発車からの間隔 (分)
+    headway: Option<f64>,
+}
+
+pub struct HeadwayService {
+    gtfs: Box<dyn external::gtfs::Gtfs>,
+}
+
+impl HeadwayService {
+    pub fn new(gtfs: Box<dyn external::gtfs::Gtfs>) -> Self {
+        Self { gtfs }
+    }
+
+    /// dateの0時から24時までのstop_idでの発車を経路ごとにまとめる (route_idがNoneなら全経路)
+    /// 24時を過ぎてから発車する便は, 前日の運行日のものをdateの発車として数える
+    pub fn compute(
+        &mut self,
+        stop_id: &StopId,
+        route_id: Option<&RouteId>,
+        date: NaiveDate,
+    ) -> Result<Vec<Headway>> {
+        let calendar = ServiceCalendar::load(self.gtfs.as_mut())?;
+        let today = self.trip_routes(&calendar, date, route_id)?;
+        let yesterday = self.trip_routes(&calendar, date - Duration::days(1), route_id)?;
+
+        let mut by_route: BTreeMap<&RouteId, Vec<(Second, &TripId)>> = BTreeMap::new();
+        let stop_times = self
+            .gtfs
+            .select_stop_times_by_stop_id(stop_id, &Query::default())?;
+        for stop_time in &stop_times {
+            // 時刻を省略した標柱は発車時刻が分からないため数えない
+            let seconds = match stop_time.departure_time.or(stop_time.arrival_time) {
+                Some(x) => x.seconds(),
+                None => continue,
+            };
+            let (trips, seconds) = if seconds < DAY {
+                (&today, seconds)
+            } else {
+                (&yesterday, seconds - DAY)
+            };
+            if let Some(route) = trips.get(&stop_time.trip_id) {
+                by_route
+                    .entry(route)
+                    .or_default()
+                    .push((seconds, &stop_time.trip_id));
+            }
+        }
+
+        Ok(by_route
+            .into_iter()
+            .map(|(route, mut times)| {
+                times.sort();
+                to_headway(route, stop_id, date, &times)
+            })
+            .collect())
+    }
+
+    /// dateに運行する便の便ID → 経路ID
+    fn trip_routes(
+        &mut self,
+        calendar: &ServiceCalendar,
+        date: NaiveDate,
+        route_id: Option<&RouteId>,
+    ) -> Result<HashMap<TripId, RouteId>> {
+        let service_ids = calendar.active_service_ids(date);
+        Ok(self
+            .gtfs
+            .select_trips_by_service_ids(&service_ids, &Query::default())?
+            .into_iter()
+            .filter(|x| route_id.is_none_or(|id| *id == x.route_id))
+            .map(|x| (x.trip_id, x.route_id))
+            .collect())
+    }
+}
+
+/// timesは時刻順に並んでいること
+fn to_headway(
+    route_id: &RouteId,
+    stop_id: &StopId,
+    date: NaiveDate,
+    times: &[(Second, &TripId)],
+) -> Headway {
+    let departures: Vec<Departure> = times
+        .iter()
+        .enumerate()
+        .map(|(i, (seconds, trip_id))| Departure {
+            trip_id: trip_id.to_string(),
+            departure_time: GtfsTime::from_seconds(*seconds),
+            headway: i
+                .checked_sub(1)
+                .map(|prev| minutes(seconds - times[prev].0)),
+        })
+        .collect();
+
+    let mut headways: Vec<f64> = departures.iter().filter_map(|x| x.headway).collect();
+    headways.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+    Headway {
+        route_id: route_id.clone(),
+        stop_id: stop_id.clone(),
+        date: date.format("%Y%m%d").to_string(),
+        min_headway: headways.first().copied(),
+        median_headway: median(&headways),
+        max_headway: headways.last().copied(),
+        departures,
+    }
+}
+
+fn minutes(seconds: Second) -> f64 {
+    f64::from(seconds) / 60.0
+}
+
+/// sortedは昇順に並んでいること
+fn median(sorted: &[f64]) -> Option<f64> {
+    let n = sorted.len();
+    match n {
+        0 => None,
+        _ if n % 2 == 1 => Some(sorted[n / 2]),
+        _ => Some((sorted[n / 2 - 1] + sorted[n / 2]) / 2.0),
+    }
+}
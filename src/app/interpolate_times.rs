@@ -0,0 +1,94 @@
+use anyhow::Result;
+use log::info;
+
+use crate::external::gtfs::stop_times::StopTime;
+use crate::external::gtfs::{Gtfs, GtfsTime};
+use crate::external::gtfsdb::{GtfsDb, Table};
+
+/// stop_timesを読み込み・更新する件数の単位
+const STOP_TIMES_BATCH: usize = 50000;
+
+pub struct InterpolateTimesService {
+    gtfs: GtfsDb,
+}
+
+/// stop_timesの空欄の到着・出発時刻を補完するアプリケーションサービス
+/// 時刻が入っているレコードはそのままにする
+impl InterpolateTimesService {
+    pub fn new(gtfs: GtfsDb) -> Self {
+        Self { gtfs }
+    }
+
+    pub fn compute(&mut self) -> Result<()> {
+        info!("ℹ️ Interpolate arrival_time and departure_time of stop_times.");
+        // 便ID, 通過順位の順に渡されるため, 便が変わった時点で前の便の停車時刻がそろう
+        let mut updated = Vec::new();
+        let mut trip: Vec<StopTime> = Vec::new();
+        self.gtfs
+            .for_each_stop_times_by_trip(STOP_TIMES_BATCH, &mut |batch| {
+                for stop_time in batch {
+                    if trip.last().is_some_and(|x| x.trip_id != stop_time.trip_id) {
+                        updated.extend(interpolate(&mut trip));
+                        trip.clear();
+                    }
+                    trip.push(stop_time.clone());
+                }
+                Ok(())
+            })?;
+        updated.extend(interpolate(&mut trip));
+        // 読み込み中は更新できないため, 補完したレコードだけを残して後から書き込む
+        for chunk in updated.chunks(STOP_TIMES_BATCH) {
+            self.gtfs.update_stop_times_times(chunk)?;
+        }
+        self.gtfs.invalidate_meta(&[StopTime::table_name()])?;
+        info!("  ✨ Success ({} records)", updated.len());
+        Ok(())
+    }
+}
+
+/// 通過順に並んだ停車時刻のうち, 到着・出発時刻がともに空欄のものを
+/// 前後の時刻が入っている停車時刻の間で線形補間し, 更新した停車時刻を返す
+/// 前後どちらかに時刻が入っている停車時刻がなければ補間しない
+fn interpolate(stop_times: &mut [StopTime]) -> Vec<StopTime> {
+    // (位置, 到着時刻, 出発時刻). 片方だけ入っている場合はもう片方と同じとみなす
+    let timed: Vec<(usize, GtfsTime, GtfsTime)> = stop_times
+        .iter()
+        .enumerate()
+        .filter_map(|(i, x)| match (x.arrival_time, x.departure_time) {
+            (Some(a), Some(d)) => Some((i, a, d)),
+            (Some(t), None) | (None, Some(t)) => Some((i, t, t)),
+            (None, None) => None,
+        })
+        .collect();
+
+    let mut updated = Vec::new();
+    for pair in timed.windows(2) {
+        let ((from, _, start), (to, end, _)) = (pair[0], pair[1]);
+        let (start, end) = (f64::from(start.seconds()), f64::from(end.seconds()));
+        for i in from + 1..to {
+            let ratio = progress(&stop_times[from], &stop_times[i], &stop_times[to]);
+            let time = GtfsTime::from_seconds((start + ratio * (end - start)).round() as u32);
+            stop_times[i].arrival_time = Some(time);
+            stop_times[i].departure_time = Some(time);
+            updated.push(stop_times[i].clone());
+        }
+    }
+    updated
+}
+
+/// fromからtoまでのうちcurrentまで進んだ割合
+/// 3つともshape_dist_traveledが入っていれば距離, そうでなければ通過順位で求める
+fn progress(from: &StopTime, current: &StopTime, to: &StopTime) -> f64 {
+    let (a, x, b) = match (
+        from.shape_dist_traveled,
+        current.shape_dist_traveled,
+        to.shape_dist_traveled,
+    ) {
+        (Some(a), Some(x), Some(b)) => (a, x, b),
+        _ => (from.stop_sequence, current.stop_sequence, to.stop_sequence),
+    };
+    if b <= a {
+        return 0.0;
+    }
+    (f64::from(x.saturating_sub(a)) / f64::from(b - a)).min(1.0)
+}
@@ -0,0 +1,118 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use log::{info, warn};
+use serde::Serialize;
+
+use crate::external::gtfs::Gtfs;
+use crate::external::gtfsdb::GtfsDb;
+
+/// 名前空間を付けるIDのカラム (テーブルを作成する順)
+/// 参照する側も同じ名前空間を付けるため, 参照関係はfeedの中で保たれる
+const NAMESPACED_COLUMNS: &[(&str, &[&str])] = &[
+    ("agency", &["agency_id"]),
+    ("office_jp", &["office_id"]),
+    ("routes", &["route_id", "agency_id", "jp_parent_route_id"]),
+    ("stops", &["stop_id", "zone_id", "parent_station"]),
+    (
+        "trips",
+        &[
+            "route_id",
+            "service_id",
+            "trip_id",
+            "block_id",
+            "shape_id",
+            "jp_office_id",
+        ],
+    ),
+    ("stop_times", &["trip_id", "stop_id"]),
+    ("calendar", &["service_id"]),
+    ("calendar_dates", &["service_id"]),
+    ("fare_attributes", &["fare_id"]),
+    (
+        "fare_rules",
+        &[
+            "fare_id",
+            "route_id",
+            "origin_id",
+            "destination_id",
+            "contains_id",
+        ],
+    ),
+    ("shapes", &["shape_id"]),
+    ("frequencies", &["trip_id"]),
+    ("transfers", &["from_stop_id", "to_stop_id"]),
+    ("levels", &["level_id"]),
+    ("pathways", &["pathway_id", "from_stop_id", "to_stop_id"]),
+    (
+        "attributions",
+        &["attribution_id", "agency_id", "route_id", "trip_id"],
+    ),
+    ("feed_info", &[]),
+    // record_idは翻訳するレコードのIDを指す
+    ("translations", &["record_id"]),
+];
+
+/// 名前空間とIDの区切り (ex: feed1:agency_id)
+const SEPARATOR: &str = ":";
+
+/// 合成するfeed
+pub struct Feed {
+    pub namespace: String,
+    pub database: PathBuf,
+}
+
+/// 主キーが重複して追加できなかったレコード
+/// 名前空間を付けないカラムが主キーのテーブル (feed_infoなど) で起こる
+#[derive(Debug, Serialize)]
+pub struct Collision {
+    pub namespace: String,
+    pub table: &'static str,
+    pub count: u32,
+}
+
+/// 複数のデータベースを1つに合成するアプリケーションサービス
+pub struct MergeService {
+    target: GtfsDb,
+}
+
+impl MergeService {
+    pub fn new(target: GtfsDb) -> Self {
+        Self { target }
+    }
+
+    /// 各feedのIDに名前空間を付けて追加する. 戻り値は追加できなかったレコード
+    pub fn merge(mut self, feeds: &[Feed]) -> Result<Vec<Collision>> {
+        info!("ℹ️ Create all tables.");
+        self.target.create_all()?;
+        info!("  ✨ Success");
+
+        let mut collisions = Vec::new();
+        for feed in feeds {
+            let prefix = format!("{}{}", feed.namespace, SEPARATOR);
+            for (table, columns) in NAMESPACED_COLUMNS {
+                info!("ℹ️ [{}] Merge {:?}", table, feed.database);
+                let (inserted, ignored) =
+                    self.target
+                        .merge_table(&feed.database, table, columns, &prefix)?;
+                info!("  ✨ Success ({} records)", inserted);
+                if ignored > 0 {
+                    warn!(
+                        "[{}] {} の{}件が既存のレコードと重複したため追加されませんでした",
+                        table, feed.namespace, ignored
+                    );
+                    collisions.push(Collision {
+                        namespace: feed.namespace.clone(),
+                        table,
+                        count: ignored,
+                    });
+                }
+            }
+        }
+
+        info!("ℹ️ Create indexes.");
+        self.target.index_all()?;
+        info!("  ✨ Success");
+        Ok(collisions)
+    }
+}
@@ -0,0 +1,35 @@
+use anyhow::Result;
+
+use crate::external::gtfs::office_jp::{JpOfficeId, OfficeJp};
+use crate::external::gtfs::{Gtfs, Query};
+use crate::external::gtfsdb::{GtfsDb, Table};
+
+pub struct OfficeService {
+    gtfs: GtfsDb,
+}
+
+impl OfficeService {
+    pub fn new(gtfs: GtfsDb) -> Self {
+        Self { gtfs }
+    }
+
+    pub fn fetch(
+        &mut self,
+        office_id: Option<&JpOfficeId>,
+        query: &Query,
+    ) -> Result<Vec<OfficeJp>> {
+        match office_id {
+            Some(id) => self.gtfs.select_offices_by_office_id(id, query),
+            None => self.gtfs.select_offices(query),
+        }
+    }
+
+    /// fetchと同じ条件に当てはまる件数
+    pub fn count(&mut self, office_id: Option<&JpOfficeId>, query: &Query) -> Result<u32> {
+        let query = match office_id {
+            Some(id) => query.clone().and_eq("office_id", id),
+            None => query.clone(),
+        };
+        self.gtfs.count_matches(OfficeJp::table_name(), &query)
+    }
+}
@@ -1,18 +1,149 @@
+use std::collections::HashMap;
+
 use anyhow::Result;
+use log::warn;
+use serde::Serialize;
+
+use crate::external::gtfs::agency::AgencyId;
+use crate::external::gtfs::routes::{Route, RouteId};
+use crate::external::gtfs::shapes::ShapeId;
+use crate::external::gtfs::trips::Direction;
+use crate::external::gtfs::{Gtfs, Query};
+use crate::external::gtfsdb::{GtfsDb, Table};
+use crate::io::geojson::{position, Geometry, Position, ToGeoFeature};
+
+/// 経路の上下区分ごとの線形. 便数が最も多い描画IDを代表とする
+#[derive(Debug, Serialize)]
+pub struct RouteLine {
+    #[serde(flatten)]
+    route: ColoredRoute,
+    /// 上下区分
+    direction_id: Option<Direction>,
+    /// 代表とした描画ID
+    shape_id: ShapeId,
+    #[serde(skip)]
+    coordinates: Vec<Position>,
+}
+
+/// 描画に使う色を加えた経路. json, yaml形式の出力に使う
+#[derive(Debug, Serialize)]
+pub struct ColoredRoute {
+    #[serde(flatten)]
+    route: Route,
+    /// 描画に使う#付きの経路色 (ex: #FFD700)
+    color: String,
+    /// 描画に使う#付きの経路文字色. 省略時は経路色から決める
+    text_color: String,
+}
+
+impl From<Route> for ColoredRoute {
+    fn from(route: Route) -> Self {
+        Self {
+            color: route.color().to_css(),
+            text_color: route.text_color().to_css(),
+            route,
+        }
+    }
+}
+
+impl ToGeoFeature for RouteLine {
+    fn geometry_fields() -> &'static [&'static str] {
+        &[]
+    }
 
-use crate::external;
-use crate::external::gtfs::routes::Route;
+    fn geometry(&self) -> Geometry {
+        Geometry::LineString(self.coordinates.clone())
+    }
+}
 
 pub struct RouteService {
-    gtfs: Box<dyn external::gtfs::Gtfs>,
+    gtfs: GtfsDb,
 }
 
 impl RouteService {
-    pub fn new(gtfs: Box<dyn external::gtfs::Gtfs>) -> Self {
+    pub fn new(gtfs: GtfsDb) -> Self {
         Self { gtfs }
     }
 
-    pub fn fetch(&mut self) -> Result<Vec<Route>> {
-        self.gtfs.select_routes()
+    pub fn fetch(
+        &mut self,
+        route_id: Option<&RouteId>,
+        agency_id: Option<&AgencyId>,
+        query: &Query,
+    ) -> Result<Vec<Route>> {
+        match (route_id, agency_id) {
+            (Some(id), _) => self.gtfs.select_routes_by_route_id(id, query),
+            (None, Some(id)) => self.gtfs.select_routes_by_agency_id(id, query),
+            (None, None) => self.gtfs.select_routes(query),
+        }
+    }
+
+    pub fn find_by_name(&mut self, name: &str, query: &Query) -> Result<Vec<Route>> {
+        self.gtfs.select_routes_by_name_like(name, query)
+    }
+
+    /// fetchと同じ条件に当てはまる件数
+    pub fn count(
+        &mut self,
+        route_id: Option<&RouteId>,
+        agency_id: Option<&AgencyId>,
+        query: &Query,
+    ) -> Result<u32> {
+        let query = match (route_id, agency_id) {
+            (Some(id), _) => query.clone().and_eq("route_id", id),
+            (None, Some(id)) => query.clone().and_eq("agency_id", id),
+            (None, None) => query.clone(),
+        };
+        self.gtfs.count_matches(Route::table_name(), &query)
+    }
+
+    /// routesの上下区分ごとの線形. 描画IDを持つ便がない経路は含めない
+    pub fn fetch_lines(&mut self, routes: Vec<Route>) -> Result<Vec<RouteLine>> {
+        // 便数の多い順に並んでいるため, 経路・上下区分ごとに最初の描画IDを代表にする
+        let mut representatives: HashMap<RouteId, Vec<(Option<Direction>, ShapeId)>> =
+            HashMap::new();
+        for x in self.gtfs.select_route_shapes()? {
+            let direction_id = x.direction_id;
+            let shapes = representatives.entry(x.route_id).or_default();
+            if shapes.last().is_none_or(|(d, _)| *d != direction_id) {
+                shapes.push((direction_id, x.shape_id));
+            }
+        }
+
+        let mut coordinates: HashMap<ShapeId, Vec<Position>> = HashMap::new();
+        let mut lines = Vec::new();
+        for route in routes {
+            let shapes = match representatives.remove(&route.route_id) {
+                Some(x) => x,
+                None => continue,
+            };
+            for (direction_id, shape_id) in shapes {
+                if !coordinates.contains_key(&shape_id) {
+                    let points = self
+                        .gtfs
+                        .select_shapes_by_shape_id(&shape_id, &Query::default())?
+                        .iter()
+                        .map(|x| position(x.shape_pt_lat, x.shape_pt_lon))
+                        .collect();
+                    coordinates.insert(shape_id.clone(), points);
+                }
+                let points = &coordinates[&shape_id];
+                // LineStringには2点以上が必要
+                if points.len() < 2 {
+                    warn!(
+                        "{} の描画点が2点未満のため線形を出力しません (route_id: {})",
+                        shape_id, route.route_id
+                    );
+                    continue;
+                }
+                lines.push(RouteLine {
+                    route: ColoredRoute::from(route.clone()),
+                    direction_id,
+                    shape_id,
+                    coordinates: points.clone(),
+                });
+            }
+        }
+        Ok(lines)
     }
 }
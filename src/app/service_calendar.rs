@@ -0,0 +1,77 @@
+use std::collections::BTreeSet;
+
+use anyhow::Result;
+use chrono::{Datelike, NaiveDate, Weekday};
+
+use crate::external::gtfs::calendar::{Calendar, OperationStatus, ServiceId};
+use crate::external::gtfs::calendar_dates::{CalendarDate, ExceptionType};
+use crate::external::gtfs::{Gtfs, Query};
+
+/// calendarの曜日パターンにcalendar_datesの例外を適用して運行日を判定する
+pub struct ServiceCalendar {
+    calendars: Vec<Calendar>,
+    calendar_dates: Vec<CalendarDate>,
+}
+
+impl ServiceCalendar {
+    pub fn load(gtfs: &mut dyn Gtfs) -> Result<Self> {
+        Ok(Self {
+            calendars: gtfs.select_calendars(&Query::default())?,
+            calendar_dates: gtfs.select_calendar_dates(&Query::default())?,
+        })
+    }
+
+    /// dateに運行する運行日ID
+    pub fn active_service_ids(&self, date: NaiveDate) -> Vec<ServiceId> {
+        let mut service_ids: BTreeSet<&ServiceId> = self
+            .calendars
+            .iter()
+            .filter(|c| runs_on(c, date))
+            .map(|c| &c.service_id)
+            .collect();
+
+        for calendar_date in self.calendar_dates.iter().filter(|d| d.date == date) {
+            match calendar_date.exception_type {
+                ExceptionType::Added => service_ids.insert(&calendar_date.service_id),
+                ExceptionType::Removed => service_ids.remove(&calendar_date.service_id),
+            };
+        }
+
+        service_ids.into_iter().cloned().collect()
+    }
+
+    /// いずれかの運行日IDが運行する日
+    pub fn service_dates(&self) -> Vec<NaiveDate> {
+        let dates = self
+            .calendars
+            .iter()
+            .flat_map(|c| vec![c.start_date, c.end_date])
+            .chain(self.calendar_dates.iter().map(|d| d.date));
+        let (first, last) = match (dates.clone().min(), dates.max()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return vec![],
+        };
+        first
+            .iter_days()
+            .take_while(|x| *x <= last)
+            .filter(|x| !self.active_service_ids(*x).is_empty())
+            .collect()
+    }
+}
+
+/// 期間内かつ曜日が運行になっているか
+fn runs_on(calendar: &Calendar, date: NaiveDate) -> bool {
+    if date < calendar.start_date || calendar.end_date < date {
+        return false;
+    }
+    let status = match date.weekday() {
+        Weekday::Mon => &calendar.monday,
+        Weekday::Tue => &calendar.tuesday,
+        Weekday::Wed => &calendar.wednesday,
+        Weekday::Thu => &calendar.thursday,
+        Weekday::Fri => &calendar.friday,
+        Weekday::Sat => &calendar.saturday,
+        Weekday::Sun => &calendar.sunday,
+    };
+    *status == OperationStatus::Present
+}
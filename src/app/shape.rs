@@ -0,0 +1,109 @@
+use anyhow::Result;
+use log::warn;
+use serde::Serialize;
+
+use crate::external::gtfs::shapes::{Shape, ShapeId};
+use crate::external::gtfs::{Gtfs, Query};
+use crate::external::gtfsdb::{GtfsDb, Table};
+use crate::geo;
+use crate::io::geojson::{position, Geometry, Position, ToGeoFeature};
+
+/// 描画IDごとの線形
+#[derive(Debug, Serialize)]
+pub struct ShapeLine {
+    /// 描画ID
+    shape_id: ShapeId,
+    /// 描画点の数
+    points: usize,
+    /// 描画点を順に結んだ長さ (メートル) をhaversine公式で求めたもの
+    meters: f64,
+    /// 最後の描画点の描画距離 (shapes.txtにあるか, fill-shape-distで計算した場合のみ)
+    shape_dist_traveled: Option<f32>,
+    #[serde(skip)]
+    coordinates: Vec<Position>,
+}
+
+impl ToGeoFeature for ShapeLine {
+    fn geometry_fields() -> &'static [&'static str] {
+        &[]
+    }
+
+    fn geometry(&self) -> Geometry {
+        Geometry::LineString(self.coordinates.clone())
+    }
+}
+
+pub struct ShapeService {
+    gtfs: GtfsDb,
+}
+
+impl ShapeService {
+    pub fn new(gtfs: GtfsDb) -> Self {
+        Self { gtfs }
+    }
+
+    /// 描画ID, 描画順序の順に返す
+    pub fn fetch(&mut self, shape_id: Option<&ShapeId>, query: &Query) -> Result<Vec<Shape>> {
+        match shape_id {
+            Some(id) => self.gtfs.select_shapes_by_shape_id(id, query),
+            None => self.gtfs.select_shapes(query),
+        }
+    }
+
+    /// fetchと同じ条件に当てはまる件数
+    pub fn count(&mut self, shape_id: Option<&ShapeId>, query: &Query) -> Result<u32> {
+        let query = match shape_id {
+            Some(id) => query.clone().and_eq("shape_id", id),
+            None => query.clone(),
+        };
+        self.gtfs.count_matches(Shape::table_name(), &query)
+    }
+
+    /// fetchと同じ条件の描画点を, 描画IDごとに描画順序の順で結んだ線形
+    /// limit, offsetは描画点ではなく線形の数に適用する
+    pub fn fetch_lines(
+        &mut self,
+        shape_id: Option<&ShapeId>,
+        query: &Query,
+    ) -> Result<Vec<ShapeLine>> {
+        // 描画IDの絞り込みも描画IDの種類を数える条件に含める
+        let query = match shape_id {
+            Some(id) => query.clone().and_eq("shape_id", id),
+            None => query.clone(),
+        };
+        let shapes = self
+            .gtfs
+            .select_shapes(&query.paginate_distinct(Shape::table_name(), "shape_id"))?;
+        let mut lines = Vec::new();
+        for points in shapes.chunk_by(|a, b| a.shape_id == b.shape_id) {
+            let shape_id = &points[0].shape_id;
+            // LineStringには2点以上が必要
+            if points.len() < 2 {
+                warn!("{} の描画点が2点未満のため線形を出力しません", shape_id);
+                continue;
+            }
+            let meters = points
+                .windows(2)
+                .map(|x| {
+                    geo::haversine_meters(
+                        x[0].shape_pt_lat,
+                        x[0].shape_pt_lon,
+                        x[1].shape_pt_lat,
+                        x[1].shape_pt_lon,
+                    )
+                })
+                .sum();
+            lines.push(ShapeLine {
+                shape_id: shape_id.clone(),
+                points: points.len(),
+                meters,
+                shape_dist_traveled: points.last().and_then(|x| x.shape_dist_traveled),
+                coordinates: points
+                    .iter()
+                    .map(|x| position(x.shape_pt_lat, x.shape_pt_lon))
+                    .collect(),
+            });
+        }
+        Ok(lines)
+    }
+}
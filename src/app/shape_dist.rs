@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use log::info;
+
+use crate::external::gtfs::shapes::{Shape, ShapeId};
+use crate::external::gtfs::stop_times::StopTime;
+use crate::external::gtfs::stops::{Stop, StopId};
+use crate::external::gtfs::trips::TripId;
+use crate::external::gtfs::{Gtfs, Latitude, Longitude, Query};
+use crate::external::gtfsdb::{GtfsDb, Table};
+use crate::geo;
+
+/// stop_timesを読み込み・更新する件数の単位
+const STOP_TIMES_BATCH: usize = 50000;
+
+pub struct ShapeDistService {
+    gtfs: GtfsDb,
+}
+
+/// shapes, stop_timesのshape_dist_traveled (通算距離) を補完するアプリケーションサービス
+/// 補完する値の単位はメートル
+impl ShapeDistService {
+    pub fn new(gtfs: GtfsDb) -> Self {
+        Self { gtfs }
+    }
+
+    /// overwriteがfalseなら値が入っているレコードはそのままにする
+    pub fn compute(&mut self, overwrite: bool) -> Result<()> {
+        let all = Query::default();
+
+        info!("ℹ️ Compute shape_dist_traveled of shapes.");
+        let mut shapes: HashMap<ShapeId, Vec<Shape>> = HashMap::new();
+        for shape in self.gtfs.select_shapes(&all)? {
+            shapes
+                .entry(shape.shape_id.clone())
+                .or_default()
+                .push(shape);
+        }
+        let mut updated_shapes = Vec::new();
+        for points in shapes.values_mut() {
+            points.sort_by_key(|x| x.shape_pt_sequence);
+            updated_shapes.extend(fill_shape(points, overwrite));
+        }
+        self.gtfs.update_shapes_dist_traveled(&updated_shapes)?;
+        info!("  ✨ Success ({} records)", updated_shapes.len());
+
+        info!("ℹ️ Compute shape_dist_traveled of stop_times.");
+        let stops: HashMap<StopId, Stop> = self
+            .gtfs
+            .select_stops(&all)?
+            .into_iter()
+            .map(|x| (x.stop_id.clone(), x))
+            .collect();
+        let trip_shapes: HashMap<TripId, ShapeId> = self
+            .gtfs
+            .select_trips(&all)?
+            .into_iter()
+            .filter_map(|x| Some((x.trip_id, x.shape_id?)))
+            .collect();
+        // 便ID, 通過順位の順に渡されるため, 便が変わった時点で前の便の停車時刻がそろう
+        let mut updated_stop_times = Vec::new();
+        let mut trip: Vec<StopTime> = Vec::new();
+        let mut fill_trip = |times: &mut Vec<StopTime>| {
+            let points = times
+                .first()
+                .and_then(|x| trip_shapes.get(&x.trip_id))
+                .and_then(|x| shapes.get(x));
+            if let Some(points) = points {
+                updated_stop_times.extend(fill_stop_times(times, points, &stops, overwrite));
+            }
+            times.clear();
+        };
+        self.gtfs
+            .for_each_stop_times_by_trip(STOP_TIMES_BATCH, &mut |batch| {
+                for stop_time in batch {
+                    if trip.last().is_some_and(|x| x.trip_id != stop_time.trip_id) {
+                        fill_trip(&mut trip);
+                    }
+                    trip.push(stop_time.clone());
+                }
+                Ok(())
+            })?;
+        fill_trip(&mut trip);
+        // 読み込み中は更新できないため, 補完したレコードだけを残して後から書き込む
+        for chunk in updated_stop_times.chunks(STOP_TIMES_BATCH) {
+            self.gtfs.update_stop_times_dist_traveled(chunk)?;
+        }
+        info!("  ✨ Success ({} records)", updated_stop_times.len());
+
+        self.gtfs
+            .invalidate_meta(&[Shape::table_name(), StopTime::table_name()])
+    }
+}
+
+fn shape_position(shape: &Shape) -> (Latitude, Longitude) {
+    (shape.shape_pt_lat, shape.shape_pt_lon)
+}
+
+/// 描画順に並んだ点の通算距離を埋め, 更新した点を返す
+fn fill_shape(points: &mut [Shape], overwrite: bool) -> Vec<Shape> {
+    let mut distance = 0.0;
+    let mut previous: Option<(Latitude, Longitude)> = None;
+    let mut updated = Vec::new();
+    for point in points.iter_mut() {
+        let position = shape_position(point);
+        if let Some((lat, lon)) = previous {
+            distance += geo::haversine_meters(lat, lon, position.0, position.1);
+        }
+        previous = Some(position);
+
+        if overwrite || point.shape_dist_traveled.is_none() {
+            point.shape_dist_traveled = Some(distance as f32);
+            updated.push(point.clone());
+        }
+    }
+    updated
+}
+
+/// 通過順に並んだ停車時刻の通算距離を, 標柱を描画線へ射影して埋める
+/// 折り返しなどで同じ場所を2度通る場合に備え, 前の標柱より手前には戻らない
+fn fill_stop_times(
+    stop_times: &mut [StopTime],
+    points: &[Shape],
+    stops: &HashMap<StopId, Stop>,
+    overwrite: bool,
+) -> Vec<StopTime> {
+    let mut from = 0;
+    let mut updated = Vec::new();
+    for stop_time in stop_times.iter_mut() {
+        if !overwrite && stop_time.shape_dist_traveled.is_some() {
+            continue;
+        }
+        let stop = match stops.get(&stop_time.stop_id) {
+            Some(x) => x,
+            None => continue,
+        };
+        if let Some((segment, distance)) = project(points, from, (stop.stop_lat, stop.stop_lon)) {
+            from = segment;
+            stop_time.shape_dist_traveled = Some(distance.round() as u32);
+            updated.push(stop_time.clone());
+        }
+    }
+    updated
+}
+
+/// from番目以降の線分のうち最も近いものへ射影し, (線分の位置, 通算距離) を返す
+fn project(points: &[Shape], from: usize, position: (Latitude, Longitude)) -> Option<(usize, f64)> {
+    let dist = |x: &Shape| f64::from(x.shape_dist_traveled.unwrap_or(0.0));
+    if points.len() == 1 {
+        return Some((0, dist(&points[0])));
+    }
+
+    points
+        .windows(2)
+        .enumerate()
+        .skip(from)
+        .map(|(i, w)| {
+            let (t, offset) =
+                geo::project_onto_segment(position, shape_position(&w[0]), shape_position(&w[1]));
+            (i, dist(&w[0]) + t * (dist(&w[1]) - dist(&w[0])), offset)
+        })
+        .min_by(|a, b| a.2.partial_cmp(&b.2).unwrap_or(std::cmp::Ordering::Equal))
+        .map(|(i, distance, _)| (i, distance))
+}
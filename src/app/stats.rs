@@ -0,0 +1,74 @@
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::app::service_calendar::ServiceCalendar;
+use crate::external::gtfs::Gtfs;
+use crate::external::gtfsdb::GtfsDb;
+
+/// 集計項目と値
+#[derive(Debug, Serialize)]
+pub struct Stat {
+    item: String,
+    value: Value,
+}
+
+impl Stat {
+    fn new(item: &str, value: impl Into<Value>) -> Self {
+        Self {
+            item: item.to_string(),
+            value: value.into(),
+        }
+    }
+}
+
+pub struct StatsService {
+    gtfs: GtfsDb,
+}
+
+impl StatsService {
+    pub fn new(gtfs: GtfsDb) -> Self {
+        Self { gtfs }
+    }
+
+    /// 各テーブルのレコード数に続いて, 運行の概要を集計する
+    pub fn compute(&mut self) -> Result<Vec<Stat>> {
+        let mut stats: Vec<Stat> = self
+            .gtfs
+            .count_records()?
+            .into_iter()
+            .map(|(table, count)| Stat::new(table, count))
+            .collect();
+
+        stats.push(Stat::new(
+            "routes_in_service",
+            self.gtfs.count_distinct("trips", "route_id")?,
+        ));
+
+        let dates = ServiceCalendar::load(&mut self.gtfs)?.service_dates();
+        stats.push(Stat::new("service_dates", dates.len()));
+        stats.push(Stat::new(
+            "first_service_date",
+            optional(dates.first().map(|x| x.format("%Y%m%d").to_string())),
+        ));
+        stats.push(Stat::new(
+            "last_service_date",
+            optional(dates.last().map(|x| x.format("%Y%m%d").to_string())),
+        ));
+
+        let feed_info = self.gtfs.select_feed_info()?;
+        let (start, end) = match feed_info {
+            Some(x) => (x.feed_start_date, x.feed_end_date),
+            None => (None, None),
+        };
+        stats.push(Stat::new("feed_start_date", optional(start)));
+        stats.push(Stat::new("feed_end_date", optional(end)));
+
+        Ok(stats)
+    }
+}
+
+/// 値がなければnull (csvでは空欄) にする
+fn optional(value: Option<String>) -> Value {
+    value.map_or(Value::Null, Value::from)
+}
@@ -0,0 +1,122 @@
+use std::cmp::Ordering;
+
+use anyhow::{Context, Result};
+use log::warn;
+use serde::Serialize;
+
+use crate::external::gtfs::routes::RouteId;
+use crate::external::gtfs::stops::{Stop, StopId};
+use crate::external::gtfs::{Gtfs, Latitude, Longitude, Query};
+use crate::external::gtfsdb::{GtfsDb, Table};
+use crate::geo;
+
+#[derive(Debug, Serialize)]
+pub struct Distance {
+    from_stop_id: StopId,
+    to_stop_id: StopId,
+    meters: f64,
+}
+
+pub struct StopService {
+    gtfs: GtfsDb,
+    /// SpatiaLiteの空間インデックスで検索するか
+    spatialite: bool,
+}
+
+impl StopService {
+    pub fn new(gtfs: GtfsDb) -> Self {
+        Self {
+            gtfs,
+            spatialite: false,
+        }
+    }
+
+    /// SpatiaLite拡張とstopsのgeometryカラムがあれば, nearbyで空間インデックスを使う
+    /// どちらかがなければ警告し, 緯度経度の範囲とhaversine公式で検索する
+    pub fn enable_spatialite(&mut self) -> Result<()> {
+        if !self.gtfs.load_spatialite()? {
+            warn!(
+                "SpatiaLite拡張 (mod_spatialite) が見つからないため, 空間インデックスを使いません"
+            );
+            return Ok(());
+        }
+        if !self.gtfs.has_stop_geometries()? {
+            warn!("stopsにgeometryカラムがないため, 空間インデックスを使いません (make-db --spatialite で作成できます)");
+            return Ok(());
+        }
+        self.spatialite = true;
+        Ok(())
+    }
+
+    pub fn fetch(&mut self, route_id: Option<&RouteId>, query: &Query) -> Result<Vec<Stop>> {
+        match route_id {
+            Some(id) => self.gtfs.select_stops_by_route_id(id, query),
+            None => self.gtfs.select_stops(query),
+        }
+    }
+
+    /// 親駅に属する標柱・出入口など
+    pub fn children(&mut self, parent_station: &StopId, query: &Query) -> Result<Vec<Stop>> {
+        self.gtfs
+            .select_stops_by_parent_station(parent_station, query)
+    }
+
+    /// fetchまたはchildrenと同じ条件に当てはまる件数
+    pub fn count(
+        &mut self,
+        route_id: Option<&RouteId>,
+        parent_station: Option<&StopId>,
+        query: &Query,
+    ) -> Result<u32> {
+        let mut query = query.clone();
+        if let Some(id) = parent_station {
+            query = query.and_eq("parent_station", id);
+        }
+        match route_id {
+            Some(id) => self.gtfs.count_stops_by_route_id(id, &query),
+            None => self.gtfs.count_matches(Stop::table_name(), &query),
+        }
+    }
+
+    /// 2つの停留所・標柱間の直線距離 (メートル)
+    pub fn distance(&mut self, from: &StopId, to: &StopId) -> Result<Distance> {
+        let (from, to) = (self.get(from)?, self.get(to)?);
+        Ok(Distance {
+            meters: geo::haversine_meters(from.stop_lat, from.stop_lon, to.stop_lat, to.stop_lon),
+            from_stop_id: from.stop_id,
+            to_stop_id: to.stop_id,
+        })
+    }
+
+    fn get(&mut self, stop_id: &StopId) -> Result<Stop> {
+        self.gtfs
+            .select_stops_by_stop_id(stop_id, &Query::default())?
+            .pop()
+            .with_context(|| format!("停留所・標柱が見つかりません: {}", stop_id))
+    }
+
+    /// 半径radius (メートル) 以内のstopを近い順に返す
+    /// SQLiteだけでは距離を計算できないため, 緯度経度の範囲で絞ってから距離で選別する
+    /// enable_spatialiteで空間インデックスを使える場合は楕円体上の距離でSpatiaLiteに選別させる
+    pub fn nearby(&mut self, lat: Latitude, lon: Longitude, radius: f64) -> Result<Vec<Stop>> {
+        let (south_west, north_east) = geo::bounding_box(lat, lon, radius);
+        if self.spatialite {
+            return self
+                .gtfs
+                .select_stops_within((lat, lon), south_west, north_east, radius);
+        }
+        let mut stops: Vec<(f64, Stop)> = self
+            .gtfs
+            .select_stops_in_bbox(south_west, north_east)?
+            .into_iter()
+            .map(|x| (geo::haversine_meters(lat, lon, x.stop_lat, x.stop_lon), x))
+            .filter(|(distance, _)| *distance <= radius)
+            .collect();
+        stops.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+        Ok(stops.into_iter().map(|(_, x)| x).collect())
+    }
+
+    pub fn find_by_name(&mut self, name: &str, query: &Query) -> Result<Vec<Stop>> {
+        self.gtfs.select_stops_by_name_like(name, query)
+    }
+}
@@ -0,0 +1,64 @@
+use anyhow::Result;
+use chrono::NaiveDate;
+use chrono_tz::Tz;
+
+use crate::app::time_zone::{output_time, OutputTime, TimeConverter};
+use crate::external::gtfs::stop_times::StopTime;
+use crate::external::gtfs::trips::TripId;
+use crate::external::gtfs::{Gtfs, Query};
+use crate::external::gtfsdb::{GtfsDb, Table};
+use crate::io::projection::{self, Replaced};
+
+pub struct StopTimeService {
+    gtfs: GtfsDb,
+}
+
+impl StopTimeService {
+    pub fn new(gtfs: GtfsDb) -> Self {
+        Self { gtfs }
+    }
+
+    pub fn fetch(&mut self, trip_id: Option<&TripId>, query: &Query) -> Result<Vec<StopTime>> {
+        match trip_id {
+            Some(id) => self.gtfs.select_stop_times_by_trip_id(id, query),
+            None => self.gtfs.select_stop_times(query),
+        }
+    }
+
+    /// fetchと同じ条件に当てはまる件数
+    pub fn count(&mut self, trip_id: Option<&TripId>, query: &Query) -> Result<u32> {
+        let query = match trip_id {
+            Some(id) => query.clone().and_eq("trip_id", id),
+            None => query.clone(),
+        };
+        self.gtfs.count_matches(StopTime::table_name(), &query)
+    }
+
+    /// 到着・出発時刻をdateに運行した場合のtzでの日時に置き換える
+    pub fn zoned<'a>(
+        &mut self,
+        stop_times: &'a [StopTime],
+        date: NaiveDate,
+        tz: Tz,
+    ) -> Result<Vec<Replaced<'a, StopTime, Option<OutputTime>>>> {
+        let converter = TimeConverter::load(&mut self.gtfs, date, tz)?;
+        Ok(stop_times
+            .iter()
+            .map(|x| {
+                projection::replace(
+                    x,
+                    vec![
+                        (
+                            "arrival_time",
+                            output_time(x.arrival_time, Some(&converter)),
+                        ),
+                        (
+                            "departure_time",
+                            output_time(x.departure_time, Some(&converter)),
+                        ),
+                    ],
+                )
+            })
+            .collect())
+    }
+}
@@ -2,6 +2,7 @@ use anyhow::Result;
 
 use crate::external;
 use crate::external::gtfs::shapes::Shape;
+use crate::external::gtfs::Query;
 
 pub struct TestService {
     gtfs: Box<dyn external::gtfs::Gtfs>,
@@ -15,6 +16,6 @@ impl TestService {
     }
 
     pub fn fetch(&mut self) -> Result<Vec<Shape>> {
-        self.gtfs.select_shapes()
+        self.gtfs.select_shapes(&Query::default())
     }
 }
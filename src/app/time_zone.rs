@@ -0,0 +1,60 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, NaiveDate, TimeZone};
+use chrono_tz::Tz;
+use serde::Serialize;
+
+use crate::external::gtfs::{Gtfs, GtfsTime, Query};
+
+/// 出力する時刻. タイムゾーンの指定がなければGTFSの時刻のまま
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum OutputTime {
+    Gtfs(GtfsTime),
+    Zoned(DateTime<Tz>),
+}
+
+/// GTFSの時刻を運行日の日時に変換し, 指定したタイムゾーンで表す
+pub struct TimeConverter {
+    /// 運行日の時刻の起点 (事業者のタイムゾーンで正午の12時間前)
+    origin: DateTime<Tz>,
+    target: Tz,
+}
+
+impl TimeConverter {
+    /// 事業者のタイムゾーンはfeed内で共通のため, 最初の事業者のものを使う
+    pub fn load(gtfs: &mut dyn Gtfs, date: NaiveDate, target: Tz) -> Result<Self> {
+        let agencies = gtfs.select_agencies(&Query::default())?;
+        let agency = agencies
+            .first()
+            .context("agencyがないためタイムゾーンが分かりません")?;
+        let tz = agency.agency_timezone.tz();
+        // 夏時間の切り替え日も正しく扱えるよう, GTFSの定義どおり正午から12時間戻した時刻を起点にする
+        let noon = tz
+            .from_local_datetime(
+                &date
+                    .and_hms_opt(12, 0, 0)
+                    .context("正午の日時を作れません")?,
+            )
+            .single()
+            .with_context(|| format!("{} の正午が {} で一意に決まりません", date, tz))?;
+        Ok(Self {
+            origin: noon - Duration::hours(12),
+            target,
+        })
+    }
+
+    pub fn convert(&self, time: GtfsTime) -> DateTime<Tz> {
+        (self.origin + Duration::seconds(time.seconds().into())).with_timezone(&self.target)
+    }
+}
+
+/// converterがなければGTFSの時刻のまま出力する
+pub fn output_time(
+    time: Option<GtfsTime>,
+    converter: Option<&TimeConverter>,
+) -> Option<OutputTime> {
+    match converter {
+        Some(c) => time.map(|x| OutputTime::Zoned(c.convert(x))),
+        None => time.map(OutputTime::Gtfs),
+    }
+}
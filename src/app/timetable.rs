@@ -0,0 +1,98 @@
+use anyhow::Result;
+use chrono::NaiveDate;
+use chrono_tz::Tz;
+use serde::Serialize;
+
+use crate::app::time_zone::{output_time, OutputTime, TimeConverter};
+use crate::app::trip;
+use crate::external;
+use crate::external::gtfs::routes::RouteId;
+use crate::external::gtfs::stops::StopId;
+use crate::external::gtfs::timetable::TimetableRow;
+use crate::external::gtfs::trips::{Direction, TripId};
+use crate::external::gtfs::{Query, Sequence};
+
+/// 1便分の時刻表
+#[derive(Debug, Serialize)]
+pub struct Timetable {
+    trip_id: TripId,
+    trip_headsign: Option<String>,
+    direction_id: Option<Direction>,
+    stop_times: Vec<TimetableStop>,
+}
+
+/// 便の中の1停留所分の通過時刻
+#[derive(Debug, Serialize)]
+pub struct TimetableStop {
+    stop_sequence: Sequence,
+    stop_id: StopId,
+    stop_name: Option<String>,
+    arrival_time: Option<OutputTime>,
+    departure_time: Option<OutputTime>,
+}
+
+pub struct TimetableService {
+    gtfs: Box<dyn external::gtfs::Gtfs>,
+}
+
+impl TimetableService {
+    pub fn new(gtfs: Box<dyn external::gtfs::Gtfs>) -> Self {
+        Self { gtfs }
+    }
+
+    /// zoneを指定すると, 時刻をその運行日に運行した場合のタイムゾーンでの日時にする
+    /// fill_headsignを指定すると, 空の行き先を経路名か終着の標柱名称で補う
+    pub fn fetch(
+        &mut self,
+        route_id: &RouteId,
+        zone: Option<(NaiveDate, Tz)>,
+        fill_headsign: bool,
+    ) -> Result<Vec<Timetable>> {
+        let converter = match zone {
+            Some((date, tz)) => Some(TimeConverter::load(self.gtfs.as_mut(), date, tz)?),
+            None => None,
+        };
+        let rows = self.gtfs.select_timetable_by_route_id(route_id)?;
+        let mut timetables = group_by_trip(rows, converter.as_ref());
+        if fill_headsign {
+            let route_long_name = self
+                .gtfs
+                .select_routes_by_route_id(route_id, &Query::default())?
+                .pop()
+                .and_then(|x| x.route_long_name);
+            for t in timetables.iter_mut() {
+                let last_stop_name = t.stop_times.last().and_then(|x| x.stop_name.clone());
+                t.trip_headsign = trip::fill_headsign(
+                    t.trip_headsign.take(),
+                    route_long_name.clone(),
+                    last_stop_name,
+                );
+            }
+        }
+        Ok(timetables)
+    }
+}
+
+/// 便ID順に並んだ行を便ごとにまとめる
+fn group_by_trip(rows: Vec<TimetableRow>, converter: Option<&TimeConverter>) -> Vec<Timetable> {
+    let mut timetables: Vec<Timetable> = Vec::new();
+    for row in rows {
+        let stop = TimetableStop {
+            stop_sequence: row.stop_sequence,
+            stop_id: row.stop_id,
+            stop_name: row.stop_name,
+            arrival_time: output_time(row.arrival_time, converter),
+            departure_time: output_time(row.departure_time, converter),
+        };
+        match timetables.last_mut() {
+            Some(t) if t.trip_id == row.trip_id => t.stop_times.push(stop),
+            _ => timetables.push(Timetable {
+                trip_id: row.trip_id,
+                trip_headsign: row.trip_headsign,
+                direction_id: row.direction_id,
+                stop_times: vec![stop],
+            }),
+        }
+    }
+    timetables
+}
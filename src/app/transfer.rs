@@ -0,0 +1,55 @@
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::external;
+use crate::external::gtfs::stop_transfers::StopTransfer;
+use crate::external::gtfs::stops::StopId;
+use crate::external::gtfs::transfers::TransferType;
+use crate::external::gtfs::Second;
+
+/// 標柱間の乗換
+#[derive(Debug, Serialize)]
+pub struct Connection {
+    from_stop_id: StopId,
+    from_stop_name: Option<String>,
+    to_stop_id: StopId,
+    to_stop_name: Option<String>,
+    transfer_type: TransferType,
+    /// transfer_type = 3 (乗換不可) ならfalse
+    transferable: bool,
+    min_transfer_time: Option<Second>,
+}
+
+impl From<StopTransfer> for Connection {
+    fn from(x: StopTransfer) -> Self {
+        Self {
+            transferable: x.transfer_type != TransferType::NotPossible,
+            from_stop_id: x.from_stop_id,
+            from_stop_name: x.from_stop_name,
+            to_stop_id: x.to_stop_id,
+            to_stop_name: x.to_stop_name,
+            transfer_type: x.transfer_type,
+            min_transfer_time: x.min_transfer_time,
+        }
+    }
+}
+
+pub struct TransferService {
+    gtfs: Box<dyn external::gtfs::Gtfs>,
+}
+
+impl TransferService {
+    pub fn new(gtfs: Box<dyn external::gtfs::Gtfs>) -> Self {
+        Self { gtfs }
+    }
+
+    /// 乗換元, 乗換先で絞り込む (Noneなら絞り込まない)
+    pub fn fetch_connections(
+        &mut self,
+        from_stop_id: Option<&StopId>,
+        to_stop_id: Option<&StopId>,
+    ) -> Result<Vec<Connection>> {
+        let transfers = self.gtfs.select_stop_transfers(from_stop_id, to_stop_id)?;
+        Ok(transfers.into_iter().map(Connection::from).collect())
+    }
+}
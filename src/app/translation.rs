@@ -0,0 +1,24 @@
+use anyhow::Result;
+
+use crate::external::gtfs::translations::Translation;
+use crate::external::gtfs::{Gtfs, Query};
+use crate::external::gtfsdb::{GtfsDb, Table};
+
+pub struct TranslationService {
+    gtfs: GtfsDb,
+}
+
+impl TranslationService {
+    pub fn new(gtfs: GtfsDb) -> Self {
+        Self { gtfs }
+    }
+
+    pub fn fetch(&mut self, query: &Query) -> Result<Vec<Translation>> {
+        self.gtfs.select_translations(query)
+    }
+
+    /// fetchと同じ条件に当てはまる件数
+    pub fn count(&mut self, query: &Query) -> Result<u32> {
+        self.gtfs.count_matches(Translation::table_name(), query)
+    }
+}
@@ -1,18 +1,133 @@
+use std::collections::HashMap;
+
 use anyhow::Result;
+use chrono::NaiveDate;
 
-use crate::external;
-use crate::external::gtfs::trips::Trip;
+use crate::app::service_calendar::ServiceCalendar;
+use crate::external::gtfs::routes::RouteId;
+use crate::external::gtfs::stops::StopId;
+use crate::external::gtfs::trip_endpoints::TripEndpoint;
+use crate::external::gtfs::trips::{Direction, Trip};
+use crate::external::gtfs::trips_between::TripBetween;
+use crate::external::gtfs::{Gtfs, Query};
+use crate::external::gtfsdb::{GtfsDb, Table};
+use crate::io::projection::{self, Replaced};
 
 pub struct TripService {
-    gtfs: Box<dyn external::gtfs::Gtfs>,
+    gtfs: GtfsDb,
 }
 
 impl TripService {
-    pub fn new(gtfs: Box<dyn external::gtfs::Gtfs>) -> Self {
+    pub fn new(gtfs: GtfsDb) -> Self {
         Self { gtfs }
     }
 
-    pub fn fetch(&mut self) -> Result<Vec<Trip>> {
-        self.gtfs.select_trips()
+    /// 経路IDと上下区分で絞り込む (Noneなら絞り込まない)
+    pub fn fetch(
+        &mut self,
+        route_id: Option<&RouteId>,
+        direction: Option<Direction>,
+        query: &Query,
+    ) -> Result<Vec<Trip>> {
+        self.gtfs
+            .select_trips_by_route_and_direction(route_id, direction, query)
+    }
+
+    /// fetchと同じ条件に当てはまる件数
+    pub fn count(
+        &mut self,
+        route_id: Option<&RouteId>,
+        direction: Option<Direction>,
+        query: &Query,
+    ) -> Result<u32> {
+        let mut query = query.clone();
+        if let Some(id) = route_id {
+            query = query.and_eq("route_id", id);
+        }
+        if let Some(d) = direction {
+            query = query.and(format!("direction_id = {}", d as u8));
+        }
+        self.gtfs.count_matches(Trip::table_name(), &query)
+    }
+
+    /// trip_headsignが空の便は経路名, それもなければ終着の標柱名称で補って出力する
+    /// データベースの値は変更しない
+    pub fn fill_headsigns<'a>(
+        &mut self,
+        trips: &'a [Trip],
+    ) -> Result<Vec<Replaced<'a, Trip, Option<String>>>> {
+        let route_names: HashMap<RouteId, Option<String>> = self
+            .gtfs
+            .select_routes(&Query::default())?
+            .into_iter()
+            .map(|x| (x.route_id, x.route_long_name))
+            .collect();
+
+        // 終着の標柱名称は経路名でも補えない便の経路についてだけ取得する
+        let mut destinations: HashMap<String, Option<String>> = HashMap::new();
+        let mut route_ids: Vec<&RouteId> = trips
+            .iter()
+            .filter(|x| x.trip_headsign.is_none())
+            .filter(|x| route_names.get(&x.route_id).is_none_or(|x| x.is_none()))
+            .map(|x| &x.route_id)
+            .collect();
+        route_ids.sort();
+        route_ids.dedup();
+        for route_id in route_ids {
+            for x in self.gtfs.select_trip_endpoints_by_route_id(route_id)? {
+                destinations.insert(x.trip_id, x.destination_stop_name);
+            }
+        }
+
+        Ok(trips
+            .iter()
+            .map(|x| {
+                let headsign = fill_headsign(
+                    x.trip_headsign.clone(),
+                    route_names.get(&x.route_id).cloned().flatten(),
+                    destinations.get(&x.trip_id).cloned().flatten(),
+                );
+                projection::replace(x, vec![("trip_headsign", headsign)])
+            })
+            .collect())
     }
+
+    /// 経路の各便の始発と終着
+    pub fn fetch_endpoints(&mut self, route_id: &RouteId) -> Result<Vec<TripEndpoint>> {
+        self.gtfs.select_trip_endpoints_by_route_id(route_id)
+    }
+
+    /// fromからtoへこの順に通る便. dateを指定するとその日に運行する便だけにする
+    pub fn fetch_between(
+        &mut self,
+        from: &StopId,
+        to: &StopId,
+        date: Option<NaiveDate>,
+    ) -> Result<Vec<TripBetween>> {
+        let trips = self.gtfs.select_trips_between(from, to)?;
+        let date = match date {
+            Some(x) => x,
+            None => return Ok(trips),
+        };
+        let service_ids = ServiceCalendar::load(&mut self.gtfs)?.active_service_ids(date);
+        Ok(trips
+            .into_iter()
+            .filter(|x| service_ids.contains(&x.service_id))
+            .collect())
+    }
+
+    /// dateに運行する便
+    pub fn fetch_active(&mut self, date: NaiveDate, query: &Query) -> Result<Vec<Trip>> {
+        let service_ids = ServiceCalendar::load(&mut self.gtfs)?.active_service_ids(date);
+        self.gtfs.select_trips_by_service_ids(&service_ids, query)
+    }
+}
+
+/// 表示用の行き先. 空なら経路名, それもなければ終着の標柱名称にする
+pub fn fill_headsign(
+    headsign: Option<String>,
+    route_long_name: Option<String>,
+    last_stop_name: Option<String>,
+) -> Option<String> {
+    headsign.or(route_long_name).or(last_stop_name)
 }
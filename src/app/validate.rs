@@ -0,0 +1,186 @@
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::external::gtfs::trips::TripId;
+use crate::external::gtfs::{Gtfs, GtfsTime, Sequence};
+use crate::external::gtfsdb::GtfsDb;
+
+/// 一度に読み込むstop_timesの件数
+const STOP_TIMES_BATCH: usize = 50000;
+
+/// 参照関係 (子テーブル, 子カラム, 参照先の(テーブル, カラム)のいずれか)
+struct Reference {
+    table: &'static str,
+    column: &'static str,
+    parents: &'static [(&'static str, &'static str)],
+}
+
+const REFERENCES: &[Reference] = &[
+    Reference {
+        table: "routes",
+        column: "agency_id",
+        parents: &[("agency", "agency_id")],
+    },
+    Reference {
+        table: "trips",
+        column: "route_id",
+        parents: &[("routes", "route_id")],
+    },
+    Reference {
+        table: "trips",
+        column: "service_id",
+        parents: &[("calendar", "service_id"), ("calendar_dates", "service_id")],
+    },
+    Reference {
+        table: "stop_times",
+        column: "trip_id",
+        parents: &[("trips", "trip_id")],
+    },
+    Reference {
+        table: "stop_times",
+        column: "stop_id",
+        parents: &[("stops", "stop_id")],
+    },
+];
+
+/// tableの (カラム, 参照先のテーブルのいずれか)
+pub fn referenced_tables(table: &str) -> Vec<(&'static str, Vec<&'static str>)> {
+    REFERENCES
+        .iter()
+        .filter(|r| r.table == table)
+        .map(|r| (r.column, r.parents.iter().map(|(t, _)| *t).collect()))
+        .collect()
+}
+
+/// 参照先が存在しないレコードの件数
+#[derive(Debug, Serialize)]
+pub struct DanglingReference {
+    table: &'static str,
+    column: &'static str,
+    /// 参照先 (ex: calendar.service_id|calendar_dates.service_id)
+    references: String,
+    count: u32,
+}
+
+impl DanglingReference {
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+}
+
+/// 便の中で前に通る停留所より時刻が戻っている箇所
+#[derive(Debug, Serialize)]
+pub struct TimeReversal {
+    trip_id: TripId,
+    /// 先に通る停留所の通過順位
+    from_sequence: Sequence,
+    /// 先に通る停留所の時刻
+    from_time: GtfsTime,
+    /// 後に通る停留所の通過順位 (到着より出発が早い場合はfrom_sequenceと同じ)
+    to_sequence: Sequence,
+    /// 後に通る停留所の時刻
+    to_time: GtfsTime,
+}
+
+/// 便の中で同じ通過順位を持つ停留所
+#[derive(Debug, Serialize)]
+pub struct DuplicateSequence {
+    trip_id: TripId,
+    stop_sequence: Sequence,
+    /// 同じ通過順位を持つ停留所の数
+    count: u32,
+}
+
+/// データベースの整合性を検査するアプリケーションサービス
+pub struct ValidateService {
+    gtfs: GtfsDb,
+}
+
+impl ValidateService {
+    pub fn new(gtfs: GtfsDb) -> Self {
+        Self { gtfs }
+    }
+
+    pub fn check_references(&mut self) -> Result<Vec<DanglingReference>> {
+        REFERENCES
+            .iter()
+            .map(|r| {
+                let count = self
+                    .gtfs
+                    .count_dangling_references(r.table, r.column, r.parents)?;
+                Ok(DanglingReference {
+                    table: r.table,
+                    column: r.column,
+                    references: r
+                        .parents
+                        .iter()
+                        .map(|(t, c)| format!("{}.{}", t, c))
+                        .collect::<Vec<_>>()
+                        .join("|"),
+                    count,
+                })
+            })
+            .collect()
+    }
+
+    /// 便ごとに通過順位の順で到着・出発時刻が減っていないか検査する
+    /// 24時以降の時刻も運行日の0時からの秒数で比べるため, 日をまたぐ便も正しく扱える
+    pub fn check_time_order(&mut self) -> Result<Vec<TimeReversal>> {
+        let mut reversals = Vec::new();
+        // 直前に時刻があった停留所 (便ID, 通過順位, 時刻)
+        let mut last: Option<(TripId, Sequence, GtfsTime)> = None;
+        self.gtfs
+            .for_each_stop_times_by_trip(STOP_TIMES_BATCH, &mut |batch| {
+                for st in batch {
+                    if last.as_ref().is_some_and(|(id, _, _)| *id != st.trip_id) {
+                        last = None;
+                    }
+                    for time in st.arrival_time.iter().chain(&st.departure_time) {
+                        if let Some((trip_id, sequence, previous)) = &last {
+                            if time < previous {
+                                reversals.push(TimeReversal {
+                                    trip_id: trip_id.clone(),
+                                    from_sequence: *sequence,
+                                    from_time: *previous,
+                                    to_sequence: st.stop_sequence,
+                                    to_time: *time,
+                                });
+                            }
+                        }
+                        last = Some((st.trip_id.clone(), st.stop_sequence, *time));
+                    }
+                }
+                Ok(())
+            })?;
+        Ok(reversals)
+    }
+
+    /// 便ごとに通過順位が重複していないか検査する. 通過順位の欠番 (ex: 1,2,5) は問題にしない
+    /// make-dbで作ったデータベースでは主キーで重複を防いでいるが, 他のツールで作ったものは保証されない
+    pub fn check_duplicate_sequences(&mut self) -> Result<Vec<DuplicateSequence>> {
+        let mut duplicates: Vec<DuplicateSequence> = Vec::new();
+        // 直前の停留所 (便ID, 通過順位)
+        let mut last: Option<(TripId, Sequence)> = None;
+        self.gtfs
+            .for_each_stop_times_by_trip(STOP_TIMES_BATCH, &mut |batch| {
+                for st in batch {
+                    let current = (st.trip_id.clone(), st.stop_sequence);
+                    if last.as_ref() == Some(&current) {
+                        match duplicates.last_mut() {
+                            Some(x) if x.trip_id == current.0 && x.stop_sequence == current.1 => {
+                                x.count += 1
+                            }
+                            _ => duplicates.push(DuplicateSequence {
+                                trip_id: current.0.clone(),
+                                stop_sequence: current.1,
+                                count: 2,
+                            }),
+                        }
+                    }
+                    last = Some(current);
+                }
+                Ok(())
+            })?;
+        Ok(duplicates)
+    }
+}
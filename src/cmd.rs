@@ -1,3 +1,16 @@
+pub mod compute;
+pub mod describe;
+pub mod distance;
+pub mod export;
+pub mod extract;
+pub mod find;
 pub mod get;
+pub mod headway;
+pub mod import_table;
 pub mod make_db;
+pub mod merge;
+pub mod nearby;
+pub mod schema;
+pub mod stats;
 pub mod test;
+pub mod validate;
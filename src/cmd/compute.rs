@@ -0,0 +1,28 @@
+use anyhow::Result;
+use clap::Clap;
+
+use crate::cmd;
+
+pub mod interpolate_times;
+pub mod shape_dist;
+
+#[derive(Clap, Debug)]
+pub struct Opts {
+    #[clap(subcommand)]
+    pub subcmd: SubCommand,
+}
+
+#[derive(Clap, Debug)]
+pub enum SubCommand {
+    /// shapes, stop_timesのshape_dist_traveledを緯度経度から計算する
+    ShapeDist(cmd::compute::shape_dist::Opts),
+    /// stop_timesの空欄の時刻を前後の時刻から線形補間する
+    InterpolateTimes(cmd::compute::interpolate_times::Opts),
+}
+
+pub fn run(opts: &Opts) -> Result<()> {
+    match &opts.subcmd {
+        SubCommand::ShapeDist(op) => cmd::compute::shape_dist::run(op),
+        SubCommand::InterpolateTimes(op) => cmd::compute::interpolate_times::run(op),
+    }
+}
@@ -0,0 +1,18 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::Clap;
+
+use crate::app::interpolate_times::InterpolateTimesService;
+use crate::external;
+
+#[derive(Clap, Debug)]
+pub struct Opts {
+    #[clap(short, long, parse(from_os_str), default_value = "hibou.db")]
+    database: PathBuf,
+}
+
+pub fn run(op: &Opts) -> Result<()> {
+    let gtfs = external::gtfsdb::open(&op.database)?;
+    InterpolateTimesService::new(gtfs).compute()
+}
@@ -0,0 +1,21 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::Clap;
+
+use crate::app::shape_dist::ShapeDistService;
+use crate::external;
+
+#[derive(Clap, Debug)]
+pub struct Opts {
+    #[clap(short, long, parse(from_os_str), default_value = "hibou.db")]
+    database: PathBuf,
+    /// 値が入っているレコードも計算し直す
+    #[clap(long)]
+    overwrite: bool,
+}
+
+pub fn run(op: &Opts) -> Result<()> {
+    let gtfs = external::gtfsdb::open(&op.database)?;
+    ShapeDistService::new(gtfs).compute(op.overwrite)
+}
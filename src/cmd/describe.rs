@@ -0,0 +1,70 @@
+use std::io::Write;
+
+use anyhow::{bail, Context, Result};
+use clap::Clap;
+use serde::Serialize;
+
+use crate::cmd::get::OutputOpts;
+use crate::external;
+use crate::external::gtfsdb::ColumnDescription;
+use crate::io::Format;
+
+#[derive(Clap, Debug)]
+pub struct Opts {
+    /// 表示するテーブル名 (ex: trips)
+    table: String,
+    #[clap(flatten)]
+    output: OutputOpts,
+}
+
+/// テーブルのカラム定義. 区分値のカラムは取りうる値も含む
+#[derive(Debug, Serialize)]
+struct TableDescription {
+    table_name: &'static str,
+    columns: Vec<ColumnDescription>,
+}
+
+pub fn run(op: &Opts) -> Result<()> {
+    let format = op.output.format_or(Format::Json);
+    // カラムごとに取りうる値が入れ子になるため, json, pjson, yamlのみ対応する
+    if !matches!(format, Format::Json | Format::Pjson | Format::Yaml) {
+        bail!(
+            "describeは{:?}形式に対応していません (json, pjson, yamlのみ)",
+            format
+        );
+    }
+    if op.output.has_columns() {
+        bail!("describeは--columnsに対応していません");
+    }
+    let descriptions = external::gtfsdb::descriptions();
+    let table_names: Vec<&str> = descriptions.iter().map(|(name, _)| *name).collect();
+    let (table_name, columns) = descriptions
+        .into_iter()
+        .find(|(name, _)| *name == op.table)
+        .with_context(|| {
+            format!(
+                "テーブルが見つかりません: {} (指定できるテーブル: {})",
+                op.table,
+                table_names.join(", ")
+            )
+        })?;
+    let description = TableDescription {
+        table_name,
+        columns,
+    };
+
+    op.output.write_with(|out| {
+        match format {
+            Format::Pjson => {
+                serde_json::to_writer_pretty(&mut *out, &description)?;
+                writeln!(out)?;
+            }
+            Format::Yaml => serde_yaml::to_writer(&mut *out, &description)?,
+            _ => {
+                serde_json::to_writer(&mut *out, &description)?;
+                writeln!(out)?;
+            }
+        }
+        Ok(())
+    })
+}
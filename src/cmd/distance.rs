@@ -0,0 +1,30 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::Clap;
+use strum::VariantNames;
+
+use crate::app::stop::StopService;
+use crate::io::Format;
+use crate::{external, io};
+
+#[derive(Clap, Debug)]
+pub struct Opts {
+    #[clap(short, long, parse(from_os_str), default_value = "hibou.db")]
+    database: PathBuf,
+    #[clap(short, long, default_value = "csv", possible_values(Format::VARIANTS))]
+    format: Format,
+    /// 起点の停留所・標柱ID
+    #[clap(long)]
+    from_stop: String,
+    /// 終点の停留所・標柱ID
+    #[clap(long)]
+    to_stop: String,
+}
+
+pub fn run(op: &Opts) -> Result<()> {
+    let gtfs = external::gtfsdb::open(&op.database)?;
+    let distance = StopService::new(gtfs).distance(&op.from_stop, &op.to_stop)?;
+    io::write_stdout(&[distance], &op.format)?;
+    Ok(())
+}
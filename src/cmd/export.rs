@@ -0,0 +1,23 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::Clap;
+
+use crate::app::export::ExportService;
+use crate::external;
+use crate::external::gtfszip::GtfsZip;
+
+#[derive(Clap, Debug)]
+pub struct Opts {
+    #[clap(short, long, parse(from_os_str), default_value = "hibou.db")]
+    database: PathBuf,
+    /// 出力するGTFSのzipファイル
+    #[clap(short, long, parse(from_os_str))]
+    output: PathBuf,
+}
+
+pub fn run(op: &Opts) -> Result<()> {
+    let gtfs = external::gtfsdb::init(&op.database)?;
+    let zip = GtfsZip::create(&op.output)?;
+    ExportService::new(gtfs, zip).export()
+}
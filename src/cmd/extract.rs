@@ -0,0 +1,34 @@
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{bail, Result};
+use clap::Clap;
+
+use crate::app::extract::ExtractService;
+use crate::external;
+
+#[derive(Clap, Debug)]
+pub struct Opts {
+    #[clap(short, long, parse(from_os_str), default_value = "hibou.db")]
+    database: PathBuf,
+    /// 書き出すデータベース (既に存在する場合はエラー)
+    #[clap(short, long, parse(from_os_str))]
+    output: PathBuf,
+    /// 取り出す経路ID. カンマ区切りで複数指定できる (ex: R1,R2)
+    #[clap(long, required = true, use_delimiter = true)]
+    route_id: Vec<String>,
+}
+
+pub fn run(op: &Opts) -> Result<()> {
+    if op.output.exists() {
+        bail!("{:?} は既に存在します", op.output);
+    }
+    let source = external::gtfsdb::init(&op.database)?;
+    let target = Box::new(external::gtfsdb::create(&op.output)?);
+    let result = ExtractService::new(source, target).extract(&op.route_id);
+    // 途中で失敗した場合は不完全なデータベースを残さない
+    if result.is_err() {
+        let _ = fs::remove_file(&op.output);
+    }
+    result
+}
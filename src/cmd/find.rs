@@ -0,0 +1,28 @@
+use anyhow::Result;
+use clap::Clap;
+
+use crate::cmd;
+
+pub mod route;
+pub mod stop;
+
+#[derive(Clap, Debug)]
+pub struct Opts {
+    #[clap(subcommand)]
+    pub subcmd: SubCommand,
+}
+
+#[derive(Clap, Debug)]
+pub enum SubCommand {
+    /// 名称で停留所・標柱を検索する
+    Stop(cmd::find::stop::Opts),
+    /// 経路略称・経路名で経路を検索する
+    Route(cmd::find::route::Opts),
+}
+
+pub fn run(opts: &Opts) -> Result<()> {
+    match &opts.subcmd {
+        SubCommand::Stop(op) => cmd::find::stop::run(op),
+        SubCommand::Route(op) => cmd::find::route::run(op),
+    }
+}
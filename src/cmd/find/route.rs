@@ -0,0 +1,31 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::Clap;
+
+use crate::app::route::RouteService;
+use crate::cmd::get::{OutputOpts, QueryOpts};
+use crate::external;
+
+#[derive(Clap, Debug)]
+pub struct Opts {
+    #[clap(short, long, parse(from_os_str), default_value = "hibou.db")]
+    database: PathBuf,
+    #[clap(flatten)]
+    output: OutputOpts,
+    /// 経路略称または経路名の一部 (ex: 東16). 英字の大文字・小文字は区別しない
+    #[clap(long)]
+    name: String,
+    #[clap(flatten)]
+    query: QueryOpts,
+}
+
+pub fn run(op: &Opts) -> Result<()> {
+    let gtfs = external::gtfsdb::open(&op.database)?;
+    let routes = RouteService::new(gtfs).find_by_name(&op.name, &op.query.to_query())?;
+    if op.query.count {
+        return op.output.write_count(routes.len() as u32);
+    }
+    op.output.write(&routes)?;
+    Ok(())
+}
@@ -0,0 +1,33 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::Clap;
+use strum::VariantNames;
+
+use crate::app::stop::StopService;
+use crate::cmd::get::QueryOpts;
+use crate::io::Format;
+use crate::{external, io};
+
+#[derive(Clap, Debug)]
+pub struct Opts {
+    #[clap(short, long, parse(from_os_str), default_value = "hibou.db")]
+    database: PathBuf,
+    #[clap(short, long, default_value = "csv", possible_values(Format::VARIANTS))]
+    format: Format,
+    /// 停留所・標柱名称の一部 (ex: 月島)
+    #[clap(long)]
+    name: String,
+    #[clap(flatten)]
+    query: QueryOpts,
+}
+
+pub fn run(op: &Opts) -> Result<()> {
+    let gtfs = external::gtfsdb::open(&op.database)?;
+    let stops = StopService::new(gtfs).find_by_name(&op.name, &op.query.to_query())?;
+    if op.query.count {
+        return io::write_count(std::io::stdout().lock(), stops.len() as u32, &op.format);
+    }
+    io::write_geo_stdout(&stops, &op.format)?;
+    Ok(())
+}
@@ -1,10 +1,40 @@
-use anyhow::Result;
+use std::fs::File;
+use std::io;
+use std::io::{BufWriter, IsTerminal, Write};
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
+use chrono::NaiveDate;
+use chrono_tz::Tz;
 use clap::Clap;
+use serde::Serialize;
+use strum::VariantNames;
 
 use crate::cmd;
+use crate::external::gtfs::Query;
+use crate::external::gtfsdb::Table;
+use crate::io::geojson::ToGeoFeature;
+use crate::io::projection;
+use crate::io::{Format, GzipWriter, Output};
 
+pub mod active_trips;
+pub mod agencies;
+pub mod attributions;
+pub mod block;
+pub mod calendar;
+pub mod fare;
+pub mod frequencies;
+pub mod offices;
 pub mod routes;
+pub mod shapes;
+pub mod stop_times;
+pub mod stops;
+pub mod timetable;
+pub mod transfers;
+pub mod translations;
+pub mod trip_endpoints;
 pub mod trips;
+pub mod trips_between;
 
 #[derive(Clap, Debug)]
 pub struct Opts {
@@ -12,17 +42,281 @@ pub struct Opts {
     pub subcmd: SubCommand,
 }
 
+/// 各getコマンドに共通する取得条件
+#[derive(Clap, Debug)]
+pub struct QueryOpts {
+    /// 最大件数
+    #[clap(long)]
+    limit: Option<u32>,
+    /// 先頭から読み飛ばす件数
+    #[clap(long)]
+    offset: Option<u32>,
+    /// SQLのWHERE句で絞り込む (ex: "trip_headsign LIKE '%東京%'")
+    /// 入力はそのままSQLに埋め込まれるため, 信頼できる条件のみ指定すること
+    #[clap(long = "where")]
+    condition: Option<String>,
+    /// レコードを出力せず, 条件に当てはまる件数だけを出力する
+    #[clap(long)]
+    pub count: bool,
+}
+
+impl QueryOpts {
+    pub fn to_query(&self) -> Query {
+        Query {
+            limit: self.limit,
+            offset: self.offset,
+            condition: self.condition.clone(),
+        }
+    }
+}
+
+/// 時刻をタイムゾーン付きの日時に変換する条件
+#[derive(Clap, Debug)]
+pub struct TimeZoneOpts {
+    /// 時刻を変換するタイムゾーン (ex: UTC, America/New_York). 省略時はGTFSの時刻のまま出力する
+    #[clap(long, requires = "date")]
+    timezone: Option<Tz>,
+    /// 変換に使う運行日 (ex: 2024-05-03)
+    #[clap(long, requires = "timezone")]
+    date: Option<NaiveDate>,
+}
+
+impl TimeZoneOpts {
+    pub fn to_zone(&self) -> Option<(NaiveDate, Tz)> {
+        Some((self.date?, self.timezone?))
+    }
+}
+
+/// 各getコマンドに共通する出力先
+#[derive(Clap, Debug)]
+pub struct OutputOpts {
+    /// 出力形式 (省略時は--outputの拡張子から判断し, 判断できなければcsv). parquetは--outputの指定が必要
+    #[clap(short, long, possible_values(Format::VARIANTS))]
+    format: Option<Format>,
+    /// 出力先のファイル (省略時は標準出力)
+    #[clap(short, long, parse(from_os_str))]
+    output: Option<PathBuf>,
+    /// 出力するカラムをカンマ区切りで指定する (ex: trip_id,stop_id,departure_time)
+    #[clap(long, use_delimiter = true)]
+    columns: Option<Vec<String>>,
+    /// json形式を整形して出力する (省略時は端末への出力のみ整形する)
+    #[clap(long, conflicts_with = "compact")]
+    pretty: bool,
+    /// json形式を整形せずに出力する
+    #[clap(long)]
+    compact: bool,
+    /// gzipで圧縮して出力する (--outputの拡張子が.gzなら省略できる)
+    #[clap(long)]
+    gzip: bool,
+    /// csv, tsv形式でヘッダー行を出力しない (既存のファイルへの追記などに使う)
+    #[clap(long)]
+    no_headers: bool,
+}
+
+impl OutputOpts {
+    pub fn format(&self) -> Format {
+        self.format_or(Format::Csv)
+    }
+
+    /// 形式の指定がなく拡張子からも判断できない場合はdefaultを使う
+    pub fn format_or(&self, default: Format) -> Format {
+        let format = self
+            .format
+            .or_else(|| self.output.as_deref().and_then(Format::from_path))
+            .unwrap_or(default);
+        match format {
+            Format::Json | Format::Pjson if self.compact => Format::Json,
+            Format::Json | Format::Pjson if self.pretty => Format::Pjson,
+            // 人が読む端末には整形し, パイプやファイルには大きなデータ向けに詰めて出力する
+            Format::Json if self.output.is_none() && io::stdout().is_terminal() => Format::Pjson,
+            _ => format,
+        }
+    }
+
+    /// --columnsの指定があればそのカラムだけを出力する
+    pub fn write<T>(&self, records: &[T]) -> Result<()>
+    where
+        T: Serialize + Table,
+    {
+        self.write_as::<T, T>(records)
+    }
+
+    /// Tと同じカラムを持つレコードを出力する. --columnsはTのカラムとして検査する
+    pub fn write_as<T, R>(&self, records: &[R]) -> Result<()>
+    where
+        T: Table,
+        R: Serialize,
+    {
+        if let (Format::Geojson, true) = (self.format(), self.has_columns()) {
+            bail!("geojson形式は--columnsと併用できません. --columnsを外すか, 他の形式を指定してください");
+        }
+        let definitions = T::columns();
+        match self.columns::<T>()? {
+            Some(columns) => {
+                let definitions: Vec<_> = columns
+                    .iter()
+                    .filter_map(|c| definitions.iter().find(|x| x.name == *c).cloned())
+                    .collect();
+                self.write_with(|out| {
+                    crate::io::write_table(
+                        out,
+                        &projection::project(records, &columns),
+                        &self.format(),
+                        self.headers(),
+                        &definitions,
+                    )
+                })
+            }
+            None => self.write_with(|out| {
+                crate::io::write_table(out, records, &self.format(), self.headers(), &definitions)
+            }),
+        }
+    }
+
+    /// 座標を持つレコード用. geojson形式は--columnsと併用できない
+    pub fn write_geo<T>(&self, records: &[T]) -> Result<()>
+    where
+        T: ToGeoFeature + Table,
+    {
+        match self.format() {
+            Format::Geojson if !self.has_columns() => self.write_with(|out| {
+                crate::io::write_geo(out, records, &Format::Geojson, self.headers())
+            }),
+            _ => self.write(records),
+        }
+    }
+
+    /// 条件に当てはまる件数だけを出力する
+    pub fn write_count(&self, count: u32) -> Result<()> {
+        self.write_with(|out| crate::io::write_count(out, count, &self.format()))
+    }
+
+    /// csv, tsv形式でヘッダー行を出力するか
+    pub fn headers(&self) -> bool {
+        !self.no_headers
+    }
+
+    pub fn has_columns(&self) -> bool {
+        self.columns.is_some()
+    }
+
+    /// 指定されたカラムがテーブルに存在するか検査する
+    pub fn columns<T: Table>(&self) -> Result<Option<Vec<&'static str>>> {
+        let columns = match &self.columns {
+            Some(x) => x,
+            None => return Ok(None),
+        };
+        columns
+            .iter()
+            .map(|c| {
+                T::column_names()
+                    .iter()
+                    .find(|x| *x == c)
+                    .copied()
+                    .with_context(|| {
+                        format!(
+                            "{} に {} というカラムはありません (指定できるカラム: {})",
+                            T::table_name(),
+                            c,
+                            T::column_names().join(", ")
+                        )
+                    })
+            })
+            .collect::<Result<Vec<_>>>()
+            .map(Some)
+    }
+
+    /// 出力先のファイル. 指定がなければ標準出力
+    pub fn writer(&self) -> Result<Output> {
+        let gzip = self.gzip || self.output.as_deref().is_some_and(crate::io::is_gzip_path);
+        let out: Box<dyn Write> = match &self.output {
+            Some(path) => {
+                let file = File::create(path)
+                    .with_context(|| format!("{:?} に書き込めませんでした", path))?;
+                Box::new(BufWriter::new(file))
+            }
+            None if matches!(self.format, Some(Format::Parquet)) => {
+                bail!("parquet形式は--outputでファイルを指定してください")
+            }
+            None => Box::new(io::stdout().lock()),
+        };
+        if gzip {
+            Ok(Output::Gzip(Box::new(GzipWriter::new(out))))
+        } else {
+            Ok(Output::Plain(out))
+        }
+    }
+
+    /// 出力先に書き込み, gzipで圧縮する場合は末尾まで書き出す
+    pub fn write_with<F>(&self, write: F) -> Result<()>
+    where
+        F: FnOnce(&mut Output) -> Result<()>,
+    {
+        let mut out = self.writer()?;
+        write(&mut out)?;
+        out.finish().context("出力の書き込みに失敗しました")
+    }
+}
+
 #[derive(Clap, Debug)]
 pub enum SubCommand {
+    /// agencies
+    Agencies(cmd::get::agencies::Opts),
+    /// calendar
+    Calendar(cmd::get::calendar::Opts),
     /// routes
     Routes(cmd::get::routes::Opts),
+    /// stops
+    Stops(cmd::get::stops::Opts),
+    /// stop_times
+    StopTimes(cmd::get::stop_times::Opts),
     /// trips
     Trips(cmd::get::trips::Opts),
+    /// 経路の各便の始発と終着 (trips, stop_times, stopsを結合)
+    TripEndpoints(cmd::get::trip_endpoints::Opts),
+    /// 2つの標柱をこの順に通る便 (trips, stop_timesを結合)
+    TripsBetween(cmd::get::trips_between::Opts),
+    /// 指定した日に運行する便 (calendar, calendar_datesから判定)
+    ActiveTrips(cmd::get::active_trips::Opts),
+    /// 同じ車両で続けて運行する便 (trips, stop_timesを結合)
+    Block(cmd::get::block::Opts),
+    /// 経路の時刻表 (trips, stop_times, stopsを結合)
+    Timetable(cmd::get::timetable::Opts),
+    /// 乗車条件に当てはまる運賃 (fare_rules, fare_attributesから判定)
+    Fare(cmd::get::fare::Opts),
+    /// shapes (geojson形式では描画IDごとの線形)
+    Shapes(cmd::get::shapes::Opts),
+    /// frequencies
+    Frequencies(cmd::get::frequencies::Opts),
+    /// 標柱間の乗換 (transfers, stopsを結合)
+    Transfers(cmd::get::transfers::Opts),
+    /// office_jp (営業所)
+    Offices(cmd::get::offices::Opts),
+    /// attributions
+    Attributions(cmd::get::attributions::Opts),
+    /// translations
+    Translations(cmd::get::translations::Opts),
 }
 
 pub fn run(opts: &Opts) -> Result<()> {
     match &opts.subcmd {
-        SubCommand::Trips(op) => cmd::get::trips::run(&op),
-        SubCommand::Routes(op) => cmd::get::routes::run(&op),
+        SubCommand::Agencies(op) => cmd::get::agencies::run(op),
+        SubCommand::Calendar(op) => cmd::get::calendar::run(op),
+        SubCommand::Trips(op) => cmd::get::trips::run(op),
+        SubCommand::TripEndpoints(op) => cmd::get::trip_endpoints::run(op),
+        SubCommand::TripsBetween(op) => cmd::get::trips_between::run(op),
+        SubCommand::ActiveTrips(op) => cmd::get::active_trips::run(op),
+        SubCommand::Block(op) => cmd::get::block::run(op),
+        SubCommand::Timetable(op) => cmd::get::timetable::run(op),
+        SubCommand::Routes(op) => cmd::get::routes::run(op),
+        SubCommand::Stops(op) => cmd::get::stops::run(op),
+        SubCommand::StopTimes(op) => cmd::get::stop_times::run(op),
+        SubCommand::Fare(op) => cmd::get::fare::run(op),
+        SubCommand::Shapes(op) => cmd::get::shapes::run(op),
+        SubCommand::Frequencies(op) => cmd::get::frequencies::run(op),
+        SubCommand::Transfers(op) => cmd::get::transfers::run(op),
+        SubCommand::Offices(op) => cmd::get::offices::run(op),
+        SubCommand::Attributions(op) => cmd::get::attributions::run(op),
+        SubCommand::Translations(op) => cmd::get::translations::run(op),
     }
 }
@@ -0,0 +1,33 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use chrono::NaiveDate;
+use clap::Clap;
+
+use crate::app::trip::TripService;
+use crate::cmd::get::{OutputOpts, QueryOpts};
+use crate::external;
+
+#[derive(Clap, Debug)]
+pub struct Opts {
+    #[clap(short, long, parse(from_os_str), default_value = "hibou.db")]
+    database: PathBuf,
+    #[clap(flatten)]
+    output: OutputOpts,
+    /// 運行日 (ex: 2024-05-03)
+    #[clap(long)]
+    date: NaiveDate,
+    #[clap(flatten)]
+    query: QueryOpts,
+}
+
+pub fn run(op: &Opts) -> Result<()> {
+    let gtfs = external::gtfsdb::open(&op.database)?;
+    let trips = TripService::new(gtfs).fetch_active(op.date, &op.query.to_query())?;
+    // 運行日の判定はSQLでできないため, 取得した件数を数える
+    if op.query.count {
+        return op.output.write_count(trips.len() as u32);
+    }
+    op.output.write(&trips)?;
+    Ok(())
+}
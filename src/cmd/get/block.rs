@@ -0,0 +1,38 @@
+use std::path::PathBuf;
+
+use anyhow::{bail, Result};
+use clap::Clap;
+
+use crate::app::block::BlockService;
+use crate::cmd::get::OutputOpts;
+use crate::{external, io};
+
+#[derive(Clap, Debug)]
+pub struct Opts {
+    #[clap(short, long, parse(from_os_str), default_value = "hibou.db")]
+    database: PathBuf,
+    #[clap(flatten)]
+    output: OutputOpts,
+    /// 便結合区分
+    #[clap(long, required_unless_present = "trip-id", conflicts_with = "trip-id")]
+    block_id: Option<String>,
+    /// 便ID (この便と同じblock_idの便を取得する)
+    #[clap(long)]
+    trip_id: Option<String>,
+}
+
+pub fn run(op: &Opts) -> Result<()> {
+    if op.output.has_columns() {
+        bail!("blockはtripsとstop_timesを結合した結果のため--columnsに対応していません");
+    }
+    let gtfs = external::gtfsdb::init(&op.database)?;
+    let mut service = BlockService::new(gtfs);
+    let trips = match (&op.block_id, &op.trip_id) {
+        (Some(block_id), _) => service.fetch(block_id)?,
+        (None, Some(trip_id)) => service.fetch_by_trip_id(trip_id)?,
+        (None, None) => bail!("--block-idか--trip-idを指定してください"),
+    };
+    op.output
+        .write_with(|out| io::write(out, &trips, &op.output.format(), op.output.headers()))?;
+    Ok(())
+}
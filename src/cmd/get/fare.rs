@@ -0,0 +1,47 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::Clap;
+
+use crate::app::fare::{FareService, Ride};
+use crate::cmd::get::{OutputOpts, QueryOpts};
+use crate::external;
+
+#[derive(Clap, Debug)]
+pub struct Opts {
+    #[clap(short, long, parse(from_os_str), default_value = "hibou.db")]
+    database: PathBuf,
+    #[clap(flatten)]
+    output: OutputOpts,
+    /// 乗車する経路のID
+    #[clap(long)]
+    route_id: Option<String>,
+    /// 乗車地のゾーンID (stops.zone_id)
+    #[clap(long)]
+    origin: Option<String>,
+    /// 降車地のゾーンID (stops.zone_id)
+    #[clap(long)]
+    destination: Option<String>,
+    /// 通過するゾーンIDをカンマ区切りで指定する
+    #[clap(long, use_delimiter = true)]
+    contains: Vec<String>,
+    #[clap(flatten)]
+    query: QueryOpts,
+}
+
+pub fn run(op: &Opts) -> Result<()> {
+    let gtfs = external::gtfsdb::init(&op.database)?;
+    let ride = Ride {
+        route_id: op.route_id.clone(),
+        origin_id: op.origin.clone(),
+        destination_id: op.destination.clone(),
+        contains_ids: op.contains.clone(),
+    };
+    let fares = FareService::new(gtfs).fetch_applicable(&ride, &op.query.to_query())?;
+    // 運賃が当てはまるかはSQLで判定できないため, 取得した件数を数える
+    if op.query.count {
+        return op.output.write_count(fares.len() as u32);
+    }
+    op.output.write(&fares)?;
+    Ok(())
+}
@@ -0,0 +1,33 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::Clap;
+
+use crate::app::frequency::FrequencyService;
+use crate::cmd::get::{OutputOpts, QueryOpts};
+use crate::external;
+
+#[derive(Clap, Debug)]
+pub struct Opts {
+    #[clap(short, long, parse(from_os_str), default_value = "hibou.db")]
+    database: PathBuf,
+    #[clap(flatten)]
+    output: OutputOpts,
+    /// 便IDで絞り込む
+    #[clap(long)]
+    trip_id: Option<String>,
+    #[clap(flatten)]
+    query: QueryOpts,
+}
+
+pub fn run(op: &Opts) -> Result<()> {
+    let gtfs = external::gtfsdb::open(&op.database)?;
+    let mut service = FrequencyService::new(gtfs);
+    if op.query.count {
+        let count = service.count(op.trip_id.as_ref(), &op.query.to_query())?;
+        return op.output.write_count(count);
+    }
+    let frequencies = service.fetch(op.trip_id.as_ref(), &op.query.to_query())?;
+    op.output.write(&frequencies)?;
+    Ok(())
+}
@@ -2,23 +2,62 @@ use std::path::PathBuf;
 
 use anyhow::Result;
 use clap::Clap;
-use strum::VariantNames;
 
-use crate::app::route::RouteService;
+use crate::app::route::{ColoredRoute, RouteService};
+use crate::cmd::get::{OutputOpts, QueryOpts};
+use crate::external;
+use crate::external::gtfs::routes::Route;
+use crate::io;
 use crate::io::Format;
-use crate::{external, io};
 
 #[derive(Clap, Debug)]
 pub struct Opts {
     #[clap(short, long, parse(from_os_str), default_value = "hibou.db")]
     database: PathBuf,
-    #[clap(short, long, default_value = "csv", possible_values(Format::VARIANTS))]
-    format: Format,
+    #[clap(flatten)]
+    output: OutputOpts,
+    /// 経路IDで絞り込む
+    #[clap(long, conflicts_with = "agency-id")]
+    route_id: Option<String>,
+    /// 事業者IDで絞り込む
+    #[clap(long)]
+    agency_id: Option<String>,
+    #[clap(flatten)]
+    query: QueryOpts,
 }
 
 pub fn run(op: &Opts) -> Result<()> {
-    let gtfs = external::gtfsdb::init(&op.database)?;
-    let routes = RouteService::new(gtfs).fetch()?;
-    io::write(&routes, &op.format)?;
+    let gtfs = external::gtfsdb::open(&op.database)?;
+    let mut service = RouteService::new(gtfs);
+    if op.query.count {
+        let count = service.count(
+            op.route_id.as_ref(),
+            op.agency_id.as_ref(),
+            &op.query.to_query(),
+        )?;
+        return op.output.write_count(count);
+    }
+    let routes = service.fetch(
+        op.route_id.as_ref(),
+        op.agency_id.as_ref(),
+        &op.query.to_query(),
+    )?;
+    match op.output.format() {
+        // geojson形式では経路の線形をshapesから組み立てる
+        Format::Geojson if !op.output.has_columns() => {
+            let lines = service.fetch_lines(routes)?;
+            op.output.write_with(|out| {
+                io::write_geo(out, &lines, &Format::Geojson, op.output.headers())
+            })?;
+        }
+        // csv, tsv, parquet形式と--columnsの指定時はテーブルと同じカラムのまま出力する
+        Format::Json | Format::Pjson | Format::Ndjson | Format::Yaml
+            if !op.output.has_columns() =>
+        {
+            let routes: Vec<ColoredRoute> = routes.into_iter().map(ColoredRoute::from).collect();
+            op.output.write_as::<Route, _>(&routes)?
+        }
+        _ => op.output.write(&routes)?,
+    }
     Ok(())
 }
@@ -0,0 +1,46 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::Clap;
+
+use crate::app::shape::ShapeService;
+use crate::cmd::get::{OutputOpts, QueryOpts};
+use crate::external;
+use crate::io;
+use crate::io::Format;
+
+#[derive(Clap, Debug)]
+pub struct Opts {
+    #[clap(short, long, parse(from_os_str), default_value = "hibou.db")]
+    database: PathBuf,
+    #[clap(flatten)]
+    output: OutputOpts,
+    /// 描画IDで絞り込む
+    #[clap(long)]
+    shape_id: Option<String>,
+    #[clap(flatten)]
+    query: QueryOpts,
+}
+
+pub fn run(op: &Opts) -> Result<()> {
+    let gtfs = external::gtfsdb::open(&op.database)?;
+    let mut service = ShapeService::new(gtfs);
+    if op.query.count {
+        let count = service.count(op.shape_id.as_ref(), &op.query.to_query())?;
+        return op.output.write_count(count);
+    }
+    match op.output.format() {
+        // geojson形式では描画IDごとに描画点を結んだ線形にする
+        Format::Geojson if !op.output.has_columns() => {
+            let lines = service.fetch_lines(op.shape_id.as_ref(), &op.query.to_query())?;
+            op.output.write_with(|out| {
+                io::write_geo(out, &lines, &Format::Geojson, op.output.headers())
+            })?;
+        }
+        _ => {
+            let shapes = service.fetch(op.shape_id.as_ref(), &op.query.to_query())?;
+            op.output.write(&shapes)?
+        }
+    }
+    Ok(())
+}
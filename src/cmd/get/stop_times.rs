@@ -0,0 +1,47 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::Clap;
+
+use crate::app::stop_time::StopTimeService;
+use crate::cmd::get::{OutputOpts, QueryOpts, TimeZoneOpts};
+use crate::external;
+use crate::external::gtfs::stop_times::StopTime;
+
+#[derive(Clap, Debug)]
+pub struct Opts {
+    #[clap(short, long, parse(from_os_str), default_value = "hibou.db")]
+    database: PathBuf,
+    #[clap(flatten)]
+    output: OutputOpts,
+    /// 便IDで絞り込む
+    #[clap(long, required_unless_present = "all")]
+    trip_id: Option<String>,
+    /// 全件を出力する (件数が非常に多くなることがあるため明示的な指定が必要)
+    #[clap(long, conflicts_with = "trip-id")]
+    all: bool,
+    #[clap(flatten)]
+    query: QueryOpts,
+    #[clap(flatten)]
+    zone: TimeZoneOpts,
+}
+
+pub fn run(op: &Opts) -> Result<()> {
+    let gtfs = external::gtfsdb::open(&op.database)?;
+    let trip_id = if op.all { None } else { op.trip_id.as_ref() };
+    let mut service = StopTimeService::new(gtfs);
+    if op.query.count {
+        return op
+            .output
+            .write_count(service.count(trip_id, &op.query.to_query())?);
+    }
+    let stop_times = service.fetch(trip_id, &op.query.to_query())?;
+    match op.zone.to_zone() {
+        Some((date, tz)) => {
+            op.output
+                .write_as::<StopTime, _>(&service.zoned(&stop_times, date, tz)?)?
+        }
+        None => op.output.write(&stop_times)?,
+    }
+    Ok(())
+}
@@ -0,0 +1,64 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::Clap;
+use strum::VariantNames;
+
+use crate::app::stop::StopService;
+use crate::cmd::get::{OutputOpts, QueryOpts};
+use crate::external;
+use crate::external::gtfs::stops::WheelchairBoarding;
+use crate::geo::BoundingBox;
+
+#[derive(Clap, Debug)]
+pub struct Opts {
+    #[clap(short, long, parse(from_os_str), default_value = "hibou.db")]
+    database: PathBuf,
+    #[clap(flatten)]
+    output: OutputOpts,
+    /// 経路IDで絞り込む (その経路の便が停車する停留所・標柱のみ)
+    #[clap(long)]
+    route_id: Option<String>,
+    /// 親駅の停留所IDで絞り込む (その停留所に属する標柱・出入口など)
+    #[clap(long, conflicts_with = "route-id")]
+    parent_station: Option<String>,
+    /// 車椅子での乗車可否で絞り込む
+    #[clap(long, possible_values(WheelchairBoarding::VARIANTS))]
+    wheelchair: Option<WheelchairBoarding>,
+    /// 経度・緯度の範囲で絞り込む (ex: 139.7,35.6,139.8,35.7)
+    /// 最小経度,最小緯度,最大経度,最大緯度の順に指定し, 境界上のstopも含める
+    #[clap(long)]
+    bbox: Option<BoundingBox>,
+    #[clap(flatten)]
+    query: QueryOpts,
+}
+
+pub fn run(op: &Opts) -> Result<()> {
+    let gtfs = external::gtfsdb::open(&op.database)?;
+    let mut service = StopService::new(gtfs);
+    let mut query = op.query.to_query();
+    if let Some(wheelchair) = op.wheelchair {
+        query = query.and(format!("wheelchair_boarding = {}", wheelchair as u8));
+    }
+    if let Some(b) = op.bbox {
+        // 座標はf32の精度で登録されているため, 境界上のstopを落とさないよう範囲も同じ精度に丸める
+        let round = |x: f64| f64::from(x as f32);
+        query = query.and(format!(
+            "stop_lon BETWEEN {} AND {} AND stop_lat BETWEEN {} AND {}",
+            round(b.min_lon),
+            round(b.max_lon),
+            round(b.min_lat),
+            round(b.max_lat)
+        ));
+    }
+    if op.query.count {
+        let count = service.count(op.route_id.as_ref(), op.parent_station.as_ref(), &query)?;
+        return op.output.write_count(count);
+    }
+    let stops = match &op.parent_station {
+        Some(parent) => service.children(parent, &query)?,
+        None => service.fetch(op.route_id.as_ref(), &query)?,
+    };
+    op.output.write_geo(&stops)?;
+    Ok(())
+}
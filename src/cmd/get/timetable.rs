@@ -0,0 +1,41 @@
+use std::path::PathBuf;
+
+use anyhow::{bail, Result};
+use clap::Clap;
+
+use crate::app::timetable::TimetableService;
+use crate::cmd::get::{OutputOpts, TimeZoneOpts};
+use crate::io::Format;
+use crate::{external, io};
+
+#[derive(Clap, Debug)]
+pub struct Opts {
+    #[clap(short, long, parse(from_os_str), default_value = "hibou.db")]
+    database: PathBuf,
+    #[clap(flatten)]
+    output: OutputOpts,
+    /// 経路ID
+    #[clap(long)]
+    route_id: String,
+    #[clap(flatten)]
+    zone: TimeZoneOpts,
+    /// 行き先が空の便は経路名, それもなければ終着の標柱名称で補う
+    #[clap(long)]
+    fill_headsign: bool,
+}
+
+pub fn run(op: &Opts) -> Result<()> {
+    let format = op.output.format_or(Format::Json);
+    if let Format::Csv | Format::Tsv = format {
+        bail!("時刻表は入れ子構造のためcsv, tsv形式に対応していません");
+    }
+    if op.output.has_columns() {
+        bail!("時刻表は入れ子構造のため--columnsに対応していません");
+    }
+    let gtfs = external::gtfsdb::init(&op.database)?;
+    let timetables =
+        TimetableService::new(gtfs).fetch(&op.route_id, op.zone.to_zone(), op.fill_headsign)?;
+    op.output
+        .write_with(|out| io::write(out, &timetables, &format, op.output.headers()))?;
+    Ok(())
+}
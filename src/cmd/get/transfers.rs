@@ -0,0 +1,34 @@
+use std::path::PathBuf;
+
+use anyhow::{bail, Result};
+use clap::Clap;
+
+use crate::app::transfer::TransferService;
+use crate::cmd::get::OutputOpts;
+use crate::{external, io};
+
+#[derive(Clap, Debug)]
+pub struct Opts {
+    #[clap(short, long, parse(from_os_str), default_value = "hibou.db")]
+    database: PathBuf,
+    #[clap(flatten)]
+    output: OutputOpts,
+    /// 乗換元の標柱ID
+    #[clap(long)]
+    from_stop: Option<String>,
+    /// 乗換先の標柱ID
+    #[clap(long)]
+    to_stop: Option<String>,
+}
+
+pub fn run(op: &Opts) -> Result<()> {
+    if op.output.has_columns() {
+        bail!("乗換はstopsと結合した結果のため--columnsに対応していません");
+    }
+    let gtfs = external::gtfsdb::init(&op.database)?;
+    let connections =
+        TransferService::new(gtfs).fetch_connections(op.from_stop.as_ref(), op.to_stop.as_ref())?;
+    op.output
+        .write_with(|out| io::write(out, &connections, &op.output.format(), op.output.headers()))?;
+    Ok(())
+}
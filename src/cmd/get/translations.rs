@@ -0,0 +1,29 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::Clap;
+
+use crate::app::translation::TranslationService;
+use crate::cmd::get::{OutputOpts, QueryOpts};
+use crate::external;
+
+#[derive(Clap, Debug)]
+pub struct Opts {
+    #[clap(short, long, parse(from_os_str), default_value = "hibou.db")]
+    database: PathBuf,
+    #[clap(flatten)]
+    output: OutputOpts,
+    #[clap(flatten)]
+    query: QueryOpts,
+}
+
+pub fn run(op: &Opts) -> Result<()> {
+    let gtfs = external::gtfsdb::open(&op.database)?;
+    let mut service = TranslationService::new(gtfs);
+    if op.query.count {
+        return op.output.write_count(service.count(&op.query.to_query())?);
+    }
+    let translations = service.fetch(&op.query.to_query())?;
+    op.output.write(&translations)?;
+    Ok(())
+}
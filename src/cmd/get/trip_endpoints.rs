@@ -0,0 +1,30 @@
+use std::path::PathBuf;
+
+use anyhow::{bail, Result};
+use clap::Clap;
+
+use crate::app::trip::TripService;
+use crate::cmd::get::OutputOpts;
+use crate::{external, io};
+
+#[derive(Clap, Debug)]
+pub struct Opts {
+    #[clap(short, long, parse(from_os_str), default_value = "hibou.db")]
+    database: PathBuf,
+    #[clap(flatten)]
+    output: OutputOpts,
+    /// 経路ID
+    #[clap(long)]
+    route_id: String,
+}
+
+pub fn run(op: &Opts) -> Result<()> {
+    if op.output.has_columns() {
+        bail!("始発・終着はtripsとstop_timesを結合した結果のため--columnsに対応していません");
+    }
+    let gtfs = external::gtfsdb::open(&op.database)?;
+    let endpoints = TripService::new(gtfs).fetch_endpoints(&op.route_id)?;
+    op.output
+        .write_with(|out| io::write(out, &endpoints, &op.output.format(), op.output.headers()))?;
+    Ok(())
+}
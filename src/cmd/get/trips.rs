@@ -5,20 +5,42 @@ use clap::Clap;
 use strum::VariantNames;
 
 use crate::app::trip::TripService;
-use crate::io::Format;
-use crate::{external, io};
+use crate::cmd::get::{OutputOpts, QueryOpts};
+use crate::external;
+use crate::external::gtfs::trips::{Direction, Trip};
 
 #[derive(Clap, Debug)]
 pub struct Opts {
     #[clap(short, long, parse(from_os_str), default_value = "hibou.db")]
     database: PathBuf,
-    #[clap(short, long, default_value = "csv", possible_values(Format::VARIANTS))]
-    format: Format,
+    #[clap(flatten)]
+    output: OutputOpts,
+    /// 経路IDで絞り込む
+    #[clap(long)]
+    route_id: Option<String>,
+    /// 上下区分で絞り込む (上下区分が空の便は除く)
+    #[clap(long, possible_values(Direction::VARIANTS))]
+    direction: Option<Direction>,
+    #[clap(flatten)]
+    query: QueryOpts,
+    /// 行き先が空の便は経路名, それもなければ終着の標柱名称で補う
+    #[clap(long)]
+    fill_headsign: bool,
 }
 
 pub fn run(op: &Opts) -> Result<()> {
-    let gtfs = external::gtfsdb::init(&op.database)?;
-    let trips = TripService::new(gtfs).fetch()?;
-    io::write(&trips, &op.format)?;
+    let gtfs = external::gtfsdb::open(&op.database)?;
+    let mut service = TripService::new(gtfs);
+    if op.query.count {
+        let count = service.count(op.route_id.as_ref(), op.direction, &op.query.to_query())?;
+        return op.output.write_count(count);
+    }
+    let trips = service.fetch(op.route_id.as_ref(), op.direction, &op.query.to_query())?;
+    if op.fill_headsign {
+        op.output
+            .write_as::<Trip, _>(&service.fill_headsigns(&trips)?)?;
+    } else {
+        op.output.write(&trips)?;
+    }
     Ok(())
 }
@@ -0,0 +1,37 @@
+use std::path::PathBuf;
+
+use anyhow::{bail, Result};
+use chrono::NaiveDate;
+use clap::Clap;
+
+use crate::app::trip::TripService;
+use crate::cmd::get::OutputOpts;
+use crate::{external, io};
+
+#[derive(Clap, Debug)]
+pub struct Opts {
+    #[clap(short, long, parse(from_os_str), default_value = "hibou.db")]
+    database: PathBuf,
+    #[clap(flatten)]
+    output: OutputOpts,
+    /// 乗車する標柱ID
+    #[clap(long)]
+    from: String,
+    /// 降車する標柱ID
+    #[clap(long)]
+    to: String,
+    /// 運行日 (ex: 2024-05-03). 省略時は運行日で絞り込まない
+    #[clap(long)]
+    date: Option<NaiveDate>,
+}
+
+pub fn run(op: &Opts) -> Result<()> {
+    if op.output.has_columns() {
+        bail!("trips-betweenはtripsとstop_timesを結合した結果のため--columnsに対応していません");
+    }
+    let gtfs = external::gtfsdb::open(&op.database)?;
+    let trips = TripService::new(gtfs).fetch_between(&op.from, &op.to, op.date)?;
+    op.output
+        .write_with(|out| io::write(out, &trips, &op.output.format(), op.output.headers()))?;
+    Ok(())
+}
@@ -0,0 +1,42 @@
+use std::path::PathBuf;
+
+use anyhow::{bail, Result};
+use chrono::NaiveDate;
+use clap::Clap;
+
+use crate::app::headway::HeadwayService;
+use crate::cmd::get::OutputOpts;
+use crate::io::Format;
+use crate::{external, io};
+
+#[derive(Clap, Debug)]
+pub struct Opts {
+    #[clap(short, long, parse(from_os_str), default_value = "hibou.db")]
+    database: PathBuf,
+    #[clap(flatten)]
+    output: OutputOpts,
+    /// 標柱ID
+    #[clap(long)]
+    stop_id: String,
+    /// 経路ID (省略時は標柱に停車する全経路)
+    #[clap(long)]
+    route_id: Option<String>,
+    /// 運行日 (ex: 2024-05-03)
+    #[clap(long)]
+    date: NaiveDate,
+}
+
+pub fn run(op: &Opts) -> Result<()> {
+    let format = op.output.format_or(Format::Json);
+    if let Format::Csv | Format::Tsv = format {
+        bail!("運行間隔は入れ子構造のためcsv, tsv形式に対応していません");
+    }
+    if op.output.has_columns() {
+        bail!("運行間隔は入れ子構造のため--columnsに対応していません");
+    }
+    let gtfs = external::gtfsdb::init(&op.database)?;
+    let headways = HeadwayService::new(gtfs).compute(&op.stop_id, op.route_id.as_ref(), op.date)?;
+    op.output
+        .write_with(|out| io::write(out, &headways, &format, op.output.headers()))?;
+    Ok(())
+}
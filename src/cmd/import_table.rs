@@ -0,0 +1,44 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::Clap;
+use log::info;
+use strum::VariantNames;
+
+use crate::app::gtfs::GtfsService;
+use crate::external;
+use crate::external::gtfs::OnConflict;
+use crate::io::Encoding;
+
+#[derive(Clap, Debug)]
+pub struct Opts {
+    /// 登録するテーブル (ex: stops)
+    table: String,
+    /// 登録先のデータベース (テーブルがなければ作成する)
+    #[clap(short, long, parse(from_os_str), default_value = "hibou.db")]
+    database: PathBuf,
+    /// 主キーが同じレコードは置き換えて登録する
+    #[clap(long)]
+    upsert: bool,
+    /// CSVの文字コード
+    #[clap(long, default_value = "utf-8", possible_values(Encoding::VARIANTS))]
+    encoding: Encoding,
+}
+
+pub fn run(op: &Opts) -> Result<()> {
+    let gtfs_csv = external::gtfscsv::init_stdin(&op.table, op.encoding)?;
+    let gtfs_db = external::gtfsdb::create(&op.database)?;
+
+    let mut service = GtfsService::new(gtfs_db, gtfs_csv);
+    if op.upsert {
+        service.set_on_conflict(OnConflict::Replace);
+    }
+    // テーブルの内容がファイルと一致しなくなるため, 次回のmake-dbでは全て取り込み直す
+    service.invalidate_meta()?;
+    service.create_tables()?;
+    let report = service.insert_tables()?;
+    service.create_indexes()?;
+
+    info!("✨ Imported ({})", report);
+    Ok(())
+}
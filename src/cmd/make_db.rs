@@ -1,28 +1,159 @@
 use std::path::PathBuf;
 
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
 use clap::Clap;
+use log::warn;
+use strum::VariantNames;
 
+use crate::app::feed_check::FeedCheckService;
 use crate::app::gtfs::GtfsService;
-use crate::external;
+use crate::external::gtfs::OnConflict;
+use crate::io::{Encoding, Format, InvalidRow};
+use crate::{external, io};
 
 #[derive(Clap, Debug)]
 pub struct Opts {
-    #[clap(parse(from_os_str))]
-    gtfs_dir: PathBuf,
+    /// GTFSのディレクトリまたはzipファイル
+    #[clap(parse(from_os_str), required_unless_present = "url")]
+    gtfs_path: Option<PathBuf>,
+    /// GTFSのzipファイルをダウンロードするURL
+    #[clap(long, conflicts_with = "gtfs-path")]
+    url: Option<String>,
+    /// 作成するデータベース (:memory: ならファイルを作らずに取り込みだけ確認する)
     #[clap(short, long, parse(from_os_str), default_value = "hibou.db")]
     database: PathBuf,
+    /// 高速に登録するためPRAGMAを調整する (登録中にクラッシュするとデータベースが壊れる可能性あり)
+    #[clap(long)]
+    fast: bool,
+    /// テーブルを作り直さず, 主キーが同じレコードは置き換えて登録する
+    /// (主キーのないテーブルやNULLを含む主キーのレコードは重複して追加される)
+    #[clap(long)]
+    upsert: bool,
+    /// 登録の進捗バーを表示する
+    #[clap(long)]
+    progress: bool,
+    /// GTFSファイルの文字コード
+    #[clap(long, default_value = "utf-8", possible_values(Encoding::VARIANTS))]
+    encoding: Encoding,
+    /// 前回から変更のないファイルも取り込み直す
+    /// (--encodingや--skip-invalidを変えて取り込み直す場合に指定する)
+    #[clap(long)]
+    force: bool,
+    /// パースできない行を読み飛ばし, 残りの行だけを登録する
+    #[clap(long)]
+    skip_invalid: bool,
+    /// 外部キーを検査し, 参照先のないレコードがあれば登録を失敗させる. 指定しなければ外部キーは検査しない
+    /// (routes, trips, stop_times, fare_rulesに外部キーを付けるため, 変更のないファイルもテーブルを作り直す)
+    #[clap(long)]
+    enforce_fk: bool,
+    /// 1つのINSERT文にまとめる行数 (省略時はテーブルごとにSQLiteのバインド変数の上限に収まる最大の行数)
+    /// 大きいほど文の実行回数が減って速くなるが, 1文あたりのメモリ使用量が増える.
+    /// メモリの少ない環境では小さくする. 全テーブルで上限に収まる値のみ指定できる
+    #[clap(long)]
+    batch_size: Option<usize>,
+    /// 取り込むテーブルをカンマ区切りで指定する (ex: stops,routes). 指定しないテーブルはそのまま残す
+    #[clap(long, use_delimiter = true)]
+    tables: Option<Vec<String>>,
+    /// SpatiaLite拡張 (mod_spatialite) があれば, stopsにgeometryカラムと空間インデックスを作る
+    /// 拡張が見つからなければ警告して通常どおり取り込む. 作ったデータベースに--upsertで追加する場合も指定する
+    #[clap(long)]
+    spatialite: bool,
+    /// データベースを作らず, 各ファイルを読み込めるかと件数だけを表示する
+    #[clap(long)]
+    dry_run: bool,
 }
 
 pub fn run(op: &Opts) -> Result<()> {
-    let gtfs_csv = external::gtfscsv::init(op.gtfs_dir.clone())?;
-    let gtfs_db = external::gtfsdb::init(&op.database)?;
+    // ダウンロードした一時ファイルはimportが終わるまで保持する
+    let downloaded = op
+        .url
+        .as_deref()
+        .map(external::http::download)
+        .transpose()?;
+    let gtfs_path = match &downloaded {
+        Some(file) => file.path().to_path_buf(),
+        None => op
+            .gtfs_path
+            .clone()
+            .context("GTFSのパスを指定してください")?,
+    };
+
+    let gtfs_csv = external::gtfscsv::init(gtfs_path, op.encoding)?;
+    if op.dry_run {
+        let checks = FeedCheckService::new(Box::new(gtfs_csv)).check()?;
+        io::write_stdout(&checks, &Format::Csv)?;
+        let failed = checks.iter().filter(|x| !x.is_ok()).count();
+        if failed > 0 {
+            bail!("{}件のファイルが読み込めませんでした", failed);
+        }
+        return Ok(());
+    }
+
+    let gtfs_db = external::gtfsdb::create(&op.database)?;
 
     let mut service = GtfsService::new(gtfs_db, gtfs_csv);
+    service.set_progress(op.progress);
+    service.set_skip_invalid(op.skip_invalid);
+    if let Some(tables) = &op.tables {
+        service.select_tables(tables)?;
+    }
+    if let Some(rows) = op.batch_size {
+        service.set_batch_size(rows)?;
+    }
 
-    service.drop_tables()?;
+    if op.fast {
+        service.set_fast_import(true)?;
+    }
+    if op.enforce_fk {
+        service.set_enforce_fk(true)?;
+    }
+    // geometryカラムのあるstopsへの登録にも拡張が必要なため, 先に読み込む
+    if op.spatialite {
+        service.enable_spatialite()?;
+    }
+
+    if op.upsert {
+        service.set_on_conflict(OnConflict::Replace);
+    } else {
+        // 追加登録したテーブルはファイルの内容と一致しないため, upsertではハッシュ値を記録しない
+        service.hash_files()?;
+        if !op.force && !op.enforce_fk {
+            service.skip_unchanged_tables()?;
+        }
+    }
+    service.invalidate_meta()?;
+    if !op.upsert {
+        service.drop_tables()?;
+    }
     service.create_tables()?;
-    service.insert_tables(&op.gtfs_dir)?;
+    let report = service.insert_tables()?;
+    // 一括登録中に更新するより後からまとめて作る方が速い
+    service.create_indexes()?;
+    service.create_stop_geometries()?;
+    service.save_meta(&report)?;
+
+    if op.fast {
+        service.set_fast_import(false)?;
+    }
 
+    // 既定のログレベルでは info! が出ないため, 結果は標準エラー出力に直接書く
+    eprintln!("✨ Imported ({})", report);
+    report_invalid_rows(&service.take_invalid_rows());
     Ok(())
 }
+
+/// 読み飛ばした行が多すぎる場合は先頭のMAX_REPORTED行だけ表示する
+fn report_invalid_rows(rows: &[InvalidRow]) {
+    const MAX_REPORTED: usize = 20;
+
+    if rows.is_empty() {
+        return;
+    }
+    for row in rows.iter().take(MAX_REPORTED) {
+        warn!("{} (line {}): {}", row.file, row.line, row.message);
+    }
+    if rows.len() > MAX_REPORTED {
+        warn!("... 他 {} 行", rows.len() - MAX_REPORTED);
+    }
+    warn!("⚠️ パースできない {} 行を読み飛ばしました", rows.len());
+}
@@ -0,0 +1,86 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
+use clap::Clap;
+use log::warn;
+
+use crate::app::merge::{Feed, MergeService};
+use crate::io::Format;
+use crate::{external, io};
+
+#[derive(Clap, Debug)]
+pub struct Opts {
+    /// 合成するデータベース (2つ以上)
+    #[clap(parse(from_os_str), required = true, min_values = 2)]
+    databases: Vec<PathBuf>,
+    /// 合成したデータベース (既に存在する場合はエラー)
+    #[clap(short, long, parse(from_os_str))]
+    output: PathBuf,
+    /// 各データベースのIDに付ける名前空間. カンマ区切りでデータベースと同じ順に指定する
+    /// (省略時はファイル名から拡張子を除いたもの. ex: feed1.db -> feed1:agency_id)
+    #[clap(long, use_delimiter = true)]
+    namespace: Option<Vec<String>>,
+}
+
+pub fn run(op: &Opts) -> Result<()> {
+    if op.output.exists() {
+        bail!("{:?} は既に存在します", op.output);
+    }
+    let feeds = feeds(op)?;
+    let target = external::gtfsdb::create(&op.output)?;
+    let result = MergeService::new(target).merge(&feeds);
+    // 途中で失敗した場合は不完全なデータベースを残さない
+    let collisions = match result {
+        Ok(x) => x,
+        Err(err) => {
+            let _ = fs::remove_file(&op.output);
+            return Err(err);
+        }
+    };
+    if !collisions.is_empty() {
+        warn!("重複して追加できなかったレコードがあります");
+        io::write_stdout(&collisions, &Format::Csv)?;
+    }
+    Ok(())
+}
+
+/// 名前空間は重複するとIDが衝突するため, 重複していればエラーにする
+fn feeds(op: &Opts) -> Result<Vec<Feed>> {
+    let namespaces = match &op.namespace {
+        Some(x) if x.len() != op.databases.len() => bail!(
+            "--namespaceの数 ({}) がデータベースの数 ({}) と一致しません",
+            x.len(),
+            op.databases.len()
+        ),
+        Some(x) => x.clone(),
+        None => op
+            .databases
+            .iter()
+            .map(|x| {
+                x.file_stem()
+                    .and_then(|x| x.to_str())
+                    .map(String::from)
+                    .with_context(|| format!("{:?} から名前空間を決められません", x))
+            })
+            .collect::<Result<_>>()?,
+    };
+    let mut seen = HashSet::new();
+    for namespace in &namespaces {
+        if !seen.insert(namespace) {
+            bail!(
+                "名前空間が重複しています: {} (--namespaceで指定してください)",
+                namespace
+            );
+        }
+    }
+    Ok(namespaces
+        .into_iter()
+        .zip(&op.databases)
+        .map(|(namespace, database)| Feed {
+            namespace,
+            database: database.clone(),
+        })
+        .collect())
+}
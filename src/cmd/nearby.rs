@@ -0,0 +1,41 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::Clap;
+use strum::VariantNames;
+
+use crate::app::stop::StopService;
+use crate::io::Format;
+use crate::{external, io};
+
+#[derive(Clap, Debug)]
+pub struct Opts {
+    #[clap(short, long, parse(from_os_str), default_value = "hibou.db")]
+    database: PathBuf,
+    #[clap(short, long, default_value = "csv", possible_values(Format::VARIANTS))]
+    format: Format,
+    /// 緯度 (ex: 35.681236)
+    #[clap(long, allow_hyphen_values = true)]
+    lat: f32,
+    /// 経度 (ex: 139.767125)
+    #[clap(long, allow_hyphen_values = true)]
+    lon: f32,
+    /// 検索する半径 (メートル)
+    #[clap(long, default_value = "500")]
+    radius: f64,
+    /// make-db --spatialite で作ったgeometryカラムの空間インデックスで検索する
+    /// SpatiaLite拡張 (mod_spatialite) が見つからなければ警告して通常どおり検索する
+    #[clap(long)]
+    spatialite: bool,
+}
+
+pub fn run(op: &Opts) -> Result<()> {
+    let gtfs = external::gtfsdb::open(&op.database)?;
+    let mut service = StopService::new(gtfs);
+    if op.spatialite {
+        service.enable_spatialite()?;
+    }
+    let stops = service.nearby(op.lat, op.lon, op.radius)?;
+    io::write_geo_stdout(&stops, &op.format)?;
+    Ok(())
+}
@@ -0,0 +1,29 @@
+use std::io;
+use std::io::Write;
+
+use anyhow::{bail, Result};
+use clap::Clap;
+
+use crate::external;
+
+#[derive(Clap, Debug)]
+pub struct Opts {
+    /// 表示するテーブル名 (ex: stop_times) (省略時は全テーブル)
+    #[clap(long)]
+    table: Option<String>,
+}
+
+pub fn run(op: &Opts) -> Result<()> {
+    let schema: Vec<(&str, String)> = external::gtfsdb::schema()
+        .into_iter()
+        .filter(|(name, _)| op.table.as_deref().is_none_or(|x| x == *name))
+        .collect();
+    if let (Some(table), true) = (&op.table, schema.is_empty()) {
+        bail!("テーブルが見つかりません: {}", table);
+    }
+
+    let statements: Vec<String> = schema.into_iter().map(|(_, ddl)| ddl).collect();
+    // println!はパイプが閉じられるとパニックするため, エラーとして返す
+    writeln!(io::stdout().lock(), "{}", statements.join("\n\n"))?;
+    Ok(())
+}
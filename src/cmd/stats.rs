@@ -0,0 +1,27 @@
+use std::path::PathBuf;
+
+use anyhow::{bail, Result};
+use clap::Clap;
+
+use crate::app::stats::StatsService;
+use crate::cmd::get::OutputOpts;
+use crate::{external, io};
+
+#[derive(Clap, Debug)]
+pub struct Opts {
+    #[clap(short, long, parse(from_os_str), default_value = "hibou.db")]
+    database: PathBuf,
+    #[clap(flatten)]
+    output: OutputOpts,
+}
+
+pub fn run(op: &Opts) -> Result<()> {
+    if op.output.has_columns() {
+        bail!("statsは集計結果のため--columnsに対応していません");
+    }
+    let gtfs = external::gtfsdb::open(&op.database)?;
+    let stats = StatsService::new(gtfs).compute()?;
+    op.output
+        .write_with(|out| io::write(out, &stats, &op.output.format(), op.output.headers()))?;
+    Ok(())
+}
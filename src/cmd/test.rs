@@ -21,6 +21,6 @@ pub struct Opts {
 pub fn run(op: &Opts) -> Result<()> {
     let gtfs = external::gtfsdb::init(&op.database)?;
     let results = TestService::new(gtfs).fetch()?;
-    io::write(&results, &op.format)?;
+    io::write_stdout(&results, &op.format)?;
     Ok(())
 }
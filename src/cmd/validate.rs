@@ -0,0 +1,55 @@
+use std::path::PathBuf;
+
+use anyhow::{bail, Result};
+use clap::Clap;
+use strum::VariantNames;
+
+use crate::app::validate::ValidateService;
+use crate::io::Format;
+use crate::{external, io};
+
+#[derive(Clap, Debug)]
+pub struct Opts {
+    #[clap(short, long, parse(from_os_str), default_value = "hibou.db")]
+    database: PathBuf,
+    #[clap(short, long, default_value = "csv", possible_values(Format::VARIANTS))]
+    format: Format,
+}
+
+/// 問題が見つかった場合はエラー終了する
+pub fn run(op: &Opts) -> Result<()> {
+    let gtfs = external::gtfsdb::open(&op.database)?;
+    let mut service = ValidateService::new(gtfs);
+    let references = service.check_references()?;
+    io::write_stdout(&references, &op.format)?;
+
+    // 時刻の逆転は見つかった場合のみ続けて出力する
+    let reversals = service.check_time_order()?;
+    if !reversals.is_empty() {
+        io::write_stdout(&reversals, &op.format)?;
+    }
+
+    // 通過順位の重複も見つかった場合のみ続けて出力する
+    let duplicates = service.check_duplicate_sequences()?;
+    if !duplicates.is_empty() {
+        io::write_stdout(&duplicates, &op.format)?;
+    }
+
+    let dangling: u32 = references.iter().map(|x| x.count()).sum();
+    if dangling > 0 {
+        bail!("参照先が存在しないレコードが {} 件あります", dangling);
+    }
+    if !reversals.is_empty() {
+        bail!(
+            "時刻が前の停留所より早くなっている箇所が {} 件あります",
+            reversals.len()
+        );
+    }
+    if !duplicates.is_empty() {
+        bail!(
+            "便の中で通過順位が重複している箇所が {} 件あります",
+            duplicates.len()
+        );
+    }
+    Ok(())
+}
@@ -1,3 +1,5 @@
 pub mod gtfs;
 pub mod gtfscsv;
 pub mod gtfsdb;
+pub mod gtfszip;
+pub mod http;
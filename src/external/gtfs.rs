@@ -1,42 +1,64 @@
-use anyhow::Result;
-use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
 
-use crate::external::gtfs::agency::Agency;
-use crate::external::gtfs::calendar::Calendar;
+use anyhow::{bail, Result};
+use chrono_tz::Tz;
+use hibou_derive::EnumValues;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use serde_repr::{Deserialize_repr, Serialize_repr};
+
+use crate::external::gtfs::agency::{Agency, AgencyId};
+use crate::external::gtfs::attributions::Attribution;
+use crate::external::gtfs::block_trips::BlockTrip;
+use crate::external::gtfs::calendar::{Calendar, ServiceId};
 use crate::external::gtfs::calendar_dates::CalendarDate;
 use crate::external::gtfs::fare_attributes::FareAttribute;
 use crate::external::gtfs::fare_rules::FareRule;
-use crate::external::gtfs::feed_info::Feed;
+use crate::external::gtfs::feed_info::FeedInfo;
 use crate::external::gtfs::frequencies::Frequency;
-use crate::external::gtfs::routes::Route;
-use crate::external::gtfs::shapes::Shape;
+use crate::external::gtfs::levels::Level;
+use crate::external::gtfs::office_jp::{JpOfficeId, OfficeJp};
+use crate::external::gtfs::pathways::Pathway;
+use crate::external::gtfs::route_shapes::RouteShape;
+use crate::external::gtfs::routes::{Route, RouteId};
+use crate::external::gtfs::shapes::{Shape, ShapeId};
 use crate::external::gtfs::stop_times::StopTime;
-use crate::external::gtfs::stops::Stop;
+use crate::external::gtfs::stop_transfers::StopTransfer;
+use crate::external::gtfs::stops::{Stop, StopId};
+use crate::external::gtfs::timetable::TimetableRow;
 use crate::external::gtfs::transfers::Transfer;
 use crate::external::gtfs::translations::Translation;
-use crate::external::gtfs::trips::Trip;
+use crate::external::gtfs::trip_endpoints::TripEndpoint;
+use crate::external::gtfs::trips::{BlockId, Direction, Trip, TripId};
+use crate::external::gtfs::trips_between::TripBetween;
 
 pub mod agency;
+pub mod attributions;
+pub mod block_trips;
 pub mod calendar;
 pub mod calendar_dates;
 pub mod fare_attributes;
 pub mod fare_rules;
 pub mod feed_info;
 pub mod frequencies;
+pub mod levels;
+pub mod office_jp;
+pub mod pathways;
+pub mod route_shapes;
 pub mod routes;
 pub mod shapes;
 pub mod stop_times;
+pub mod stop_transfers;
 pub mod stops;
+pub mod timetable;
 pub mod transfers;
 pub mod translations;
+pub mod trip_endpoints;
 pub mod trips;
+pub mod trips_between;
 
-/// 色. 00FFFF など 6 桁の 16 進数
-pub type Color = String;
 /// メートル
 pub type Meter = u32;
-/// HH:mm:ss形式で28時などの表現も許容
-pub type UnlimitedTime = String;
 /// YYYY-MM-DD形式の年月日
 pub type DateString = String;
 /// 秒
@@ -57,14 +79,22 @@ pub type Longitude = f32;
 // TODO (ex: Z_210)
 pub type ZoneId = String;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, EnumValues)]
 pub enum Timezone {
     /// 日本語
     #[serde(rename = "Asia/Tokyo")]
     AsiaTokyo,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl Timezone {
+    pub fn tz(&self) -> Tz {
+        match self {
+            Timezone::AsiaTokyo => Tz::Asia__Tokyo,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, EnumValues)]
 #[serde(rename_all = "lowercase")]
 pub enum Lang {
     Ja,
@@ -79,35 +109,424 @@ pub enum Lang {
     JaHrkt,
 }
 
+/// 停留所以外の経路上での乗降 (routesとstop_timesで共通)
+/// https://gtfs.org/schedule/reference/#routestxt
+#[derive(Debug, Clone, Default, Deserialize_repr, Serialize_repr)]
+#[repr(u8)]
+pub enum ContinuousStop {
+    /// 経路上のどこでも乗降できる
+    Continuous = 0,
+    /// 停留所以外では乗降できない
+    #[default]
+    None = 1,
+    /// 交通機関に予約の電話が必要
+    NeedOfficeReservation = 2,
+    /// 運転手への事前連絡が必要
+    NeedDriverReservation = 3,
+}
+
+/// HH:MM:SS形式の時刻. 日をまたぐ運行のため25:15:00などの24時以降も許容する
+/// 運行日の0時からの秒数で保持するため, 日をまたいでも正しく比較や計算ができる
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct GtfsTime(Second);
+
+impl GtfsTime {
+    pub fn from_seconds(seconds: Second) -> Self {
+        GtfsTime(seconds)
+    }
+
+    /// 運行日の0時からの秒数
+    pub fn seconds(self) -> Second {
+        self.0
+    }
+}
+
+impl FromStr for GtfsTime {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let parts: Vec<&str> = s.trim().split(':').collect();
+        let (h, m, sec) = match parts.as_slice() {
+            [h, m, sec] => (h.parse::<u32>()?, m.parse::<u32>()?, sec.parse::<u32>()?),
+            _ => bail!("HH:MM:SS形式ではありません: {}", s),
+        };
+        if m >= 60 || sec >= 60 {
+            bail!("分または秒が範囲外です: {}", s);
+        }
+        Ok(GtfsTime(h * 3600 + m * 60 + sec))
+    }
+}
+
+impl fmt::Display for GtfsTime {
+    /// 時は24以上でもそのまま出力する (ex: 25:15:00)
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = self.0;
+        write!(f, "{:02}:{:02}:{:02}", s / 3600, s / 60 % 60, s % 60)
+    }
+}
+
+impl Serialize for GtfsTime {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for GtfsTime {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(de::Error::custom)
+    }
+}
+
+/// 色. 00FFFF など 6 桁の 16 進数
+/// 先頭の#や小文字も受け付け, 大文字の6桁に揃えて保持する
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Color(String);
+
+impl Color {
+    pub fn white() -> Self {
+        Color("FFFFFF".to_string())
+    }
+
+    pub fn black() -> Self {
+        Color("000000".to_string())
+    }
+
+    /// 地図などの描画に使う#付きの形式 (ex: #FFD700)
+    pub fn to_css(&self) -> String {
+        format!("#{}", self.0)
+    }
+
+    /// この色を背景にしたときに読みやすい文字色 (黒か白)
+    pub fn contrasting_text(&self) -> Color {
+        let channel = |i: usize| u32::from_str_radix(&self.0[i..i + 2], 16).unwrap_or(0);
+        // YIQ方式の明るさ (0-255)
+        let brightness = (channel(0) * 299 + channel(2) * 587 + channel(4) * 114) / 1000;
+        if brightness >= 128 {
+            Color::black()
+        } else {
+            Color::white()
+        }
+    }
+}
+
+impl FromStr for Color {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let hex = s.trim().trim_start_matches('#');
+        if hex.len() != 6 || !hex.chars().all(|x| x.is_ascii_hexdigit()) {
+            bail!("色は6桁の16進数で指定してください (ex: FFD700): {}", s);
+        }
+        Ok(Color(hex.to_uppercase()))
+    }
+}
+
+impl fmt::Display for Color {
+    /// GTFSと同じく#を付けずに出力する
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Serialize for Color {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Color {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(de::Error::custom)
+    }
+}
+
+/// 登録時に主キーが重複した場合の扱い
+#[derive(Debug, Clone, Copy, Default)]
+pub enum OnConflict {
+    /// エラーにする
+    #[default]
+    Abort,
+    /// 既存のレコードを置き換える
+    Replace,
+}
+
+/// 空欄やNULLを既定値として読み込む (`#[serde(default, deserialize_with = "default_if_empty")]`)
+pub fn default_if_empty<'de, D, T>(deserializer: D) -> std::result::Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + Default,
+{
+    Ok(Option::<T>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+/// 取得条件
+#[derive(Debug, Default, Clone)]
+pub struct Query {
+    /// 最大件数
+    pub limit: Option<u32>,
+    /// 読み飛ばす件数
+    pub offset: Option<u32>,
+    /// SQLのWHERE句にそのまま埋め込む条件式
+    pub condition: Option<String>,
+}
+
+impl Query {
+    /// SQLを使えない場合に取得済みのレコードへ件数の条件を適用する
+    /// conditionはSQLでしか評価できないため対象外
+    pub fn paginate<T>(&self, records: Vec<T>) -> Vec<T> {
+        let offset = self.offset.unwrap_or(0) as usize;
+        let limit = self.limit.map(|x| x as usize).unwrap_or(usize::MAX);
+        records.into_iter().skip(offset).take(limit).collect()
+    }
+
+    /// conditionにAND条件を追加する
+    pub fn and(mut self, condition: String) -> Self {
+        self.condition = Some(match self.condition {
+            Some(c) => format!("({}) AND ({})", c, condition),
+            None => condition,
+        });
+        self
+    }
+
+    /// column = valueのAND条件を追加する
+    pub fn and_eq(self, column: &str, value: &impl fmt::Display) -> Self {
+        let condition = format!("{} = {}", column, sql_literal(value));
+        self.and(condition)
+    }
+
+    /// limit, offsetをレコードではなくtable.columnの値の種類に適用する条件にする
+    /// (ex: 描画点ではなく描画IDの数を制限し, 選んだ描画IDの描画点は全て取得する)
+    pub fn paginate_distinct(self, table: &str, column: &str) -> Self {
+        if self.limit.is_none() && self.offset.is_none() {
+            return self;
+        }
+        let where_sql = match &self.condition {
+            Some(c) => format!(" WHERE {}", c),
+            None => String::new(),
+        };
+        let condition = format!(
+            "{column} IN (SELECT DISTINCT {column} FROM {table}{where_sql} ORDER BY {column}{paging})",
+            column = column,
+            table = table,
+            where_sql = where_sql,
+            paging = self.paging_sql(),
+        );
+        Query {
+            limit: None,
+            offset: None,
+            ..self
+        }
+        .and(condition)
+    }
+
+    /// LIMIT, OFFSET句
+    pub fn paging_sql(&self) -> String {
+        match (self.limit, self.offset) {
+            (None, None) => String::new(),
+            // OFFSETだけの指定はできないため -1 (無制限) を指定する
+            (limit, offset) => format!(
+                " LIMIT {} OFFSET {}",
+                limit.map(i64::from).unwrap_or(-1),
+                offset.unwrap_or(0)
+            ),
+        }
+    }
+}
+
+/// 条件式に埋め込むためエスケープした文字列リテラル
+pub fn sql_literal(value: &impl fmt::Display) -> String {
+    format!("'{}'", value.to_string().replace('\'', "''"))
+}
+
 pub trait Gtfs {
+    /// テーブル (CSVの場合は対応するファイル) が存在するか
+    fn exists(&self, table_name: &str) -> Result<bool>;
     fn create_all(&self) -> Result<()>;
     fn drop_all(&self) -> Result<()>;
+    /// 外部キーのカラムにインデックスを作成する
+    fn index_all(&self) -> Result<()>;
     fn insert_agencies(&mut self, agencies: &[Agency]) -> Result<()>;
-    fn select_agencies(&mut self) -> Result<Vec<Agency>>;
+    fn select_agencies(&mut self, query: &Query) -> Result<Vec<Agency>>;
     fn insert_stops(&mut self, stops: &[Stop]) -> Result<()>;
-    fn select_stops(&mut self) -> Result<Vec<Stop>>;
+    fn select_stops(&mut self, query: &Query) -> Result<Vec<Stop>>;
+    fn select_stops_by_stop_id(&mut self, stop_id: &StopId, query: &Query) -> Result<Vec<Stop>>;
+    /// 緯度経度が範囲内 (両端を含む) のstopを取得する
+    fn select_stops_in_bbox(
+        &mut self,
+        south_west: (Latitude, Longitude),
+        north_east: (Latitude, Longitude),
+    ) -> Result<Vec<Stop>>;
+    /// centerから半径radius (メートル) 以内のstopを近い順に取得する
+    /// 空間インデックスはsouth_westからnorth_eastの範囲で引く. geometryカラムとSpatiaLite拡張が必要
+    fn select_stops_within(
+        &mut self,
+        center: (Latitude, Longitude),
+        south_west: (Latitude, Longitude),
+        north_east: (Latitude, Longitude),
+        radius: f64,
+    ) -> Result<Vec<Stop>>;
+    fn select_stops_by_route_id(&mut self, route_id: &RouteId, query: &Query) -> Result<Vec<Stop>>;
+    /// 親駅がparent_stationであるstopを取得する
+    fn select_stops_by_parent_station(
+        &mut self,
+        parent_station: &StopId,
+        query: &Query,
+    ) -> Result<Vec<Stop>>;
+    /// 停留所・標柱名称に部分一致するstopを取得する
+    fn select_stops_by_name_like(&mut self, name: &str, query: &Query) -> Result<Vec<Stop>>;
     fn insert_routes(&mut self, routes: &[Route]) -> Result<()>;
-    fn select_routes(&mut self) -> Result<Vec<Route>>;
+    fn select_routes(&mut self, query: &Query) -> Result<Vec<Route>>;
+    fn select_routes_by_route_id(
+        &mut self,
+        route_id: &RouteId,
+        query: &Query,
+    ) -> Result<Vec<Route>>;
+    /// 経路略称または経路名に部分一致するrouteを取得する (英字の大文字・小文字は区別しない)
+    fn select_routes_by_name_like(&mut self, name: &str, query: &Query) -> Result<Vec<Route>>;
+    fn select_routes_by_agency_id(
+        &mut self,
+        agency_id: &AgencyId,
+        query: &Query,
+    ) -> Result<Vec<Route>>;
     fn insert_trips(&mut self, trips: &[Trip]) -> Result<()>;
-    fn select_trips(&mut self) -> Result<Vec<Trip>>;
+    fn select_trips(&mut self, query: &Query) -> Result<Vec<Trip>>;
+    /// 運行日IDのいずれかに一致するtripを取得する
+    fn select_trips_by_service_ids(
+        &mut self,
+        service_ids: &[ServiceId],
+        query: &Query,
+    ) -> Result<Vec<Trip>>;
+    /// 経路IDと上下区分で絞り込む (Noneなら絞り込まない. 上下区分が空の便は上下区分の指定があれば除く)
+    fn select_trips_by_route_and_direction(
+        &mut self,
+        route_id: Option<&RouteId>,
+        direction: Option<Direction>,
+        query: &Query,
+    ) -> Result<Vec<Trip>>;
+    /// block_idが同じ便を始発の出発時刻順に取得する
+    fn select_block_trips(&mut self, block_id: &BlockId) -> Result<Vec<BlockTrip>>;
+    /// trip_idの便と同じblock_idを持つ便を始発の出発時刻順に取得する
+    fn select_block_trips_by_trip_id(&mut self, trip_id: &TripId) -> Result<Vec<BlockTrip>>;
     fn insert_stop_times(&mut self, stop_times: &[StopTime]) -> Result<()>;
-    fn select_stop_times(&mut self) -> Result<Vec<StopTime>>;
+    /// shape_dist_traveledだけを更新する
+    fn update_stop_times_dist_traveled(&mut self, stop_times: &[StopTime]) -> Result<()>;
+    /// arrival_time, departure_timeだけを更新する
+    fn update_stop_times_times(&mut self, stop_times: &[StopTime]) -> Result<()>;
+    fn select_stop_times(&mut self, query: &Query) -> Result<Vec<StopTime>>;
+    /// 全件をメモリに載せず, batch_size件ずつeachに渡す. 戻り値は件数
+    fn for_each_stop_times(
+        &mut self,
+        batch_size: usize,
+        each: &mut dyn FnMut(&[StopTime]) -> Result<()>,
+    ) -> Result<usize>;
+    /// for_each_stop_timesと同じだが, 便ID, 通過順位の順に渡す
+    fn for_each_stop_times_by_trip(
+        &mut self,
+        batch_size: usize,
+        each: &mut dyn FnMut(&[StopTime]) -> Result<()>,
+    ) -> Result<usize>;
+    fn select_stop_times_by_trip_id(
+        &mut self,
+        trip_id: &TripId,
+        query: &Query,
+    ) -> Result<Vec<StopTime>>;
+    fn select_stop_times_by_stop_id(
+        &mut self,
+        stop_id: &StopId,
+        query: &Query,
+    ) -> Result<Vec<StopTime>>;
+    /// 経路の全便の通過時刻を便ID, 通過順位の順に取得する
+    fn select_timetable_by_route_id(&mut self, route_id: &RouteId) -> Result<Vec<TimetableRow>>;
+    /// 経路の各便の始発と終着を始発の出発時刻順に取得する
+    fn select_trip_endpoints_by_route_id(
+        &mut self,
+        route_id: &RouteId,
+    ) -> Result<Vec<TripEndpoint>>;
+    /// fromからtoへこの順に通る便をfromの出発時刻順に取得する
+    fn select_trips_between(&mut self, from: &StopId, to: &StopId) -> Result<Vec<TripBetween>>;
     fn insert_calendars(&mut self, calendars: &[Calendar]) -> Result<()>;
-    fn select_calendars(&mut self) -> Result<Vec<Calendar>>;
+    fn select_calendars(&mut self, query: &Query) -> Result<Vec<Calendar>>;
+    fn select_calendars_by_service_id(
+        &mut self,
+        service_id: &ServiceId,
+        query: &Query,
+    ) -> Result<Vec<Calendar>>;
     fn insert_calendar_dates(&mut self, calendar_dates: &[CalendarDate]) -> Result<()>;
-    fn select_calendar_dates(&mut self) -> Result<Vec<CalendarDate>>;
+    fn select_calendar_dates(&mut self, query: &Query) -> Result<Vec<CalendarDate>>;
     fn insert_fare_attributes(&mut self, fare_attributes: &[FareAttribute]) -> Result<()>;
-    fn select_fare_attributes(&mut self) -> Result<Vec<FareAttribute>>;
+    fn select_fare_attributes(&mut self, query: &Query) -> Result<Vec<FareAttribute>>;
     fn insert_fare_rules(&mut self, fare_rules: &[FareRule]) -> Result<()>;
-    fn select_fare_rules(&mut self) -> Result<Vec<FareRule>>;
+    fn select_fare_rules(&mut self, query: &Query) -> Result<Vec<FareRule>>;
     fn insert_shapes(&mut self, shapes: &[Shape]) -> Result<()>;
-    fn select_shapes(&mut self) -> Result<Vec<Shape>>;
+    /// shape_dist_traveledだけを更新する
+    fn update_shapes_dist_traveled(&mut self, shapes: &[Shape]) -> Result<()>;
+    fn select_shapes(&mut self, query: &Query) -> Result<Vec<Shape>>;
+    /// 全件をメモリに載せず, batch_size件ずつeachに渡す. 戻り値は件数
+    fn for_each_shapes(
+        &mut self,
+        batch_size: usize,
+        each: &mut dyn FnMut(&[Shape]) -> Result<()>,
+    ) -> Result<usize>;
+    /// 経路・上下区分ごとに便が使う描画IDを, 便数の多い順に取得する
+    fn select_route_shapes(&mut self) -> Result<Vec<RouteShape>>;
+    fn select_shapes_by_shape_id(
+        &mut self,
+        shape_id: &ShapeId,
+        query: &Query,
+    ) -> Result<Vec<Shape>>;
     fn insert_frequencies(&mut self, frequencies: &[Frequency]) -> Result<()>;
-    fn select_frequencies(&mut self) -> Result<Vec<Frequency>>;
+    fn select_frequencies(&mut self, query: &Query) -> Result<Vec<Frequency>>;
+    fn select_frequencies_by_trip_id(
+        &mut self,
+        trip_id: &TripId,
+        query: &Query,
+    ) -> Result<Vec<Frequency>>;
     fn insert_transfers(&mut self, transfers: &[Transfer]) -> Result<()>;
-    fn select_transfers(&mut self) -> Result<Vec<Transfer>>;
-    fn insert_feeds(&mut self, feeds: &[Feed]) -> Result<()>;
-    fn select_feeds(&mut self) -> Result<Vec<Feed>>;
+    fn select_transfers(&mut self, query: &Query) -> Result<Vec<Transfer>>;
+    /// 乗換元, 乗換先で絞り込んだ乗換を標柱名と合わせて取得する (Noneなら絞り込まない)
+    fn select_stop_transfers(
+        &mut self,
+        from_stop_id: Option<&StopId>,
+        to_stop_id: Option<&StopId>,
+    ) -> Result<Vec<StopTransfer>>;
+    fn insert_levels(&mut self, levels: &[Level]) -> Result<()>;
+    fn select_levels(&mut self, query: &Query) -> Result<Vec<Level>>;
+    fn insert_pathways(&mut self, pathways: &[Pathway]) -> Result<()>;
+    fn select_pathways(&mut self, query: &Query) -> Result<Vec<Pathway>>;
+    fn insert_attributions(&mut self, attributions: &[Attribution]) -> Result<()>;
+    fn select_attributions(&mut self, query: &Query) -> Result<Vec<Attribution>>;
+    fn insert_feed_info(&mut self, feed_info: &FeedInfo) -> Result<()>;
+    fn select_feed_info(&mut self) -> Result<Option<FeedInfo>>;
+    fn insert_offices(&mut self, offices: &[OfficeJp]) -> Result<()>;
+    fn select_offices(&mut self, query: &Query) -> Result<Vec<OfficeJp>>;
+    fn select_offices_by_office_id(
+        &mut self,
+        office_id: &JpOfficeId,
+        query: &Query,
+    ) -> Result<Vec<OfficeJp>>;
     fn insert_translations(&mut self, translations: &[Translation]) -> Result<()>;
-    fn select_translations(&mut self) -> Result<Vec<Translation>>;
+    fn select_translations(&mut self, query: &Query) -> Result<Vec<Translation>>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paginate_distinct_limits_values_instead_of_records() {
+        let query = Query {
+            limit: Some(2),
+            offset: Some(1),
+            condition: Some("shape_pt_lat > 35".to_string()),
+        }
+        .paginate_distinct("shapes", "shape_id");
+        assert_eq!(query.paging_sql(), "");
+        assert_eq!(
+            query.condition.unwrap(),
+            "(shape_pt_lat > 35) AND (shape_id IN (SELECT DISTINCT shape_id FROM shapes \
+             WHERE shape_pt_lat > 35 ORDER BY shape_id LIMIT 2 OFFSET 1))"
+        );
+    }
 }
@@ -0,0 +1,85 @@
+use serde::{Deserialize, Serialize};
+use serde_repr::{Deserialize_repr, Serialize_repr};
+
+use crate::external::gtfs::agency::AgencyId;
+use crate::external::gtfs::routes::RouteId;
+use crate::external::gtfs::trips::TripId;
+use crate::external::gtfs::{MailAddress, TelephoneNumber, Url};
+use crate::external::gtfsdb::Table;
+
+/// 組織がその役割を担っているか
+#[derive(Debug, Deserialize_repr, Serialize_repr)]
+#[repr(u8)]
+enum Role {
+    /// 担っていない
+    No = 0,
+    /// 担っている
+    Yes = 1,
+}
+
+/// 帰属情報 (データの作成者・運行者・管轄機関など)
+/// agency_id, route_id, trip_idがすべて空ならフィード全体が対象
+/// https://gtfs.org/schedule/reference/#attributionstxt
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Attribution {
+    /// 帰属情報ID
+    attribution_id: Option<String>,
+    /// 対象の事業者ID
+    agency_id: Option<AgencyId>,
+    /// 対象の経路ID
+    route_id: Option<RouteId>,
+    /// 対象の便ID
+    trip_id: Option<TripId>,
+    /// 組織名称 (ex: 東京都交通局)
+    organization_name: String,
+    /// データの作成者か
+    is_producer: Option<Role>,
+    /// 運行者か
+    is_operator: Option<Role>,
+    /// 管轄機関か
+    is_authority: Option<Role>,
+    /// 組織のURL
+    attribution_url: Option<Url>,
+    /// 組織のメールアドレス
+    attribution_email: Option<MailAddress>,
+    /// 組織の電話番号
+    attribution_phone: Option<TelephoneNumber>,
+}
+
+impl Table for Attribution {
+    fn table_name() -> &'static str {
+        "attributions"
+    }
+
+    fn column_names() -> &'static [&'static str] {
+        &[
+            "attribution_id",
+            "agency_id",
+            "route_id",
+            "trip_id",
+            "organization_name",
+            "is_producer",
+            "is_operator",
+            "is_authority",
+            "attribution_url",
+            "attribution_email",
+            "attribution_phone",
+        ]
+    }
+
+    fn create_sql() -> &'static str {
+        "
+        attribution_id text,
+        agency_id text,
+        route_id text,
+        trip_id text,
+        organization_name text not null,
+        is_producer int,
+        is_operator int,
+        is_authority int,
+        attribution_url text,
+        attribution_email text,
+        attribution_phone text
+        "
+    }
+}
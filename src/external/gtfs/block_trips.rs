@@ -0,0 +1,28 @@
+use serde::{Deserialize, Serialize};
+
+use crate::external::gtfs::calendar::ServiceId;
+use crate::external::gtfs::routes::RouteId;
+use crate::external::gtfs::trips::{BlockId, Direction, TripId};
+use crate::external::gtfs::GtfsTime;
+
+/// 同じblock_idを持つ便の1つ. tripsとstop_timesを結合して始発・終着時刻を求めたもの
+/// GTFSのファイルではなく検索結果としてのみ使う
+#[derive(Debug, Deserialize, Serialize)]
+pub struct BlockTrip {
+    /// 便結合区分
+    pub block_id: BlockId,
+    /// 便ID
+    pub trip_id: TripId,
+    /// 経路ID
+    pub route_id: RouteId,
+    /// 運行日ID
+    pub service_id: ServiceId,
+    /// 便行き先
+    pub trip_headsign: Option<String>,
+    /// 上下区分
+    pub direction_id: Option<Direction>,
+    /// 始発の出発時刻 (stop_timesがない場合はNone)
+    pub first_departure_time: Option<GtfsTime>,
+    /// 終着の到着時刻 (stop_timesがない場合はNone)
+    pub last_arrival_time: Option<GtfsTime>,
+}
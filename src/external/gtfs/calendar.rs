@@ -8,9 +8,9 @@ use serde_repr::{Deserialize_repr, Serialize_repr};
 pub type ServiceId = String;
 
 /// 運行状態
-#[derive(Debug, Deserialize_repr, Serialize_repr)]
+#[derive(Debug, PartialEq, Deserialize_repr, Serialize_repr)]
 #[repr(u8)]
-enum OperationStatus {
+pub enum OperationStatus {
     /// 非運行
     Absent = 0,
     /// 運行
@@ -22,27 +22,27 @@ enum OperationStatus {
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Calendar {
     /// 運行日ID
-    service_id: ServiceId,
+    pub service_id: ServiceId,
     /// 月曜日
-    monday: OperationStatus,
+    pub monday: OperationStatus,
     /// 火曜日
-    tuesday: OperationStatus,
+    pub tuesday: OperationStatus,
     /// 水曜日
-    wednesday: OperationStatus,
+    pub wednesday: OperationStatus,
     /// 木曜日
-    thursday: OperationStatus,
+    pub thursday: OperationStatus,
     /// 金曜日
-    friday: OperationStatus,
+    pub friday: OperationStatus,
     /// 土曜日
-    saturday: OperationStatus,
+    pub saturday: OperationStatus,
     /// 日曜日
-    sunday: OperationStatus,
+    pub sunday: OperationStatus,
     /// サービス開始日
     #[serde(with = "yyyymmdd")]
-    start_date: NaiveDate,
+    pub start_date: NaiveDate,
     /// サービス終了日
     #[serde(with = "yyyymmdd")]
-    end_date: NaiveDate,
+    pub end_date: NaiveDate,
 }
 
 impl Table for Calendar {
@@ -10,11 +10,11 @@ pub type ServiceId = String;
 /// 利用タイプ
 #[derive(Debug, Deserialize_repr, Serialize_repr)]
 #[repr(u8)]
-enum ExceptionType {
-    /// 運行区分適用
-    Apply = 1,
-    /// 運行区分非適用
-    NotApply = 2,
+pub enum ExceptionType {
+    /// 運行区分適用 (運行日を追加)
+    Added = 1,
+    /// 運行区分非適用 (運行日を削除)
+    Removed = 2,
 }
 
 /// 運行区分情報
@@ -22,12 +22,12 @@ enum ExceptionType {
 #[derive(Debug, Deserialize, Serialize)]
 pub struct CalendarDate {
     /// サービスID
-    service_id: ServiceId,
+    pub service_id: ServiceId,
     /// 日付
     #[serde(with = "yyyymmdd")]
-    date: NaiveDate,
+    pub date: NaiveDate,
     /// 利用タイプ
-    exception_type: ExceptionType,
+    pub exception_type: ExceptionType,
 }
 
 impl Table for CalendarDate {
@@ -35,7 +35,7 @@ enum TransferCount {
 #[derive(Debug, Deserialize, Serialize)]
 pub struct FareAttribute {
     /// 運賃ID
-    fare_id: FareId,
+    pub fare_id: FareId,
     /// 運賃
     price: i32,
     /// 通過
@@ -66,13 +66,12 @@ impl Table for FareAttribute {
 
     fn create_sql() -> &'static str {
         "
-        fare_id text,
+        fare_id text primary key,
         price int not null,
-        currency_type text,
+        currency_type text not null,
         payment_method int not null,
-        transfers int not null,
-        transfer_duration int,
-        PRIMARY KEY(fare_id, currency_type)
+        transfers int,
+        transfer_duration int
         "
     }
 }
@@ -10,15 +10,15 @@ use crate::external::gtfsdb::Table;
 #[derive(Debug, Deserialize, Serialize)]
 pub struct FareRule {
     /// 運賃ID
-    fare_id: FareId,
+    pub fare_id: FareId,
     /// 経路ID
-    route_id: Option<RouteId>,
+    pub route_id: Option<RouteId>,
     /// 乗車地ゾーン
-    origin_id: Option<ZoneId>,
+    pub origin_id: Option<ZoneId>,
     /// 降車地ゾーン
-    destination_id: Option<ZoneId>,
+    pub destination_id: Option<ZoneId>,
     /// 通過ゾーン (JPでは使わない)
-    contains_id: Option<ZoneId>,
+    pub contains_id: Option<ZoneId>,
 }
 
 impl Table for FareRule {
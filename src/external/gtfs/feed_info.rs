@@ -6,7 +6,7 @@ use crate::external::gtfsdb::Table;
 /// 提供情報
 /// https://www.gtfs.jp/developpers-guide/format-reference.html#feed_info
 #[derive(Debug, Deserialize, Serialize)]
-pub struct Feed {
+pub struct FeedInfo {
     /// 提供組織名 (ex: 東京都交通局)
     feed_publisher_name: String,
     /// 提供組織 URL
@@ -14,14 +14,14 @@ pub struct Feed {
     /// 提供言語
     feed_lang: Lang,
     /// 有効期間開始日
-    feed_start_date: Option<DateString>,
+    pub feed_start_date: Option<DateString>,
     /// 有効期間終了日
-    feed_end_date: Option<DateString>,
+    pub feed_end_date: Option<DateString>,
     /// 提供データバージョン
     feed_version: Option<String>,
 }
 
-impl Table for Feed {
+impl Table for FeedInfo {
     fn table_name() -> &'static str {
         "feed_info"
     }
@@ -41,7 +41,7 @@ impl Table for Feed {
         "
         feed_publisher_name text primary key,
         feed_publisher_url text not null,
-        feed_lang int not null,
+        feed_lang text not null,
         feed_start_date text,
         feed_end_date text,
         feed_version text
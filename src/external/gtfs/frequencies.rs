@@ -2,7 +2,7 @@ use serde::{Deserialize, Serialize};
 use serde_repr::{Deserialize_repr, Serialize_repr};
 
 use crate::external::gtfs::trips::TripId;
-use crate::external::gtfs::{Second, UnlimitedTime};
+use crate::external::gtfs::{GtfsTime, Second};
 use crate::external::gtfsdb::Table;
 
 /// 利用タイプ
@@ -20,11 +20,11 @@ enum GuideExactTimes {
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Frequency {
     /// 便ID
-    trip_id: TripId,
+    pub trip_id: TripId,
     /// 開始時刻
-    start_time: UnlimitedTime,
+    start_time: GtfsTime,
     /// 終了時刻
-    end_time: UnlimitedTime,
+    end_time: GtfsTime,
     /// 運行間隔
     headway_secs: Second,
     /// 案内精度
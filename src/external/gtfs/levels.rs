@@ -0,0 +1,36 @@
+use serde::{Deserialize, Serialize};
+
+use crate::external::gtfsdb::Table;
+
+/// 階層ID (ex: L_B1)
+pub type LevelId = String;
+
+/// 階層情報
+/// https://gtfs.org/schedule/reference/#levelstxt
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Level {
+    /// 階層ID
+    level_id: LevelId,
+    /// 地上階を0とした階層の番号 (ex: -1.0)
+    level_index: f32,
+    /// 階層名称 (ex: 地下1階)
+    level_name: Option<String>,
+}
+
+impl Table for Level {
+    fn table_name() -> &'static str {
+        "levels"
+    }
+
+    fn column_names() -> &'static [&'static str] {
+        &["level_id", "level_index", "level_name"]
+    }
+
+    fn create_sql() -> &'static str {
+        "
+        level_id text primary key,
+        level_index double not null,
+        level_name text
+        "
+    }
+}
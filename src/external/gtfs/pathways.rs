@@ -0,0 +1,93 @@
+use serde::{Deserialize, Serialize};
+use serde_repr::{Deserialize_repr, Serialize_repr};
+
+use crate::external::gtfs::stops::StopId;
+use crate::external::gtfs::Second;
+use crate::external::gtfsdb::Table;
+
+/// 通路の種類
+#[derive(Debug, Deserialize_repr, Serialize_repr)]
+#[repr(u8)]
+enum PathwayMode {
+    /// 通路
+    Walkway = 1,
+    /// 階段
+    Stairs = 2,
+    /// 動く歩道
+    MovingSidewalk = 3,
+    /// エスカレーター
+    Escalator = 4,
+    /// エレベーター
+    Elevator = 5,
+    /// 改札 (入場)
+    FareGate = 6,
+    /// 改札 (出場)
+    ExitGate = 7,
+}
+
+/// 通行できる向き
+#[derive(Debug, Deserialize_repr, Serialize_repr)]
+#[repr(u8)]
+enum Bidirectional {
+    /// from_stop_idからto_stop_idへの一方通行
+    Unidirectional = 0,
+    /// 双方向
+    Bidirectional = 1,
+}
+
+/// 通路ID (ex: P_1)
+pub type PathwayId = String;
+
+/// 駅構内の通路情報
+/// https://gtfs.org/schedule/reference/#pathwaystxt
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Pathway {
+    /// 通路ID
+    pathway_id: PathwayId,
+    /// 通路の始点となる標柱・出入口などのID
+    from_stop_id: StopId,
+    /// 通路の終点となる標柱・出入口などのID
+    to_stop_id: StopId,
+    /// 通路の種類
+    pathway_mode: PathwayMode,
+    /// 通行できる向き
+    is_bidirectional: Bidirectional,
+    /// 通路の長さ (メートル)
+    length: Option<f32>,
+    /// 通過にかかる時間
+    traversal_time: Option<Second>,
+    /// 階段の段数 (下りは負の数)
+    stair_count: Option<i32>,
+}
+
+impl Table for Pathway {
+    fn table_name() -> &'static str {
+        "pathways"
+    }
+
+    fn column_names() -> &'static [&'static str] {
+        &[
+            "pathway_id",
+            "from_stop_id",
+            "to_stop_id",
+            "pathway_mode",
+            "is_bidirectional",
+            "length",
+            "traversal_time",
+            "stair_count",
+        ]
+    }
+
+    fn create_sql() -> &'static str {
+        "
+        pathway_id text primary key,
+        from_stop_id text not null,
+        to_stop_id text not null,
+        pathway_mode int not null,
+        is_bidirectional int not null,
+        length double,
+        traversal_time int,
+        stair_count int
+        "
+    }
+}
@@ -0,0 +1,19 @@
+use serde::{Deserialize, Serialize};
+
+use crate::external::gtfs::routes::RouteId;
+use crate::external::gtfs::shapes::ShapeId;
+use crate::external::gtfs::trips::Direction;
+
+/// 経路・上下区分ごとに便が使う描画IDと便数. tripsを集計したもの
+/// GTFSのファイルではなく検索結果としてのみ使う
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RouteShape {
+    /// 経路ID
+    pub route_id: RouteId,
+    /// 上下区分
+    pub direction_id: Option<Direction>,
+    /// 描画ID
+    pub shape_id: ShapeId,
+    /// この描画IDを使う便の数
+    pub trips: u32,
+}
@@ -2,34 +2,53 @@ use serde::{Deserialize, Serialize};
 use serde_repr::{Deserialize_repr, Serialize_repr};
 
 use crate::external::gtfs::agency::AgencyId;
-use crate::external::gtfs::Color;
+use crate::external::gtfs::{default_if_empty, Color, ContinuousStop};
 use crate::external::gtfsdb::Table;
 
 /// 経路ID (ex: 1001)
 pub type RouteId = String;
 
-// GTFSの場合は他にも追加されるはず
-#[derive(Debug, Deserialize_repr, Serialize_repr)]
+/// 経路タイプ (GTFS-JPではバスのみ)
+/// https://gtfs.org/schedule/reference/#routestxt
+#[derive(Debug, Clone, Deserialize_repr, Serialize_repr)]
 #[repr(u8)]
 enum RouteType {
+    /// 路面電車
+    Tram = 0,
+    /// 地下鉄
+    Subway = 1,
+    /// 鉄道
+    Rail = 2,
     /// バス
-    BUS = 3,
+    Bus = 3,
+    /// フェリー
+    Ferry = 4,
+    /// ケーブルカー (路面を走るもの)
+    CableTram = 5,
+    /// ロープウェイ・ゴンドラ
+    AerialLift = 6,
+    /// ケーブルカー (鋼索鉄道)
+    Funicular = 7,
+    /// トロリーバス
+    Trolleybus = 11,
+    /// モノレール
+    Monorail = 12,
 }
 
 /// 経路情報
 /// https://www.gtfs.jp/developpers-guide/format-reference.html#routes
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Route {
     /// 経路ID
-    route_id: RouteId,
+    pub route_id: RouteId,
     /// 事業者ID
-    agency_id: AgencyId,
+    pub agency_id: AgencyId,
     /// 経路略称 (ex: 東16)
     /// route_long_nameとどちらか1つは指定必須
     route_short_name: Option<String>,
     /// 経路名 (ex: 東京駅八重洲口～月島駅前～東京ビ ッグサイト)
     /// route_long_nameとどちらか1つは指定必須
-    route_long_name: Option<String>,
+    pub route_long_name: Option<String>,
     /// 経路情報
     route_desc: Option<String>,
     /// 経路タイプ
@@ -40,10 +59,30 @@ pub struct Route {
     route_color: Option<Color>,
     /// 経路文字色 (ex: 000000)
     route_text_color: Option<Color>,
+    /// 経路上での乗車 (空欄は停留所以外で乗車不可) (ex: 1)
+    #[serde(default, deserialize_with = "default_if_empty")]
+    continuous_pickup: ContinuousStop,
+    /// 経路上での降車 (空欄は停留所以外で降車不可) (ex: 1)
+    #[serde(default, deserialize_with = "default_if_empty")]
+    continuous_drop_off: ContinuousStop,
     /// 路線ID
     jp_parent_route_id: Option<String>,
 }
 
+impl Route {
+    /// 経路色. 省略時はGTFSの既定と同じ白
+    pub fn color(&self) -> Color {
+        self.route_color.clone().unwrap_or_else(Color::white)
+    }
+
+    /// 経路文字色. 省略時は経路色に対して読みやすい黒か白
+    pub fn text_color(&self) -> Color {
+        self.route_text_color
+            .clone()
+            .unwrap_or_else(|| self.color().contrasting_text())
+    }
+}
+
 impl Table for Route {
     fn table_name() -> &'static str {
         "routes"
@@ -60,6 +99,8 @@ impl Table for Route {
             "route_url",
             "route_color",
             "route_text_color",
+            "continuous_pickup",
+            "continuous_drop_off",
             "jp_parent_route_id",
         ]
     }
@@ -75,7 +116,35 @@ impl Table for Route {
         route_url text,
         route_color text,
         route_text_color text,
+        continuous_pickup int,
+        continuous_drop_off int,
         jp_parent_route_id text
         "
     }
+
+    fn index_columns() -> &'static [&'static str] {
+        &["agency_id"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn route_type_deserializes_every_gtfs_value() {
+        for value in &[0, 1, 2, 3, 4, 5, 6, 7, 11, 12] {
+            let route_type: RouteType = serde_json::from_str(&value.to_string()).unwrap();
+            assert_eq!(
+                serde_json::to_string(&route_type).unwrap(),
+                value.to_string()
+            );
+        }
+    }
+
+    #[test]
+    fn route_type_rejects_unknown_value() {
+        assert!(serde_json::from_str::<RouteType>("8").is_err());
+        assert!(serde_json::from_str::<RouteType>("700").is_err());
+    }
 }
@@ -8,18 +8,18 @@ pub type ShapeId = String;
 
 /// 描画情報
 /// https://www.gtfs.jp/developpers-guide/format-reference.html#shapes
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Shape {
     /// 描画ID
-    shape_id: ShapeId,
+    pub shape_id: ShapeId,
     /// 描画緯度
-    shape_pt_lat: Latitude,
+    pub shape_pt_lat: Latitude,
     /// 描画経度
-    shape_pt_lon: Longitude,
+    pub shape_pt_lon: Longitude,
     /// 描画順序
-    shape_pt_sequence: Sequence,
+    pub shape_pt_sequence: Sequence,
     /// 描画距離 (JPでは使わない)
-    shape_dist_traveled: Option<f32>,
+    pub shape_dist_traveled: Option<f32>,
 }
 
 impl Table for Shape {
@@ -1,15 +1,16 @@
 use serde::{Deserialize, Serialize};
 use serde_repr::{Deserialize_repr, Serialize_repr};
 
-use crate::external::gtfs::{Meter, Sequence};
 use crate::external::gtfs::stops::StopId;
 use crate::external::gtfs::trips::TripId;
+use crate::external::gtfs::{default_if_empty, ContinuousStop, GtfsTime, Meter, Sequence};
 use crate::external::gtfsdb::Table;
 
-#[derive(Debug, Deserialize_repr, Serialize_repr)]
+#[derive(Debug, Clone, Default, Deserialize_repr, Serialize_repr)]
 #[repr(u8)]
 enum PickupType {
     /// 通常の乗車地
+    #[default]
     Usual = 0,
     /// 乗車不可能
     Deny = 1,
@@ -19,10 +20,11 @@ enum PickupType {
     NeedDriverReservation = 3,
 }
 
-#[derive(Debug, Deserialize_repr, Serialize_repr)]
+#[derive(Debug, Clone, Default, Deserialize_repr, Serialize_repr)]
 #[repr(u8)]
 enum DropOffType {
     /// 通常の降車地 (ブザーを押して申告する一般的な停留所を含む)
+    #[default]
     Usual = 0,
     /// 降車不可能
     Deny = 1,
@@ -34,26 +36,34 @@ enum DropOffType {
 
 /// 通過時刻情報
 /// https://www.gtfs.jp/developpers-guide/format-reference.html#stop_times
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct StopTime {
     /// 便ID
-    trip_id: TripId,
-    /// 到着時刻 (ex: 7:00:00)
-    arrival_time: String,
-    /// 出発時刻 (ex: 7:00:00)
-    departure_time: String,
+    pub trip_id: TripId,
+    /// 到着時刻 (始発と終着以外は省略可) (ex: 7:00:00)
+    pub arrival_time: Option<GtfsTime>,
+    /// 出発時刻 (始発と終着以外は省略可) (ex: 7:00:00)
+    pub departure_time: Option<GtfsTime>,
     /// 標柱ID (location_type=0のstopのみ結合可) (ex: 100_10)
-    stop_id: StopId,
+    pub stop_id: StopId,
     /// 通過順位 (ex: 0)
-    stop_sequence: Sequence,
+    pub stop_sequence: Sequence,
     /// 停留所行先 (ex: 東京ビッグサイト（月島駅経由）)
     stop_headsign: Option<String>,
-    /// 乗車区分 (ex: 0)
-    pickup_type: Option<PickupType>,
-    /// 降車区分 (ex: 0)
-    drop_off_type: Option<DropOffType>,
+    /// 乗車区分 (空欄は通常の乗車地) (ex: 0)
+    #[serde(default, deserialize_with = "default_if_empty")]
+    pickup_type: PickupType,
+    /// 降車区分 (空欄は通常の降車地) (ex: 0)
+    #[serde(default, deserialize_with = "default_if_empty")]
+    drop_off_type: DropOffType,
+    /// この標柱から次の標柱までの経路上での乗車 (空欄は乗車不可) (ex: 1)
+    #[serde(default, deserialize_with = "default_if_empty")]
+    continuous_pickup: ContinuousStop,
+    /// この標柱から次の標柱までの経路上での降車 (空欄は降車不可) (ex: 1)
+    #[serde(default, deserialize_with = "default_if_empty")]
+    continuous_drop_off: ContinuousStop,
     /// 通算距離 (メートル) (ex: 0)
-    shape_dist_traveled: Option<Meter>,
+    pub shape_dist_traveled: Option<Meter>,
     /// 発着時間精度 (日本では使用しない)
     timepoint: Option<i32>,
 }
@@ -73,6 +83,8 @@ impl Table for StopTime {
             "stop_headsign",
             "pickup_type",
             "drop_off_type",
+            "continuous_pickup",
+            "continuous_drop_off",
             "shape_dist_traveled",
             "timepoint",
         ]
@@ -81,16 +93,22 @@ impl Table for StopTime {
     fn create_sql() -> &'static str {
         "
         trip_id text,
-        arrival_time datetime not null,
-        departure_time datetime not null,
+        arrival_time datetime,
+        departure_time datetime,
         stop_id text not null,
         stop_sequence int,
         stop_headsign text,
         pickup_type int,
         drop_off_type int,
+        continuous_pickup int,
+        continuous_drop_off int,
         shape_dist_traveled int,
         timepoint int,
         PRIMARY KEY(trip_id, stop_sequence)
         "
     }
+
+    fn index_columns() -> &'static [&'static str] {
+        &["trip_id", "stop_id"]
+    }
 }
@@ -0,0 +1,23 @@
+use serde::Deserialize;
+
+use crate::external::gtfs::stops::StopId;
+use crate::external::gtfs::transfers::TransferType;
+use crate::external::gtfs::Second;
+
+/// transfersとstopsを結合した乗換の1行
+/// GTFSのファイルではなく検索結果としてのみ使う
+#[derive(Debug, Deserialize)]
+pub struct StopTransfer {
+    /// 乗換元標柱ID
+    pub from_stop_id: StopId,
+    /// 乗換元標柱名称 (stopsに存在しない場合はNone)
+    pub from_stop_name: Option<String>,
+    /// 乗換先標柱ID
+    pub to_stop_id: StopId,
+    /// 乗換先標柱名称 (stopsに存在しない場合はNone)
+    pub to_stop_name: Option<String>,
+    /// 乗換タイプ
+    pub transfer_type: TransferType,
+    /// 乗換時間
+    pub min_transfer_time: Option<Second>,
+}
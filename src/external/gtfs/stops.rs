@@ -1,8 +1,10 @@
 use serde::{Deserialize, Serialize};
 use serde_repr::{Deserialize_repr, Serialize_repr};
+use strum_macros::{EnumString, EnumVariantNames};
 
-use crate::external::gtfs::{Latitude, Longitude, Timezone, Url, ZoneId};
+use crate::external::gtfs::{default_if_empty, Latitude, Longitude, Timezone, Url, ZoneId};
 use crate::external::gtfsdb::Table;
+use crate::io::geojson::{position, Geometry, ToGeoFeature};
 
 /// 停留所・標柱ID (ex: ①100 ②100_10)
 pub type StopId = String;
@@ -17,6 +19,28 @@ enum LocationType {
     Pole = 0,
     /// 停留所
     Stop = 1,
+    /// 駅の出入口
+    EntranceExit = 2,
+    /// 駅構内の通路などの結節点
+    GenericNode = 3,
+    /// 標柱内の乗降位置
+    BoardingArea = 4,
+}
+
+/// 車椅子での乗車可否 (親駅の場合は構内の標柱のいずれかへ車椅子で到達できるか)
+#[derive(
+    Debug, Clone, Copy, Default, Deserialize_repr, Serialize_repr, EnumString, EnumVariantNames,
+)]
+#[repr(u8)]
+#[strum(serialize_all = "kebab-case")]
+pub enum WheelchairBoarding {
+    /// 情報なし
+    #[default]
+    Unknown = 0,
+    /// 車椅子で乗車可能
+    Accessible = 1,
+    /// 車椅子で乗車不可
+    NotAccessible = 2,
 }
 
 /// 停留所・標柱情報
@@ -24,7 +48,7 @@ enum LocationType {
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Stop {
     /// 停留所・標柱ID
-    stop_id: StopId,
+    pub stop_id: StopId,
     /// 停留所・標柱番号
     stop_code: Option<String>,
     /// 停留所・標柱名称 (ex: ①東京駅八重洲口 ②東京駅八重洲口)
@@ -32,21 +56,22 @@ pub struct Stop {
     /// 停留所・標柱付加情報
     stop_desc: Option<String>,
     /// 緯度 (ex: ①35.680515 ※ターミナル中心 ②35.679752 ※標柱位置)
-    stop_lat: Latitude,
+    pub stop_lat: Latitude,
     /// 経度 (ex: ①139.764698 ※ターミナル中心）②139.768330 ※標柱位置)
-    stop_lon: Longitude,
+    pub stop_lon: Longitude,
     /// 運賃エリアID (ex: ①設定しない ②Z_210　※都区内エリアID)
     zone_id: Option<ZoneId>,
     /// 停留所・標柱URL
     stop_url: Option<Url>,
     /// 停留所・標柱区分
     location_type: Option<LocationType>,
-    /// 親駅情報
-    parent_station: Option<StopId>,
+    /// 親駅情報 (標柱・出入口などは停留所, 乗降位置は標柱を指す)
+    pub parent_station: Option<StopId>,
     /// タイムゾーン (日本ではagency_timezoneが優先されるため不要)
     stop_timezone: Option<Timezone>,
-    /// 車椅子情報 (日本のバスでは設定しなそうなのでenum定義しない)
-    wheelchair_boarding: Option<u32>,
+    /// 車椅子情報 (空欄は情報なし)
+    #[serde(default, deserialize_with = "default_if_empty")]
+    wheelchair_boarding: WheelchairBoarding,
     /// のりば情報 (ex: ①※設定なし ②10)
     platform_code: Option<PlatformCode>,
 }
@@ -88,7 +113,34 @@ impl Table for Stop {
         parent_station text,
         stop_timezone text,
         wheelchair_boarding int,
-        platform_code text
+        platform_code text,
+        FOREIGN KEY(parent_station) REFERENCES stops(stop_id) DEFERRABLE INITIALLY DEFERRED
         "
     }
+
+    fn index_columns() -> &'static [&'static str] {
+        &["parent_station"]
+    }
+}
+
+impl Stop {
+    /// 親駅の階層の深さ (停留所=0, 標柱・出入口など=1, 乗降位置=2)
+    /// 親駅から順に登録すれば分割して登録しても外部キー制約に違反しない
+    pub fn depth(&self) -> u8 {
+        match self.location_type {
+            Some(LocationType::Stop) => 0,
+            Some(LocationType::BoardingArea) => 2,
+            _ => 1,
+        }
+    }
+}
+
+impl ToGeoFeature for Stop {
+    fn geometry_fields() -> &'static [&'static str] {
+        &["stop_lat", "stop_lon"]
+    }
+
+    fn geometry(&self) -> Geometry {
+        Geometry::Point(position(self.stop_lat, self.stop_lon))
+    }
 }
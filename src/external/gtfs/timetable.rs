@@ -0,0 +1,27 @@
+use serde::Deserialize;
+
+use crate::external::gtfs::stops::StopId;
+use crate::external::gtfs::trips::{Direction, TripId};
+use crate::external::gtfs::{GtfsTime, Sequence};
+
+/// trips, stop_times, stopsを結合した時刻表の1行
+/// GTFSのファイルではなく検索結果としてのみ使う
+#[derive(Debug, Deserialize)]
+pub struct TimetableRow {
+    /// 便ID
+    pub trip_id: TripId,
+    /// 便行き先
+    pub trip_headsign: Option<String>,
+    /// 上下区分
+    pub direction_id: Option<Direction>,
+    /// 通過順位
+    pub stop_sequence: Sequence,
+    /// 標柱ID
+    pub stop_id: StopId,
+    /// 停留所・標柱名称 (stopsに存在しない場合はNone)
+    pub stop_name: Option<String>,
+    /// 到着時刻
+    pub arrival_time: Option<GtfsTime>,
+    /// 出発時刻
+    pub departure_time: Option<GtfsTime>,
+}
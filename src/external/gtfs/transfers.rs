@@ -6,17 +6,17 @@ use crate::external::gtfs::Second;
 use crate::external::gtfsdb::Table;
 
 /// 利用タイプ
-#[derive(Debug, Deserialize_repr, Serialize_repr)]
+#[derive(Debug, PartialEq, Deserialize_repr, Serialize_repr)]
 #[repr(u8)]
-enum TransferType {
+pub enum TransferType {
     /// 2つの経路間の推奨乗換地点
     Recommended = 0,
-    /// 2つの経路間で時間に余裕のある乗換地点
-    Afford = 1,
-    /// 2つの経路間で時間ギリギリの乗換地点
-    Barely = 2,
+    /// 2つの経路間で時間に余裕のある乗換地点 (接続を保証)
+    Timed = 1,
+    /// 2つの経路間で時間ギリギリの乗換地点 (min_transfer_timeが必要)
+    MinTime = 2,
     /// 2つの経路間で乗換が不可能
-    Impossible = 3,
+    NotPossible = 3,
 }
 
 /// 乗換情報
@@ -24,9 +24,9 @@ enum TransferType {
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Transfer {
     /// 乗換元標柱ID
-    from_stop_id: StopId,
+    pub from_stop_id: StopId,
     /// 乗換先標柱ID
-    to_stop_id: StopId,
+    pub to_stop_id: StopId,
     /// 乗換タイプ
     transfer_type: TransferType,
     /// 乗換時間
@@ -4,6 +4,7 @@ use crate::external::gtfs::Lang;
 use crate::external::gtfsdb::Table;
 
 /// 翻訳情報
+/// https://www.gtfs.jp/developpers-guide/format-reference.html#translations
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Translation {
     /// テーブル名
@@ -0,0 +1,29 @@
+use serde::{Deserialize, Serialize};
+
+use crate::external::gtfs::stops::StopId;
+use crate::external::gtfs::trips::{Direction, TripId};
+use crate::external::gtfs::GtfsTime;
+
+/// 便の始発と終着. trips, stop_times, stopsを結合したもの
+/// GTFSのファイルではなく検索結果としてのみ使う
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TripEndpoint {
+    /// 便ID
+    pub trip_id: TripId,
+    /// 便行き先
+    pub trip_headsign: Option<String>,
+    /// 上下区分
+    pub direction_id: Option<Direction>,
+    /// 始発の標柱ID (通過順位が最小)
+    pub origin_stop_id: StopId,
+    /// 始発の標柱名称 (stopsに存在しない場合はNone)
+    pub origin_stop_name: Option<String>,
+    /// 始発の出発時刻
+    pub first_departure_time: Option<GtfsTime>,
+    /// 終着の標柱ID (通過順位が最大)
+    pub destination_stop_id: StopId,
+    /// 終着の標柱名称 (stopsに存在しない場合はNone)
+    pub destination_stop_name: Option<String>,
+    /// 終着の到着時刻
+    pub last_arrival_time: Option<GtfsTime>,
+}
@@ -1,20 +1,34 @@
+use hibou_derive::{EnumValues, Table};
 use serde::{Deserialize, Serialize};
 use serde_repr::{Deserialize_repr, Serialize_repr};
+use strum_macros::{EnumString, EnumVariantNames};
 
 use crate::external::gtfs::calendar::ServiceId;
+use crate::external::gtfs::office_jp::JpOfficeId;
 use crate::external::gtfs::routes::RouteId;
-use crate::external::gtfsdb::Table;
+use crate::external::gtfs::shapes::ShapeId;
 
-#[derive(Debug, Deserialize_repr, Serialize_repr)]
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Deserialize_repr,
+    Serialize_repr,
+    EnumString,
+    EnumVariantNames,
+    EnumValues,
+)]
 #[repr(u8)]
-enum Direction {
+#[strum(serialize_all = "kebab-case")]
+pub enum Direction {
     /// 往路
     Outbound = 0,
     /// 復路
     Inbound = 1,
 }
 
-#[derive(Debug, Deserialize_repr, Serialize_repr)]
+#[derive(Debug, Deserialize_repr, Serialize_repr, EnumValues)]
 #[repr(u8)]
 enum WheelchairAccessible {
     /// 車いすによる乗車可否の情報なし
@@ -25,7 +39,7 @@ enum WheelchairAccessible {
     Deny = 2,
 }
 
-#[derive(Debug, Deserialize_repr, Serialize_repr)]
+#[derive(Debug, Deserialize_repr, Serialize_repr, EnumValues)]
 #[repr(u8)]
 enum BikesAllowed {
     /// 自転車の持込可否の情報なし
@@ -38,80 +52,53 @@ enum BikesAllowed {
 
 /// 便ID (ex: 1001_WD_001)
 pub type TripId = String;
-
-/// 営業所ID (ex: S)
-type JpOfficeId = String;
+/// 便結合区分 (同じ車両で続けて運行する便に共通のID)
+pub type BlockId = String;
 
 /// 便情報
 /// https://www.gtfs.jp/developpers-guide/format-reference.html#trips
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, Table)]
+#[table(name = "trips")]
+#[table(index = "route_id")]
+#[table(index = "service_id")]
 pub struct Trip {
     /// 経路ID
-    route_id: RouteId,
+    #[table(sql = "text not null")]
+    pub route_id: RouteId,
     /// 運行日ID
-    service_id: ServiceId,
+    #[table(sql = "text not null")]
+    pub service_id: ServiceId,
     /// 便ID
-    trip_id: TripId,
+    #[table(sql = "text primary key")]
+    pub trip_id: TripId,
     /// 便行き先 (ex: 東京ビッグサイト（月島駅経由）)
-    trip_headsign: Option<String>,
+    #[table(sql = "text")]
+    pub trip_headsign: Option<String>,
     /// 便名称
+    #[table(sql = "text")]
     trip_short_name: Option<String>,
     /// 上下区分
+    #[table(sql = "int", enum_values)]
     direction_id: Option<Direction>,
     /// 便結合区分
-    block_id: Option<String>,
+    #[table(sql = "text")]
+    block_id: Option<BlockId>,
     /// 描画ID (ex: S_1001)
-    shape_id: Option<String>,
+    #[table(sql = "text")]
+    pub shape_id: Option<ShapeId>,
     /// 車いす利用区分
+    #[table(sql = "int", enum_values)]
     wheelchair_accessible: Option<WheelchairAccessible>,
     /// 自転車持込区分
+    #[table(sql = "int", enum_values)]
     bikes_allowed: Option<BikesAllowed>,
     /// 便情報
+    #[table(sql = "text")]
     jp_trip_desc: Option<String>,
     /// 便記号
+    #[table(sql = "text")]
     jp_trip_desc_symbol: Option<String>,
     /// 営業所ID
-    jp_office_id: Option<JpOfficeId>,
-}
-
-impl Table for Trip {
-    fn table_name() -> &'static str {
-        "trips"
-    }
-
-    fn column_names() -> &'static [&'static str] {
-        &[
-            "route_id",
-            "service_id",
-            "trip_id",
-            "trip_headsign",
-            "trip_short_name",
-            "direction_id",
-            "block_id",
-            "shape_id",
-            "wheelchair_accessible",
-            "bikes_allowed",
-            "jp_trip_desc",
-            "jp_trip_desc_symbol",
-            "jp_office_id",
-        ]
-    }
-
-    fn create_sql() -> &'static str {
-        "
-         route_id text not null,
-         service_id text not null,
-         trip_id text primary key,
-         trip_headsign text,
-         trip_short_name text,
-         direction_id int,
-         block_id text,
-         shape_id text,
-         wheelchair_accessible int,
-         bikes_allowed int,
-         jp_trip_desc text,
-         jp_trip_desc_symbol text,
-         jp_office_id text
-        "
-    }
+    #[table(sql = "text")]
+    pub jp_office_id: Option<JpOfficeId>,
 }
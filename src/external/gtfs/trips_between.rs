@@ -0,0 +1,31 @@
+use serde::{Deserialize, Serialize};
+
+use crate::external::gtfs::calendar::ServiceId;
+use crate::external::gtfs::routes::RouteId;
+use crate::external::gtfs::stops::StopId;
+use crate::external::gtfs::trips::{Direction, TripId};
+use crate::external::gtfs::GtfsTime;
+
+/// 2つの標柱をこの順に通る便. tripsとstop_timesを結合したもの
+/// GTFSのファイルではなく検索結果としてのみ使う
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TripBetween {
+    /// 便ID
+    pub trip_id: TripId,
+    /// 経路ID
+    pub route_id: RouteId,
+    /// 運行日ID
+    pub service_id: ServiceId,
+    /// 便行き先
+    pub trip_headsign: Option<String>,
+    /// 上下区分
+    pub direction_id: Option<Direction>,
+    /// 乗車する標柱ID
+    pub from_stop_id: StopId,
+    /// 乗車する標柱の出発時刻
+    pub departure_time: Option<GtfsTime>,
+    /// 降車する標柱ID
+    pub to_stop_id: StopId,
+    /// 降車する標柱の到着時刻
+    pub arrival_time: Option<GtfsTime>,
+}
@@ -1,40 +1,290 @@
-use std::path::PathBuf;
-
-use anyhow::Result;
-
-use crate::external::gtfs::agency::Agency;
-use crate::external::gtfs::calendar::Calendar;
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use log::warn;
+use serde::de::DeserializeOwned;
+use sha2::{Digest, Sha256};
+use zip::ZipArchive;
+
+use crate::external::gtfs::agency::{Agency, AgencyId};
+use crate::external::gtfs::attributions::Attribution;
+use crate::external::gtfs::block_trips::BlockTrip;
+use crate::external::gtfs::calendar::{Calendar, ServiceId};
 use crate::external::gtfs::calendar_dates::CalendarDate;
 use crate::external::gtfs::fare_attributes::FareAttribute;
 use crate::external::gtfs::fare_rules::FareRule;
-use crate::external::gtfs::feed_info::Feed;
+use crate::external::gtfs::feed_info::FeedInfo;
 use crate::external::gtfs::frequencies::Frequency;
-use crate::external::gtfs::routes::Route;
-use crate::external::gtfs::shapes::Shape;
+use crate::external::gtfs::levels::Level;
+use crate::external::gtfs::office_jp::{JpOfficeId, OfficeJp};
+use crate::external::gtfs::pathways::Pathway;
+use crate::external::gtfs::route_shapes::RouteShape;
+use crate::external::gtfs::routes::{Route, RouteId};
+use crate::external::gtfs::shapes::{Shape, ShapeId};
 use crate::external::gtfs::stop_times::StopTime;
-use crate::external::gtfs::stops::Stop;
+use crate::external::gtfs::stop_transfers::StopTransfer;
+use crate::external::gtfs::stops::{Stop, StopId};
+use crate::external::gtfs::timetable::TimetableRow;
 use crate::external::gtfs::transfers::Transfer;
 use crate::external::gtfs::translations::Translation;
-use crate::external::gtfs::trips::Trip;
-use crate::external::gtfs::Gtfs;
+use crate::external::gtfs::trip_endpoints::TripEndpoint;
+use crate::external::gtfs::trips::{BlockId, Direction, Trip, TripId};
+use crate::external::gtfs::trips_between::TripBetween;
+use crate::external::gtfs::{Gtfs, Latitude, Longitude, Query};
+use crate::external::gtfsdb;
+use crate::external::gtfsdb::Table;
 use crate::io;
+use crate::io::{Encoding, InvalidRow};
+
+/// GTFSファイルの読み込み元
+enum Source {
+    Dir(PathBuf),
+    Zip {
+        archive: ZipArchive<File>,
+        /// 小文字のファイル名 → zip内のエントリ名
+        entries: HashMap<String, String>,
+    },
+    /// 標準入力から1テーブル分だけ読み込む
+    Stdin {
+        /// 読み込むファイル名 (ex: stops.txt)
+        file_name: String,
+    },
+}
 
 pub struct GtfsCsv {
-    gtfs_dir: PathBuf,
+    source: Source,
+    encoding: Encoding,
+    /// Noneならパースできない行でエラーにする
+    invalid_rows: Option<Vec<InvalidRow>>,
+}
+
+/// 存在しなければ取り込みを始めない必須ファイル (拡張子なし)
+const REQUIRED_TABLES: &[&str] = &["agency", "stops", "routes", "trips", "stop_times"];
+
+pub fn init(path: PathBuf, encoding: Encoding) -> Result<GtfsCsv> {
+    GtfsCsv::new(path, encoding)
 }
 
-pub fn init(path: PathBuf) -> Result<Box<dyn Gtfs>> {
-    let ins = GtfsCsv::new(path)?;
-    Ok(Box::new(ins))
+/// 標準入力をtable_nameのファイルとして読み込む
+pub fn init_stdin(table_name: &str, encoding: Encoding) -> Result<GtfsCsv> {
+    let names: Vec<&str> = gtfsdb::schema().into_iter().map(|(x, _)| x).collect();
+    if !names.contains(&table_name) {
+        bail!(
+            "テーブルが見つかりません: {} (指定できるテーブル: {})",
+            table_name,
+            names.join(", ")
+        );
+    }
+    Ok(GtfsCsv {
+        source: Source::Stdin {
+            file_name: format!("{}.txt", table_name),
+        },
+        encoding,
+        invalid_rows: None,
+    })
 }
 
 impl GtfsCsv {
-    pub fn new(gtfs_dir: PathBuf) -> Result<Self> {
-        Ok(GtfsCsv { gtfs_dir })
+    /// pathにはGTFSのディレクトリかzipファイルを指定する
+    pub fn new(path: PathBuf, encoding: Encoding) -> Result<Self> {
+        let source = if path.is_file() {
+            open_zip(&path)?
+        } else {
+            Source::Dir(path)
+        };
+        let ins = GtfsCsv {
+            source,
+            encoding,
+            invalid_rows: None,
+        };
+
+        let mut missing = vec![];
+        for table in REQUIRED_TABLES {
+            if !ins.exists(table)? {
+                missing.push(format!("{}.txt", table));
+            }
+        }
+        if !missing.is_empty() {
+            bail!("必須ファイルがありません: {}", missing.join(", "));
+        }
+
+        Ok(ins)
+    }
+
+    /// 有効にするとパースできない行でエラーにせず, 読み飛ばして記録する
+    pub fn set_skip_invalid(&mut self, enabled: bool) {
+        self.invalid_rows = if enabled { Some(vec![]) } else { None };
+    }
+
+    /// 読み飛ばした行を取り出す
+    pub fn take_invalid_rows(&mut self) -> Vec<InvalidRow> {
+        self.invalid_rows
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
+    }
+
+    /// 取り込み元ファイルのSHA-256
+    pub fn file_hash(&mut self, table_name: &str) -> Result<String> {
+        let (mut reader, name) = Self::open_file(&mut self.source, &format!("{}.txt", table_name))?;
+        let mut hasher = Sha256::new();
+        std::io::copy(&mut reader, &mut hasher)
+            .with_context(|| format!("{:?} が読み込めませんでした", name))?;
+        Ok(format!("{:x}", hasher.finalize()))
+    }
+
+    fn file_name<T: Table>() -> String {
+        format!("{}.txt", T::table_name())
+    }
+
+    /// ファイルのreaderと, エラーメッセージに使うファイル名を返す
+    fn open_file<'a>(
+        source: &'a mut Source,
+        file_name: &str,
+    ) -> Result<(Box<dyn std::io::Read + 'a>, String)> {
+        match source {
+            Source::Dir(dir) => {
+                let path = dir.join(file_name);
+                let file = File::open(&path)
+                    .with_context(|| format!("{:?} が読み込めませんでした", path))?;
+                Ok((Box::new(file), path.to_string_lossy().into_owned()))
+            }
+            Source::Zip { archive, entries } => {
+                let entry = entries
+                    .get(file_name)
+                    .with_context(|| format!("zip内に {} がありません", file_name))?;
+                let file = archive
+                    .by_name(entry)
+                    .with_context(|| format!("zip内の {} が読み込めませんでした", entry))?;
+                Ok((Box::new(file), entry.clone()))
+            }
+            Source::Stdin { .. } => Ok((Box::new(std::io::stdin()), "stdin".to_string())),
+        }
+    }
+
+    /// ファイルをUTF-8に変換しながら読み込むreaderと, エラーメッセージに使うファイル名を返す
+    fn open<T: Table>(
+        source: &mut Source,
+        encoding: Encoding,
+    ) -> Result<(Box<dyn std::io::Read + '_>, String)> {
+        let (reader, name) = Self::open_file(source, &Self::file_name::<T>())?;
+        Ok((encoding.decode(reader), name))
     }
+
+    fn read<T>(&mut self) -> Result<Vec<T>>
+    where
+        T: DeserializeOwned + Send + Table + 'static,
+    {
+        let (reader, name) = Self::open::<T>(&mut self.source, self.encoding)?;
+        io::read_from(
+            reader,
+            &name,
+            check_headers::<T>,
+            self.invalid_rows.as_mut(),
+        )
+    }
+
+    fn read_batches<T>(
+        &mut self,
+        batch_size: usize,
+        each: &mut dyn FnMut(&[T]) -> Result<()>,
+    ) -> Result<usize>
+    where
+        T: DeserializeOwned + Send + Table + 'static,
+    {
+        let (reader, name) = Self::open::<T>(&mut self.source, self.encoding)?;
+        io::read_batches_from(
+            reader,
+            &name,
+            check_headers::<T>,
+            self.invalid_rows.as_mut(),
+            batch_size,
+            |batch| each(&batch),
+        )
+    }
+}
+
+/// 必須カラム (create_sqlでnot nullかprimary keyのカラム) がなければエラーにし,
+/// 定義にないカラムは読み飛ばされるため警告する
+fn check_headers<T: Table>(headers: &csv::StringRecord) -> Result<()> {
+    let file_name = GtfsCsv::file_name::<T>();
+    let missing: Vec<&str> = required_columns::<T>()
+        .into_iter()
+        .filter(|c| !headers.iter().any(|h| h == *c))
+        .collect();
+    if !missing.is_empty() {
+        bail!(
+            "{} に必須カラムがありません: {}",
+            file_name,
+            missing.join(", ")
+        );
+    }
+
+    let unknown: Vec<&str> = headers
+        .iter()
+        .filter(|h| !T::column_names().contains(h))
+        .collect();
+    if !unknown.is_empty() {
+        warn!(
+            "{} の未対応のカラムは無視します: {}",
+            file_name,
+            unknown.join(", ")
+        );
+    }
+    Ok(())
+}
+
+fn required_columns<T: Table>() -> Vec<&'static str> {
+    T::create_sql()
+        .lines()
+        .filter_map(|line| {
+            let mut words = line.trim().trim_end_matches(',').splitn(2, ' ');
+            let column = words.next()?;
+            let definition = words.next()?.to_lowercase();
+            let required = definition.contains("not null") || definition.contains("primary key");
+            if required && T::column_names().contains(&column) {
+                Some(column)
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// zipを開き, 含まれる.txtファイルを大文字小文字やトップレベルのフォルダに関係なく引けるようにする
+fn open_zip(path: &Path) -> Result<Source> {
+    let file = File::open(path).with_context(|| format!("{:?} が読み込めませんでした", path))?;
+    let mut archive = ZipArchive::new(file)
+        .with_context(|| format!("{:?} はzipとして読み込めませんでした", path))?;
+
+    let mut entries = HashMap::new();
+    for i in 0..archive.len() {
+        let entry = archive.by_index(i)?;
+        let name = entry.name()?.to_string();
+        if entry.is_dir() || name.starts_with("__MACOSX/") {
+            continue;
+        }
+        let base_name = name.rsplit('/').next().unwrap_or(&name).to_lowercase();
+        if base_name.ends_with(".txt") {
+            entries.insert(base_name, name);
+        }
+    }
+
+    Ok(Source::Zip { archive, entries })
 }
 
 impl Gtfs for GtfsCsv {
+    fn exists(&self, table_name: &str) -> Result<bool> {
+        let file_name = format!("{}.txt", table_name);
+        Ok(match &self.source {
+            Source::Dir(dir) => dir.join(file_name).exists(),
+            Source::Zip { entries, .. } => entries.contains_key(&file_name),
+            Source::Stdin { file_name: x } => *x == file_name,
+        })
+    }
+
     fn create_all(&self) -> Result<()> {
         unimplemented!()
     }
@@ -43,129 +293,374 @@ impl Gtfs for GtfsCsv {
         unimplemented!()
     }
 
+    fn index_all(&self) -> Result<()> {
+        unimplemented!()
+    }
+
     fn insert_agencies(&mut self, _agencies: &[Agency]) -> Result<()> {
         unimplemented!()
     }
 
-    fn select_agencies(&mut self) -> Result<Vec<Agency>> {
-        let results = io::read::<Agency>(&self.gtfs_dir.join("agency.txt"))?;
-        Ok(results)
+    fn select_agencies(&mut self, query: &Query) -> Result<Vec<Agency>> {
+        let results = self.read::<Agency>()?;
+        Ok(query.paginate(results))
     }
 
     fn insert_stops(&mut self, _stops: &[Stop]) -> Result<()> {
         unimplemented!()
     }
 
-    fn select_stops(&mut self) -> Result<Vec<Stop>> {
-        let results = io::read::<Stop>(&self.gtfs_dir.join("stops.txt"))?;
-        Ok(results)
+    fn select_stops(&mut self, query: &Query) -> Result<Vec<Stop>> {
+        let results = self.read::<Stop>()?;
+        Ok(query.paginate(results))
+    }
+
+    fn select_stops_by_stop_id(&mut self, _stop_id: &StopId, _query: &Query) -> Result<Vec<Stop>> {
+        unimplemented!()
+    }
+
+    fn select_stops_by_parent_station(
+        &mut self,
+        _parent_station: &StopId,
+        _query: &Query,
+    ) -> Result<Vec<Stop>> {
+        unimplemented!()
+    }
+
+    fn select_stops_in_bbox(
+        &mut self,
+        _south_west: (Latitude, Longitude),
+        _north_east: (Latitude, Longitude),
+    ) -> Result<Vec<Stop>> {
+        unimplemented!()
+    }
+
+    fn select_stops_within(
+        &mut self,
+        _center: (Latitude, Longitude),
+        _south_west: (Latitude, Longitude),
+        _north_east: (Latitude, Longitude),
+        _radius: f64,
+    ) -> Result<Vec<Stop>> {
+        unimplemented!()
+    }
+
+    fn select_stops_by_route_id(
+        &mut self,
+        _route_id: &RouteId,
+        _query: &Query,
+    ) -> Result<Vec<Stop>> {
+        unimplemented!()
+    }
+
+    fn select_stops_by_name_like(&mut self, _name: &str, _query: &Query) -> Result<Vec<Stop>> {
+        unimplemented!()
     }
 
     fn insert_routes(&mut self, _routes: &[Route]) -> Result<()> {
         unimplemented!()
     }
 
-    fn select_routes(&mut self) -> Result<Vec<Route>> {
-        let results = io::read::<Route>(&self.gtfs_dir.join("routes.txt"))?;
-        Ok(results)
+    fn select_routes(&mut self, query: &Query) -> Result<Vec<Route>> {
+        let results = self.read::<Route>()?;
+        Ok(query.paginate(results))
+    }
+
+    fn select_routes_by_route_id(
+        &mut self,
+        _route_id: &RouteId,
+        _query: &Query,
+    ) -> Result<Vec<Route>> {
+        unimplemented!()
+    }
+
+    fn select_routes_by_name_like(&mut self, _name: &str, _query: &Query) -> Result<Vec<Route>> {
+        unimplemented!()
+    }
+
+    fn select_routes_by_agency_id(
+        &mut self,
+        _agency_id: &AgencyId,
+        _query: &Query,
+    ) -> Result<Vec<Route>> {
+        unimplemented!()
     }
 
     fn insert_trips(&mut self, _trips: &[Trip]) -> Result<()> {
         unimplemented!()
     }
 
-    fn select_trips(&mut self) -> Result<Vec<Trip>> {
-        let results = io::read::<Trip>(&self.gtfs_dir.join("trips.txt"))?;
-        Ok(results)
+    fn select_trips(&mut self, query: &Query) -> Result<Vec<Trip>> {
+        let results = self.read::<Trip>()?;
+        Ok(query.paginate(results))
+    }
+
+    fn select_trips_by_service_ids(
+        &mut self,
+        _service_ids: &[ServiceId],
+        _query: &Query,
+    ) -> Result<Vec<Trip>> {
+        unimplemented!()
+    }
+
+    fn select_trips_by_route_and_direction(
+        &mut self,
+        _route_id: Option<&RouteId>,
+        _direction: Option<Direction>,
+        _query: &Query,
+    ) -> Result<Vec<Trip>> {
+        unimplemented!()
+    }
+
+    fn select_block_trips(&mut self, _block_id: &BlockId) -> Result<Vec<BlockTrip>> {
+        unimplemented!()
+    }
+
+    fn select_block_trips_by_trip_id(&mut self, _trip_id: &TripId) -> Result<Vec<BlockTrip>> {
+        unimplemented!()
     }
 
     fn insert_stop_times(&mut self, _stop_times: &[StopTime]) -> Result<()> {
         unimplemented!()
     }
 
-    fn select_stop_times(&mut self) -> Result<Vec<StopTime>> {
-        let results = io::read::<StopTime>(&self.gtfs_dir.join("stop_times.txt"))?;
-        Ok(results)
+    fn update_stop_times_dist_traveled(&mut self, _stop_times: &[StopTime]) -> Result<()> {
+        unimplemented!()
+    }
+
+    fn update_stop_times_times(&mut self, _stop_times: &[StopTime]) -> Result<()> {
+        unimplemented!()
+    }
+
+    fn select_stop_times(&mut self, query: &Query) -> Result<Vec<StopTime>> {
+        let results = self.read::<StopTime>()?;
+        Ok(query.paginate(results))
+    }
+
+    fn for_each_stop_times(
+        &mut self,
+        batch_size: usize,
+        each: &mut dyn FnMut(&[StopTime]) -> Result<()>,
+    ) -> Result<usize> {
+        self.read_batches(batch_size, each)
+    }
+
+    fn for_each_stop_times_by_trip(
+        &mut self,
+        _batch_size: usize,
+        _each: &mut dyn FnMut(&[StopTime]) -> Result<()>,
+    ) -> Result<usize> {
+        unimplemented!()
+    }
+
+    fn select_stop_times_by_trip_id(
+        &mut self,
+        _trip_id: &TripId,
+        _query: &Query,
+    ) -> Result<Vec<StopTime>> {
+        unimplemented!()
+    }
+
+    fn select_stop_times_by_stop_id(
+        &mut self,
+        _stop_id: &StopId,
+        _query: &Query,
+    ) -> Result<Vec<StopTime>> {
+        unimplemented!()
+    }
+
+    fn select_timetable_by_route_id(&mut self, _route_id: &RouteId) -> Result<Vec<TimetableRow>> {
+        unimplemented!()
+    }
+
+    fn select_trip_endpoints_by_route_id(
+        &mut self,
+        _route_id: &RouteId,
+    ) -> Result<Vec<TripEndpoint>> {
+        unimplemented!()
+    }
+
+    fn select_trips_between(&mut self, _from: &StopId, _to: &StopId) -> Result<Vec<TripBetween>> {
+        unimplemented!()
     }
 
     fn insert_calendars(&mut self, _calendars: &[Calendar]) -> Result<()> {
         unimplemented!()
     }
 
-    fn select_calendars(&mut self) -> Result<Vec<Calendar>> {
-        let results = io::read::<Calendar>(&self.gtfs_dir.join("calendar.txt"))?;
-        Ok(results)
+    fn select_calendars(&mut self, query: &Query) -> Result<Vec<Calendar>> {
+        let results = self.read::<Calendar>()?;
+        Ok(query.paginate(results))
+    }
+
+    fn select_calendars_by_service_id(
+        &mut self,
+        _service_id: &ServiceId,
+        _query: &Query,
+    ) -> Result<Vec<Calendar>> {
+        unimplemented!()
     }
 
     fn insert_calendar_dates(&mut self, _calendar_dates: &[CalendarDate]) -> Result<()> {
         unimplemented!()
     }
 
-    fn select_calendar_dates(&mut self) -> Result<Vec<CalendarDate>> {
-        let results = io::read::<CalendarDate>(&self.gtfs_dir.join("calendar_dates.txt"))?;
-        Ok(results)
+    fn select_calendar_dates(&mut self, query: &Query) -> Result<Vec<CalendarDate>> {
+        let results = self.read::<CalendarDate>()?;
+        Ok(query.paginate(results))
     }
 
     fn insert_fare_attributes(&mut self, _fare_attributes: &[FareAttribute]) -> Result<()> {
         unimplemented!()
     }
 
-    fn select_fare_attributes(&mut self) -> Result<Vec<FareAttribute>> {
-        let results = io::read::<FareAttribute>(&self.gtfs_dir.join("fare_attributes.txt"))?;
-        Ok(results)
+    fn select_fare_attributes(&mut self, query: &Query) -> Result<Vec<FareAttribute>> {
+        let results = self.read::<FareAttribute>()?;
+        Ok(query.paginate(results))
     }
 
     fn insert_fare_rules(&mut self, _fare_rules: &[FareRule]) -> Result<()> {
         unimplemented!()
     }
 
-    fn select_fare_rules(&mut self) -> Result<Vec<FareRule>> {
-        let results = io::read::<FareRule>(&self.gtfs_dir.join("fare_rules.txt"))?;
-        Ok(results)
+    fn select_fare_rules(&mut self, query: &Query) -> Result<Vec<FareRule>> {
+        let results = self.read::<FareRule>()?;
+        Ok(query.paginate(results))
     }
 
     fn insert_shapes(&mut self, _shapes: &[Shape]) -> Result<()> {
         unimplemented!()
     }
 
-    fn select_shapes(&mut self) -> Result<Vec<Shape>> {
-        let results = io::read::<Shape>(&self.gtfs_dir.join("shapes.txt"))?;
-        Ok(results)
+    fn update_shapes_dist_traveled(&mut self, _shapes: &[Shape]) -> Result<()> {
+        unimplemented!()
+    }
+
+    fn select_shapes(&mut self, query: &Query) -> Result<Vec<Shape>> {
+        let results = self.read::<Shape>()?;
+        Ok(query.paginate(results))
+    }
+
+    fn for_each_shapes(
+        &mut self,
+        batch_size: usize,
+        each: &mut dyn FnMut(&[Shape]) -> Result<()>,
+    ) -> Result<usize> {
+        self.read_batches(batch_size, each)
+    }
+
+    fn select_route_shapes(&mut self) -> Result<Vec<RouteShape>> {
+        unimplemented!()
+    }
+
+    fn select_shapes_by_shape_id(
+        &mut self,
+        _shape_id: &ShapeId,
+        _query: &Query,
+    ) -> Result<Vec<Shape>> {
+        unimplemented!()
     }
 
     fn insert_frequencies(&mut self, _frequencies: &[Frequency]) -> Result<()> {
         unimplemented!()
     }
 
-    fn select_frequencies(&mut self) -> Result<Vec<Frequency>> {
-        let results = io::read::<Frequency>(&self.gtfs_dir.join("frequencies.txt"))?;
-        Ok(results)
+    fn select_frequencies(&mut self, query: &Query) -> Result<Vec<Frequency>> {
+        let results = self.read::<Frequency>()?;
+        Ok(query.paginate(results))
+    }
+
+    fn select_frequencies_by_trip_id(
+        &mut self,
+        _trip_id: &TripId,
+        _query: &Query,
+    ) -> Result<Vec<Frequency>> {
+        unimplemented!()
     }
 
     fn insert_transfers(&mut self, _transfers: &[Transfer]) -> Result<()> {
         unimplemented!()
     }
 
-    fn select_transfers(&mut self) -> Result<Vec<Transfer>> {
-        let results = io::read::<Transfer>(&self.gtfs_dir.join("transfers.txt"))?;
-        Ok(results)
+    fn select_transfers(&mut self, query: &Query) -> Result<Vec<Transfer>> {
+        let results = self.read::<Transfer>()?;
+        Ok(query.paginate(results))
+    }
+
+    fn select_stop_transfers(
+        &mut self,
+        _from_stop_id: Option<&StopId>,
+        _to_stop_id: Option<&StopId>,
+    ) -> Result<Vec<StopTransfer>> {
+        unimplemented!()
+    }
+
+    fn insert_levels(&mut self, _levels: &[Level]) -> Result<()> {
+        unimplemented!()
+    }
+
+    fn select_levels(&mut self, query: &Query) -> Result<Vec<Level>> {
+        let results = self.read::<Level>()?;
+        Ok(query.paginate(results))
     }
 
-    fn insert_feeds(&mut self, _feeds: &[Feed]) -> Result<()> {
+    fn insert_pathways(&mut self, _pathways: &[Pathway]) -> Result<()> {
         unimplemented!()
     }
 
-    fn select_feeds(&mut self) -> Result<Vec<Feed>> {
-        let results = io::read::<Feed>(&self.gtfs_dir.join("feed_info.txt"))?;
-        Ok(results)
+    fn select_pathways(&mut self, query: &Query) -> Result<Vec<Pathway>> {
+        let results = self.read::<Pathway>()?;
+        Ok(query.paginate(results))
+    }
+
+    fn insert_attributions(&mut self, _attributions: &[Attribution]) -> Result<()> {
+        unimplemented!()
+    }
+
+    fn select_attributions(&mut self, query: &Query) -> Result<Vec<Attribution>> {
+        let results = self.read::<Attribution>()?;
+        Ok(query.paginate(results))
+    }
+
+    fn insert_feed_info(&mut self, _feed_info: &FeedInfo) -> Result<()> {
+        unimplemented!()
+    }
+
+    fn select_feed_info(&mut self) -> Result<Option<FeedInfo>> {
+        let results = self.read::<FeedInfo>()?;
+        // feed_infoは1行だけの想定のため, 2行目以降は登録しない
+        if results.len() > 1 {
+            warn!(
+                "feed_info.txtに{}行ありますが, 先頭の1行だけを登録します",
+                results.len()
+            );
+        }
+        Ok(results.into_iter().next())
+    }
+
+    fn insert_offices(&mut self, _offices: &[OfficeJp]) -> Result<()> {
+        unimplemented!()
+    }
+
+    fn select_offices(&mut self, query: &Query) -> Result<Vec<OfficeJp>> {
+        let results = self.read::<OfficeJp>()?;
+        Ok(query.paginate(results))
+    }
+
+    fn select_offices_by_office_id(
+        &mut self,
+        _office_id: &JpOfficeId,
+        _query: &Query,
+    ) -> Result<Vec<OfficeJp>> {
+        unimplemented!()
     }
 
     fn insert_translations(&mut self, _translations: &[Translation]) -> Result<()> {
         unimplemented!()
     }
 
-    fn select_translations(&mut self) -> Result<Vec<Translation>> {
-        let results = io::read::<Translation>(&self.gtfs_dir.join("translations.txt"))?;
-        Ok(results)
+    fn select_translations(&mut self, query: &Query) -> Result<Vec<Translation>> {
+        let results = self.read::<Translation>()?;
+        Ok(query.paginate(results))
     }
 }
@@ -1,59 +1,381 @@
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
 
-use anyhow::{Context, Result};
-use log::{debug, trace};
-use rusqlite::{Connection, NO_PARAMS};
+use anyhow::{bail, Context, Result};
+use log::{debug, error, trace};
+use rusqlite::types::{ToSql, ToSqlOutput, Value};
+use rusqlite::{Connection, LoadExtensionGuard, Transaction, NO_PARAMS};
 use serde::__private::fmt::Debug;
-use serde_rusqlite::{from_rows, to_params_named};
+use serde::Serialize;
+use serde_rusqlite::{from_rows, to_params_named, NamedParamSlice};
 
-use crate::external::gtfs::agency::Agency;
-use crate::external::gtfs::calendar::Calendar;
+use crate::external::gtfs::agency::{Agency, AgencyId};
+use crate::external::gtfs::attributions::Attribution;
+use crate::external::gtfs::block_trips::BlockTrip;
+use crate::external::gtfs::calendar::{Calendar, ServiceId};
 use crate::external::gtfs::calendar_dates::CalendarDate;
 use crate::external::gtfs::fare_attributes::FareAttribute;
 use crate::external::gtfs::fare_rules::FareRule;
-use crate::external::gtfs::feed_info::Feed;
+use crate::external::gtfs::feed_info::FeedInfo;
 use crate::external::gtfs::frequencies::Frequency;
-use crate::external::gtfs::routes::Route;
-use crate::external::gtfs::shapes::Shape;
+use crate::external::gtfs::levels::Level;
+use crate::external::gtfs::office_jp::{JpOfficeId, OfficeJp};
+use crate::external::gtfs::pathways::Pathway;
+use crate::external::gtfs::route_shapes::RouteShape;
+use crate::external::gtfs::routes::{Route, RouteId};
+use crate::external::gtfs::shapes::{Shape, ShapeId};
 use crate::external::gtfs::stop_times::StopTime;
-use crate::external::gtfs::stops::Stop;
+use crate::external::gtfs::stop_transfers::StopTransfer;
+use crate::external::gtfs::stops::{Stop, StopId};
+use crate::external::gtfs::timetable::TimetableRow;
 use crate::external::gtfs::transfers::Transfer;
 use crate::external::gtfs::translations::Translation;
-use crate::external::gtfs::trips::Trip;
-use crate::external::gtfs::Gtfs;
+use crate::external::gtfs::trip_endpoints::TripEndpoint;
+use crate::external::gtfs::trips::{BlockId, Direction, Trip, TripId};
+use crate::external::gtfs::trips_between::TripBetween;
+use crate::external::gtfs::{Gtfs, Latitude, Longitude, OnConflict, Query};
+use crate::external::gtfsdb::meta::Meta;
+
+pub mod meta;
 
 pub struct GtfsDb {
     connection: Connection,
+    on_conflict: OnConflict,
+    enforce_fk: bool,
+    /// 1つのINSERT文にまとめる行数 (Noneならテーブルごとの上限)
+    batch_size: Option<usize>,
+    /// set_fast_importで変更する前のPRAGMA. 無効にするか破棄するときに元に戻す
+    saved_pragmas: Option<Pragmas>,
+}
+
+/// set_fast_importで変更するPRAGMAの値
+#[derive(Debug)]
+struct Pragmas {
+    journal_mode: String,
+    synchronous: i64,
+    temp_store: i64,
+}
+
+impl Pragmas {
+    fn read(conn: &Connection) -> Result<Self> {
+        let pragma = |name: &str| format!("PRAGMA {}", name);
+        Ok(Pragmas {
+            journal_mode: conn.query_row(&pragma("journal_mode"), NO_PARAMS, |row| row.get(0))?,
+            synchronous: conn.query_row(&pragma("synchronous"), NO_PARAMS, |row| row.get(0))?,
+            temp_store: conn.query_row(&pragma("temp_store"), NO_PARAMS, |row| row.get(0))?,
+        })
+    }
+
+    fn apply(&self, conn: &Connection) -> Result<()> {
+        conn.execute_batch(&format!(
+            "
+            PRAGMA journal_mode = {};
+            PRAGMA synchronous = {};
+            PRAGMA temp_store = {};
+            ",
+            self.journal_mode, self.synchronous, self.temp_store
+        ))?;
+        Ok(())
+    }
 }
 
 pub trait Table {
     fn table_name() -> &'static str;
     fn column_names() -> &'static [&'static str];
     fn create_sql() -> &'static str;
+    /// 登録後にインデックスを作成するカラム
+    fn index_columns() -> &'static [&'static str] {
+        &[]
+    }
+    /// 全件を取得する際の並び順. 既定ではcreate_sqlの主キー (主キーがなければ空)
+    fn order_by() -> Vec<&'static str> {
+        const TABLE_CONSTRAINT: &str = "PRIMARY KEY(";
+        let sql = Self::create_sql();
+        if let Some(start) = sql.find(TABLE_CONSTRAINT) {
+            let columns = &sql[start + TABLE_CONSTRAINT.len()..];
+            let end = columns.find(')').unwrap_or(columns.len());
+            return columns[..end].split(',').map(str::trim).collect();
+        }
+        sql.split(',')
+            .map(str::trim)
+            .filter(|x| x.to_lowercase().contains("primary key"))
+            .filter_map(|x| x.split_whitespace().next())
+            .collect()
+    }
+    /// 各カラムの定義. 既定ではcreate_sqlから型とNOT NULL制約を読み取る
+    fn columns() -> Vec<ColumnDescription> {
+        Self::column_names()
+            .iter()
+            .map(|name| {
+                let sql = Self::create_sql()
+                    .split(',')
+                    .map(str::trim)
+                    .find_map(|x| x.strip_prefix(name)?.strip_prefix(' '))
+                    .unwrap_or_default()
+                    .to_lowercase();
+                ColumnDescription {
+                    name,
+                    sql_type: sql
+                        .split_whitespace()
+                        .next()
+                        .unwrap_or_default()
+                        .to_string(),
+                    nullable: !sql.contains("not null") && !sql.contains("primary key"),
+                    values: None,
+                }
+            })
+            .collect()
+    }
+    /// create_sqlのカラム定義でテーブルを作成する
+    fn create(conn: &Connection) -> Result<()>
+    where
+        Self: Sized,
+    {
+        conn.execute(create_table_sql::<Self>(&[]).as_str(), NO_PARAMS)?;
+        debug!("Create table `{}`", Self::table_name());
+        Ok(())
+    }
+}
+
+/// describeで出力するカラムの定義
+#[derive(Debug, Clone, Serialize)]
+pub struct ColumnDescription {
+    pub name: &'static str,
+    /// SQLの型 (ex: text, int)
+    pub sql_type: String,
+    pub nullable: bool,
+    /// 区分値のカラムなら取りうる値 (区分値でなければNone)
+    pub values: Option<Vec<EnumValue>>,
+}
+
+/// 区分値の1つ
+#[derive(Debug, Clone, Serialize)]
+pub struct EnumValue {
+    /// ファイルに書かれる値 (ex: 0, Asia/Tokyo)
+    pub value: serde_json::Value,
+    /// Rustでのバリアント名
+    pub name: &'static str,
+    pub description: &'static str,
 }
 
-pub fn init(path: &PathBuf) -> Result<Box<dyn Gtfs>> {
-    let ins = GtfsDb::new(path)?;
-    Ok(Box::new(ins))
+impl EnumValue {
+    pub fn new<T: Serialize>(variant: &T, name: &'static str, description: &'static str) -> Self {
+        Self {
+            value: serde_json::to_value(variant).unwrap_or(serde_json::Value::Null),
+            name,
+            description,
+        }
+    }
+}
+
+/// 区分値の列挙型. derive(EnumValues)で実装する
+pub trait EnumValues {
+    fn values() -> Vec<EnumValue>;
 }
 
-pub fn create<T>(conn: &Connection) -> Result<()>
+/// 全テーブルの (テーブル名, カラムの定義). 作成する順に並ぶ
+pub fn descriptions() -> Vec<(&'static str, Vec<ColumnDescription>)> {
+    vec![
+        description::<Agency>(),
+        description::<OfficeJp>(),
+        description::<Route>(),
+        description::<Stop>(),
+        description::<Trip>(),
+        description::<StopTime>(),
+        description::<Calendar>(),
+        description::<CalendarDate>(),
+        description::<FareAttribute>(),
+        description::<FareRule>(),
+        description::<Shape>(),
+        description::<Frequency>(),
+        description::<Transfer>(),
+        description::<Level>(),
+        description::<Pathway>(),
+        description::<Attribution>(),
+        description::<FeedInfo>(),
+        description::<Translation>(),
+    ]
+}
+
+fn description<T>() -> (&'static str, Vec<ColumnDescription>)
 where
     T: Table,
 {
-    conn.execute(
-        format!(
-            "CREATE TABLE IF NOT EXISTS {} ({})",
-            T::table_name(),
-            T::create_sql()
-        )
-        .as_str(),
-        NO_PARAMS,
-    )?;
-    debug!("Create table `{}`", T::table_name());
+    (T::table_name(), T::columns())
+}
+
+/// ファイルの代わりにメモリ上へデータベースを作る場合のパス
+const IN_MEMORY: &str = ":memory:";
+
+/// 他のプロセスがロックしている間, 失敗せずに待つ時間 (ミリ秒)
+static BUSY_TIMEOUT_MS: AtomicU64 = AtomicU64::new(5000);
+
+/// 以降に開くデータベースのビジータイムアウトを設定する
+pub fn set_busy_timeout(timeout: Duration) {
+    BUSY_TIMEOUT_MS.store(timeout.as_millis() as u64, Ordering::Relaxed);
+}
+
+pub fn init(path: &Path) -> Result<Box<dyn Gtfs>> {
+    Ok(Box::new(open(path)?))
+}
+
+/// 既存のデータベースを開く. ファイルがなければ作成せず, make-dbを促す
+/// データベースでしか行えない操作 (件数の集計や取り込みの設定など) にも使う
+pub fn open(path: &Path) -> Result<GtfsDb> {
+    if path != Path::new(IN_MEMORY) && !path.exists() {
+        bail!(
+            "{:?} がありません. 先に make-db でデータベースを作成してください",
+            path
+        );
+    }
+    create(path)
+}
+
+/// データベースを開く. ファイルがなければ作成する. make-dbなど書き込み先のデータベースに使う
+pub fn create(path: &Path) -> Result<GtfsDb> {
+    if path == Path::new(IN_MEMORY) {
+        GtfsDb::new_in_memory()
+    } else {
+        GtfsDb::new(path)
+    }
+}
+
+/// SpatiaLite拡張のライブラリ名. 拡張子はSQLiteがOSに合わせて補う
+const SPATIALITE: &str = "mod_spatialite";
+
+/// SpatiaLiteの関数を呼び出し, 成功 (1) したか
+fn call_spatialite(conn: &Connection, sql: &str) -> Result<bool> {
+    let result: i32 = conn
+        .query_row(sql, NO_PARAMS, |row| row.get(0))
+        .with_context(|| format!("Fail to call spatialite: {}", sql))?;
+    Ok(result == 1)
+}
+
+/// constraintsはcreate_sqlのカラム定義の後に追加する制約
+fn create_table_sql<T>(constraints: &[&str]) -> String
+where
+    T: Table,
+{
+    let mut definitions = vec![T::create_sql().trim_end()];
+    definitions.extend(constraints);
+    format!(
+        "CREATE TABLE IF NOT EXISTS {} ({})",
+        T::table_name(),
+        definitions.join(", ")
+    )
+}
+
+/// --enforce-fkで追加する外部キー (テーブル名, 制約)
+/// trips.service_idはcalendarとcalendar_datesのどちらにあってもよいため外部キーにできない
+/// upsertで参照先を置き換えても失敗しないよう, コミット時に検査する
+const ENFORCED_FOREIGN_KEYS: &[(&str, &str)] = &[
+    (
+        "routes",
+        "FOREIGN KEY(agency_id) REFERENCES agency(agency_id) DEFERRABLE INITIALLY DEFERRED",
+    ),
+    (
+        "trips",
+        "FOREIGN KEY(route_id) REFERENCES routes(route_id) DEFERRABLE INITIALLY DEFERRED",
+    ),
+    (
+        "stop_times",
+        "FOREIGN KEY(trip_id) REFERENCES trips(trip_id) DEFERRABLE INITIALLY DEFERRED",
+    ),
+    (
+        "stop_times",
+        "FOREIGN KEY(stop_id) REFERENCES stops(stop_id) DEFERRABLE INITIALLY DEFERRED",
+    ),
+    (
+        "fare_rules",
+        "FOREIGN KEY(fare_id) REFERENCES fare_attributes(fare_id) DEFERRABLE INITIALLY DEFERRED",
+    ),
+    (
+        "fare_rules",
+        "FOREIGN KEY(route_id) REFERENCES routes(route_id) DEFERRABLE INITIALLY DEFERRED",
+    ),
+];
+
+fn create_index_sql<T>(column: &str) -> String
+where
+    T: Table,
+{
+    format!(
+        "CREATE INDEX IF NOT EXISTS {table}_{column}_index ON {table} ({column})",
+        table = T::table_name(),
+        column = column,
+    )
+}
+
+pub fn index<T>(conn: &Connection) -> Result<()>
+where
+    T: Table,
+{
+    for column in T::index_columns() {
+        conn.execute(create_index_sql::<T>(column).as_str(), NO_PARAMS)?;
+        debug!("Create index `{}.{}`", T::table_name(), column);
+    }
     Ok(())
 }
 
+/// テーブルのCREATE文とインデックスのCREATE文を読みやすく整形する
+fn ddl<T>() -> (&'static str, String)
+where
+    T: Table,
+{
+    let columns: Vec<String> = T::create_sql()
+        .lines()
+        .map(str::trim)
+        .filter(|x| !x.is_empty())
+        .map(|x| format!("    {}", x))
+        .collect();
+    let mut statements = vec![format!(
+        "CREATE TABLE IF NOT EXISTS {} (\n{}\n);",
+        T::table_name(),
+        columns.join("\n")
+    )];
+    for column in T::index_columns() {
+        statements.push(format!("{};", create_index_sql::<T>(column)));
+    }
+    (T::table_name(), statements.join("\n"))
+}
+
+/// 全テーブルの (テーブル名, DDL). 作成する順に並ぶ
+/// --enforce-fkで作成した場合に外部キーで参照するテーブル
+pub fn foreign_key_tables(table_name: &str) -> Vec<&'static str> {
+    ENFORCED_FOREIGN_KEYS
+        .iter()
+        .filter(|(x, _)| *x == table_name)
+        .filter_map(|(_, constraint)| constraint.split("REFERENCES ").nth(1))
+        .filter_map(|x| x.split('(').next())
+        .map(str::trim)
+        .collect()
+}
+
+pub fn schema() -> Vec<(&'static str, String)> {
+    vec![
+        ddl::<Agency>(),
+        ddl::<OfficeJp>(),
+        ddl::<Route>(),
+        ddl::<Stop>(),
+        ddl::<Trip>(),
+        ddl::<StopTime>(),
+        ddl::<Calendar>(),
+        ddl::<CalendarDate>(),
+        ddl::<FareAttribute>(),
+        ddl::<FareRule>(),
+        ddl::<Shape>(),
+        ddl::<Frequency>(),
+        ddl::<Transfer>(),
+        ddl::<Level>(),
+        ddl::<Pathway>(),
+        ddl::<Attribution>(),
+        ddl::<FeedInfo>(),
+        ddl::<Translation>(),
+    ]
+}
+
 pub fn drop<T>(conn: &Connection) -> Result<()>
 where
     T: Table,
@@ -66,212 +388,1779 @@ where
     Ok(())
 }
 
-pub fn insert<T>(conn: &mut Connection, records: &[T]) -> rusqlite::Result<()>
+/// SQLiteのバインド変数の上限 (SQLITE_MAX_VARIABLE_NUMBER の古いバージョンでの既定値)
+const MAX_VARIABLES: usize = 999;
+
+/// 全テーブルでバインド変数の上限を超えない, 1つのINSERT文にまとめる行数の上限
+pub fn max_batch_size() -> usize {
+    descriptions()
+        .iter()
+        .map(|(_, columns)| MAX_VARIABLES / columns.len().max(1))
+        .min()
+        .unwrap_or(1)
+}
+
+/// 複数行をまとめたINSERT文を作成する
+fn insert_sql<T>(rows: usize, on_conflict: OnConflict) -> String
+where
+    T: Table,
+{
+    let placeholders = format!("({})", vec!["?"; T::column_names().len()].join(","));
+    let verb = match on_conflict {
+        OnConflict::Abort => "INSERT",
+        OnConflict::Replace => "INSERT OR REPLACE",
+    };
+    format!(
+        "{} INTO {} ({}) VALUES {}",
+        verb,
+        T::table_name(),
+        T::column_names().join(","),
+        vec![placeholders; rows].join(","),
+    )
+}
+
+/// serializeしたフィールドの順序ではなくnamesの順序で値を並べる
+fn ordered_values<'a>(
+    params: &'a [NamedParamSlice],
+    names: &[String],
+) -> rusqlite::Result<Vec<&'a dyn ToSql>> {
+    let mut values: Vec<&dyn ToSql> = Vec::with_capacity(params.len() * names.len());
+    for p in params {
+        for name in names {
+            let (_, value) = p
+                .iter()
+                .find(|(n, _)| n == name)
+                .ok_or_else(|| rusqlite::Error::InvalidParameterName(name.clone()))?;
+            values.push(value.as_ref());
+        }
+    }
+    Ok(values)
+}
+
+/// 1行ずつINSERTする. 失敗したらレコードの位置と内容をエラーに含める
+fn insert_each<T>(
+    tx: &Transaction,
+    records: &[T],
+    offset: usize,
+    names: &[String],
+    on_conflict: OnConflict,
+) -> Result<()>
+where
+    T: serde::ser::Serialize + Debug + Table,
+{
+    let mut stmt = tx.prepare(insert_sql::<T>(1, on_conflict).as_str())?;
+    for (i, record) in records.iter().enumerate() {
+        let params = [to_params_named(record)?];
+        stmt.execute(ordered_values(&params, names)?)
+            .with_context(|| {
+                format!(
+                    "failed inserting record {} into {}: {:?}",
+                    offset + i,
+                    T::table_name(),
+                    record
+                )
+            })?;
+    }
+    Ok(())
+}
+
+/// 複数行のINSERTが失敗したとき, 1行ずつ登録し直して原因のレコードを特定する
+/// 失敗した文の変更だけが取り消されるため, 同じトランザクション内で再試行できる
+fn find_failed_record<T>(
+    tx: &Transaction,
+    chunk: &[T],
+    offset: usize,
+    names: &[String],
+    on_conflict: OnConflict,
+    err: rusqlite::Error,
+) -> anyhow::Error
+where
+    T: serde::ser::Serialize + Debug + Table,
+{
+    match insert_each(tx, chunk, offset, names, on_conflict) {
+        Err(e) => e,
+        Ok(_) => anyhow::Error::new(err).context(format!(
+            "failed inserting records {}..{} into {}",
+            offset,
+            offset + chunk.len(),
+            T::table_name()
+        )),
+    }
+}
+
+/// ATTACHしたmerge_sourceのtableを追加する. 主キーが重複するレコードは追加しない
+fn merge_attached(
+    conn: &Connection,
+    table: &str,
+    prefixed: &[&str],
+    prefix: &str,
+) -> Result<(u32, u32)> {
+    let exists: bool = conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM merge_source.sqlite_master WHERE type = 'table' AND name = ?)",
+        &[table],
+        |row| row.get(0),
+    )?;
+    if !exists {
+        return Ok((0, 0));
+    }
+    let mut stmt = conn.prepare("SELECT name FROM pragma_table_info(?)")?;
+    let columns = stmt
+        .query_map(&[table], |row| row.get(0))?
+        .collect::<rusqlite::Result<Vec<String>>>()?;
+    // NULLはNULLのまま (NULLとの連結はNULL) になり, 参照先のないことが保たれる
+    let values: Vec<String> = columns
+        .iter()
+        .map(|c| {
+            if prefixed.contains(&c.as_str()) {
+                format!("?1 || {}", c)
+            } else {
+                c.clone()
+            }
+        })
+        .collect();
+    let total: u32 = conn.query_row(
+        format!("SELECT COUNT(*) FROM merge_source.{}", table).as_str(),
+        NO_PARAMS,
+        |row| row.get(0),
+    )?;
+    let sql = format!(
+        "INSERT OR IGNORE INTO main.{table} ({columns}) SELECT {values} FROM merge_source.{table}",
+        table = table,
+        columns = columns.join(", "),
+        values = values.join(", "),
+    );
+    // 名前空間を付けるカラムがなければプレースホルダもない
+    let inserted = if values.iter().any(|x| x.starts_with("?1")) {
+        conn.execute(sql.as_str(), &[prefix])?
+    } else {
+        conn.execute(sql.as_str(), NO_PARAMS)?
+    } as u32;
+    Ok((inserted, total - inserted))
+}
+
+/// 主キーのカラム名 (テーブル定義から取得する)
+fn primary_key_columns(conn: &Connection, table_name: &str) -> Result<Vec<String>> {
+    let mut stmt =
+        conn.prepare("SELECT name FROM pragma_table_info(?) WHERE pk > 0 ORDER BY pk")?;
+    let columns = stmt
+        .query_map(&[table_name], |row| row.get(0))?
+        .collect::<rusqlite::Result<Vec<String>>>()?;
+    Ok(columns)
+}
+
+fn display_value(value: &Value) -> String {
+    match value {
+        Value::Null => "NULL".to_string(),
+        Value::Integer(x) => x.to_string(),
+        Value::Real(x) => x.to_string(),
+        Value::Text(x) => x.clone(),
+        Value::Blob(x) => format!("{:?}", x),
+    }
+}
+
+/// 主キーが重複するレコードを探し, 全ての重複をまとめてエラーにする
+/// SQLiteと同様にNULLを含むキーは重複とみなさない
+fn check_duplicate_keys<T>(conn: &Connection, records: &[T]) -> Result<()>
+where
+    T: serde::ser::Serialize + Table,
+{
+    let keys = primary_key_columns(conn, T::table_name())?;
+    if keys.is_empty() {
+        return Ok(());
+    }
+    let names = keys.iter().map(|x| format!(":{}", x)).collect::<Vec<_>>();
+
+    let mut positions: HashMap<Vec<String>, Vec<usize>> = HashMap::new();
+    let mut order = Vec::new();
+    for (i, record) in records.iter().enumerate() {
+        let params = [to_params_named(record)?];
+        let values = ordered_values(&params, &names)?
+            .into_iter()
+            .map(|x| {
+                Ok(match x.to_sql()? {
+                    ToSqlOutput::Borrowed(v) => Value::from(v),
+                    ToSqlOutput::Owned(v) => v,
+                    other => Value::Text(format!("{:?}", other)),
+                })
+            })
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        if values.contains(&Value::Null) {
+            continue;
+        }
+        let key = values.iter().map(display_value).collect::<Vec<_>>();
+        let entry = positions.entry(key.clone()).or_default();
+        if entry.len() == 1 {
+            order.push(key);
+        }
+        entry.push(i);
+    }
+    if order.is_empty() {
+        return Ok(());
+    }
+
+    const MAX_REPORTS: usize = 10;
+    let mut lines: Vec<String> = order
+        .iter()
+        .take(MAX_REPORTS)
+        .map(|key| {
+            let records = positions[key]
+                .iter()
+                .map(|x| x.to_string())
+                .collect::<Vec<_>>();
+            format!("  ({}) at records {}", key.join(", "), records.join(", "))
+        })
+        .collect();
+    if order.len() > MAX_REPORTS {
+        lines.push(format!("  ... and {} more", order.len() - MAX_REPORTS));
+    }
+    bail!(
+        "duplicate primary keys ({}) in {}: {} keys\n{}",
+        keys.join(", "),
+        T::table_name(),
+        order.len(),
+        lines.join("\n")
+    )
+}
+
+/// batch_sizeは1つのINSERT文にまとめる行数. Noneならバインド変数の上限に収まる最大の行数にする
+pub fn insert<T>(
+    conn: &mut Connection,
+    records: &[T],
+    on_conflict: OnConflict,
+    batch_size: Option<usize>,
+) -> Result<()>
 where
     T: serde::ser::Serialize + Debug + Table,
 {
     let tx = conn.transaction()?;
 
+    // バインド変数の上限を超えないように分割してINSERTする
+    let max_rows = (MAX_VARIABLES / T::column_names().len()).max(1);
+    let rows_per_statement = batch_size.map_or(max_rows, |x| x.clamp(1, max_rows));
+    let names = T::column_names()
+        .iter()
+        .map(|x| format!(":{}", x))
+        .collect::<Vec<_>>();
+
+    debug!("Insert {} records to {}", records.len(), T::table_name());
+    {
+        // 同じ行数のINSERT文は一度だけ準備して使い回す (最後の端数だけ別の文になる)
+        let mut stmt = tx.prepare(insert_sql::<T>(rows_per_statement, on_conflict).as_str())?;
+        for (i, chunk) in records.chunks(rows_per_statement).enumerate() {
+            let params = chunk
+                .iter()
+                .map(to_params_named)
+                .collect::<serde_rusqlite::Result<Vec<_>>>()?;
+            let values = ordered_values(&params, &names)?;
+
+            let result = if chunk.len() == rows_per_statement {
+                stmt.execute(values)
+            } else {
+                tx.prepare(insert_sql::<T>(chunk.len(), on_conflict).as_str())?
+                    .execute(values)
+            };
+            if let Err(err) = result {
+                // 重複は途中の1件だけでなく全件をまとめて報告する
+                check_duplicate_keys(&tx, records)?;
+                return Err(find_failed_record(
+                    &tx,
+                    chunk,
+                    i * rows_per_statement,
+                    &names,
+                    on_conflict,
+                    err,
+                ));
+            }
+        }
+    }
+
+    // DEFERRABLEな外部キーはコミット時に検査される
+    tx.commit()
+        .with_context(|| format!("failed committing {}", T::table_name()))?;
+
+    trace!("Insert {:?}", records);
+    Ok(())
+}
+
+/// keysが一致するレコードのcolumnsだけを更新する
+pub fn update<T>(
+    conn: &mut Connection,
+    records: &[T],
+    columns: &[&str],
+    keys: &[&str],
+) -> Result<()>
+where
+    T: serde::ser::Serialize + Debug + Table,
+{
     let sql = format!(
-        "INSERT INTO {} ({}) VALUES ({})",
+        "UPDATE {} SET {} WHERE {}",
         T::table_name(),
-        T::column_names().join(","),
-        T::column_names()
+        columns
             .iter()
-            .map(|x| format!(":{}", x))
+            .map(|x| format!("{} = ?", x))
+            .collect::<Vec<_>>()
+            .join(", "),
+        keys.iter()
+            .map(|x| format!("{} = ?", x))
             .collect::<Vec<_>>()
-            .join(","),
+            .join(" AND ")
     );
+    let names = columns
+        .iter()
+        .chain(keys)
+        .map(|x| format!(":{}", x))
+        .collect::<Vec<_>>();
 
-    debug!("Insert {} records to {}", records.len(), T::table_name());
-    for record in records {
-        tx.execute_named(sql.as_str(), &to_params_named(&record).unwrap().to_slice())?;
+    let tx = conn.transaction()?;
+    debug!("Update {} records of {}", records.len(), T::table_name());
+    {
+        let mut stmt = tx.prepare(sql.as_str())?;
+        for record in records {
+            let params = [to_params_named(record)?];
+            stmt.execute(ordered_values(&params, &names)?)
+                .with_context(|| format!("failed updating {}: {:?}", T::table_name(), record))?;
+        }
     }
-
     tx.commit()?;
+    Ok(())
+}
 
-    trace!("Insert {:?}", records);
+/// 全件を取得する際のORDER BY句. 主キーがなければ登録順 (rowid) に並べる
+fn order_by_sql<T>() -> String
+where
+    T: Table,
+{
+    let columns = T::order_by();
+    if columns.is_empty() {
+        " ORDER BY rowid".to_string()
+    } else {
+        format!(" ORDER BY {}", columns.join(", "))
+    }
+}
+
+/// SELECT文を組み立てる
+/// 出力が実行ごとに変わらないよう, order_byの指定がなければ条件の有無によらず主キー順に並べる
+fn select_sql<T>(condition: Option<&str>, order_by: Option<&str>, query: &Query) -> String
+where
+    T: Table,
+{
+    let conditions: Vec<String> = query
+        .condition
+        .as_deref()
+        .into_iter()
+        .chain(condition)
+        .map(|c| format!("({})", c))
+        .collect();
+    let where_sql = if conditions.is_empty() {
+        String::new()
+    } else {
+        format!(" WHERE {}", conditions.join(" AND "))
+    };
+    let order_by = match order_by {
+        Some(x) => format!(" ORDER BY {}", x),
+        None => order_by_sql::<T>(),
+    };
+    format!(
+        "SELECT * FROM {}{}{}{}",
+        T::table_name(),
+        where_sql,
+        order_by,
+        query.paging_sql()
+    )
+}
+
+/// テーブルがなければ, データベースを作り忘れている可能性が高いためmake-dbを促す
+fn ensure_table<T>(conn: &Connection) -> Result<()>
+where
+    T: Table,
+{
+    ensure_table_named(conn, T::table_name())
+}
+
+fn ensure_table_named(conn: &Connection, table: &str) -> Result<()> {
+    let exists: bool = conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?)",
+        &[table],
+        |row| row.get(0),
+    )?;
+    if !exists {
+        bail!(
+            "テーブル {} がありません. 先に make-db でデータベースを作成してください",
+            table
+        );
+    }
     Ok(())
 }
 
-fn select_all<T>(conn: &mut Connection) -> serde_rusqlite::Result<Vec<T>>
+/// stopsにSpatiaLiteのgeometryカラムがあるか
+fn has_stop_geometries(conn: &Connection) -> Result<bool> {
+    let exists: bool = conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM pragma_table_info('stops') WHERE name = 'geom')",
+        NO_PARAMS,
+        |row| row.get(0),
+    )?;
+    Ok(exists)
+}
+
+fn select_all<T>(conn: &mut Connection, query: &Query) -> Result<Vec<T>>
 where
     T: serde::de::DeserializeOwned + Table,
 {
-    let mut stmt = conn.prepare(format!("SELECT * FROM {}", T::table_name()).as_str())?;
-    let result = from_rows::<T>(stmt.query(NO_PARAMS)?).collect();
-    result
+    ensure_table::<T>(conn)?;
+    let mut stmt = conn.prepare(select_sql::<T>(None, None, query).as_str())?;
+    let result = from_rows::<T>(stmt.query(NO_PARAMS)?).collect::<serde_rusqlite::Result<_>>()?;
+    Ok(result)
+}
+
+/// 全件をメモリに載せず, batch_size件ずつeachに渡す. 戻り値は件数
+fn select_batches<T>(
+    conn: &mut Connection,
+    order_by: Option<&str>,
+    batch_size: usize,
+    each: &mut dyn FnMut(&[T]) -> Result<()>,
+) -> Result<usize>
+where
+    T: serde::de::DeserializeOwned + Table,
+{
+    ensure_table::<T>(conn)?;
+    let batch_size = batch_size.max(1);
+    let order_by = match order_by {
+        Some(x) => format!(" ORDER BY {}", x),
+        None => order_by_sql::<T>(),
+    };
+    let sql = format!("SELECT * FROM {}{}", T::table_name(), order_by);
+    let mut stmt = conn.prepare(&sql)?;
+    let mut count = 0;
+    let mut batch = Vec::with_capacity(batch_size);
+    for record in from_rows::<T>(stmt.query(NO_PARAMS)?) {
+        batch.push(record?);
+        if batch.len() == batch_size {
+            each(&batch)?;
+            count += batch.len();
+            batch.clear();
+        }
+    }
+    if !batch.is_empty() {
+        each(&batch)?;
+        count += batch.len();
+    }
+    Ok(count)
+}
+
+/// conditionに一致する便を始発の出発時刻順に取得する (ex: `t.block_id = ?`)
+fn select_block_trips(
+    conn: &mut Connection,
+    condition: &str,
+    param: &str,
+) -> Result<Vec<BlockTrip>> {
+    ensure_table::<Trip>(conn)?;
+    ensure_table::<StopTime>(conn)?;
+    // stop_timesのない便も落とさないように外部結合する
+    let sql = format!(
+        "
+        SELECT
+            t.block_id,
+            t.trip_id,
+            t.route_id,
+            t.service_id,
+            t.trip_headsign,
+            t.direction_id,
+            MIN(st.departure_time) AS first_departure_time,
+            MAX(st.arrival_time) AS last_arrival_time
+        FROM trips t
+        LEFT JOIN stop_times st ON st.trip_id = t.trip_id
+        WHERE {}
+        GROUP BY t.trip_id
+        ORDER BY first_departure_time, t.trip_id
+        ",
+        condition
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let result =
+        from_rows::<BlockTrip>(stmt.query(&[param])?).collect::<serde_rusqlite::Result<_>>()?;
+    Ok(result)
+}
+
+/// 条件に一致するレコードを主キー順に取得する
+/// conditionはWHERE句の条件 (ex: `shape_id = ?`)
+fn select_where<T>(
+    conn: &mut Connection,
+    condition: &str,
+    params: &[&dyn ToSql],
+    query: &Query,
+) -> Result<Vec<T>>
+where
+    T: serde::de::DeserializeOwned + Table,
+{
+    select_where_ordered(conn, condition, None, params, query)
+}
+
+/// 条件に一致するレコードをorder_by (ex: `stop_name, stop_id`) の順に取得する
+fn select_where_ordered<T>(
+    conn: &mut Connection,
+    condition: &str,
+    order_by: Option<&str>,
+    params: &[&dyn ToSql],
+    query: &Query,
+) -> Result<Vec<T>>
+where
+    T: serde::de::DeserializeOwned + Table,
+{
+    ensure_table::<T>(conn)?;
+    let mut stmt = conn.prepare(select_sql::<T>(Some(condition), order_by, query).as_str())?;
+    let result = from_rows::<T>(stmt.query(params)?).collect::<serde_rusqlite::Result<_>>()?;
+    Ok(result)
+}
+
+/// 経路に停車するstopの条件
+const STOPS_BY_ROUTE_ID: &str = "
+    stop_id IN (
+        SELECT st.stop_id
+        FROM stop_times st
+        JOIN trips t ON st.trip_id = t.trip_id
+        WHERE t.route_id = ?
+    )
+";
+
+/// 条件に一致するレコード数. limitとoffsetを件数に反映するため, 絞り込んだ結果を数える
+fn count_where(
+    conn: &Connection,
+    table: &str,
+    condition: Option<&str>,
+    params: &[&dyn ToSql],
+    query: &Query,
+) -> Result<u32> {
+    ensure_table_named(conn, table)?;
+    let conditions: Vec<String> = query
+        .condition
+        .as_deref()
+        .into_iter()
+        .chain(condition)
+        .map(|c| format!("({})", c))
+        .collect();
+    let where_sql = if conditions.is_empty() {
+        String::new()
+    } else {
+        format!(" WHERE {}", conditions.join(" AND "))
+    };
+    let sql = format!(
+        "SELECT COUNT(*) FROM (SELECT 1 FROM {}{}{})",
+        table,
+        where_sql,
+        query.paging_sql()
+    );
+    conn.query_row(sql.as_str(), params, |row| row.get(0))
+        .with_context(|| format!("Fail to count records: {}", table))
+}
+
+/// 同梱のSQLiteは外部キーの検査が既定で有効なため, --enforce-fkの指定がなければ明示的に無効にする
+fn disable_foreign_keys(conn: &Connection) -> Result<()> {
+    conn.execute_batch("PRAGMA foreign_keys = OFF")?;
+    Ok(())
 }
 
 impl GtfsDb {
-    pub fn new(db: &PathBuf) -> Result<Self> {
-        let db_file = db.to_str().unwrap();
-        let conn = Connection::open(db_file)?;
+    pub fn new(db: &Path) -> Result<Self> {
+        let conn = Connection::open(db).with_context(|| format!("{:?} が開けませんでした", db))?;
+        // ロック中でもすぐにSQLITE_BUSYで失敗せず, 解放されるまで待つ
+        conn.busy_timeout(Duration::from_millis(
+            BUSY_TIMEOUT_MS.load(Ordering::Relaxed),
+        ))?;
+        disable_foreign_keys(&conn)?;
+
+        Ok(GtfsDb {
+            connection: conn,
+            on_conflict: OnConflict::default(),
+            enforce_fk: false,
+            batch_size: None,
+            saved_pragmas: None,
+        })
+    }
+
+    /// プロセス終了時に破棄される一時的なデータベース
+    pub fn new_in_memory() -> Result<Self> {
+        let conn =
+            Connection::open_in_memory().context("メモリ上にデータベースを作成できませんでした")?;
+        disable_foreign_keys(&conn)?;
+
+        Ok(GtfsDb {
+            connection: conn,
+            on_conflict: OnConflict::default(),
+            enforce_fk: false,
+            batch_size: None,
+            saved_pragmas: None,
+        })
+    }
+
+    /// --enforce-fkが有効なら外部キーを追加してテーブルを作成する
+    fn create<T>(&self) -> Result<()>
+    where
+        T: Table,
+    {
+        if !self.enforce_fk {
+            return T::create(&self.connection);
+        }
+        let constraints: Vec<&str> = ENFORCED_FOREIGN_KEYS
+            .iter()
+            .filter(|(table_name, _)| *table_name == T::table_name())
+            .map(|(_, constraint)| *constraint)
+            .collect();
+        self.connection
+            .execute(create_table_sql::<T>(&constraints).as_str(), NO_PARAMS)?;
+        debug!("Create table `{}` with foreign keys", T::table_name());
+        Ok(())
+    }
+
+    /// テーブルを削除する
+    pub fn drop_table(&self, table_name: &str) -> Result<()> {
+        if !schema().iter().any(|(name, _)| *name == table_name) {
+            bail!("テーブルが見つかりません: {}", table_name);
+        }
+        self.connection.execute(
+            format!("DROP TABLE IF EXISTS {}", table_name).as_str(),
+            NO_PARAMS,
+        )?;
+        debug!("Drop table `{}`", table_name);
+        Ok(())
+    }
+
+    /// 前回の取り込み内容 (記録がなければ空)
+    pub fn select_meta(&mut self) -> Result<Vec<Meta>> {
+        if !self.exists(Meta::table_name())? {
+            return Ok(vec![]);
+        }
+        select_all::<Meta>(&mut self.connection, &Query::default()).context("Fail to select meta")
+    }
+
+    /// 取り込み内容の記録を置き換える
+    pub fn replace_meta(&mut self, meta: &[Meta]) -> Result<()> {
+        Meta::create(&self.connection)?;
+        self.connection.execute(
+            format!("DELETE FROM {}", Meta::table_name()).as_str(),
+            NO_PARAMS,
+        )?;
+        insert(&mut self.connection, meta, OnConflict::default(), None)?;
+        Ok(())
+    }
+
+    /// 取り込み後に書き換えたテーブルの記録を消す. 次回のmake-dbで変更なしと判定されないようにする
+    pub fn invalidate_meta(&mut self, table_names: &[&str]) -> Result<()> {
+        let meta: Vec<Meta> = self
+            .select_meta()?
+            .into_iter()
+            .filter(|x| !table_names.contains(&x.table_name.as_str()))
+            .collect();
+        self.replace_meta(&meta)
+    }
+
+    /// 別のデータベースsourceのtableを追加する. prefixedのカラムは値の先頭にprefixを付ける
+    /// 戻り値は (追加した件数, 主キーが重複して追加できなかった件数)
+    pub fn merge_table(
+        &mut self,
+        source: &Path,
+        table: &str,
+        prefixed: &[&str],
+        prefix: &str,
+    ) -> Result<(u32, u32)> {
+        if !schema().iter().any(|(name, _)| *name == table) {
+            bail!("テーブルが見つかりません: {}", table);
+        }
+        let path = source
+            .to_str()
+            .with_context(|| format!("{:?} はUTF-8のパスではありません", source))?;
+        self.connection
+            .execute("ATTACH DATABASE ? AS merge_source", &[path])
+            .with_context(|| format!("{:?} が開けませんでした", source))?;
+        let result = merge_attached(&self.connection, table, prefixed, prefix);
+        self.connection
+            .execute("DETACH DATABASE merge_source", NO_PARAMS)?;
+        result.with_context(|| format!("Fail to merge {} from {:?}", table, source))
+    }
+
+    /// table.columnの値がparentsのいずれにも存在しないレコード数
+    pub fn count_dangling_references(
+        &self,
+        table: &str,
+        column: &str,
+        parents: &[(&str, &str)],
+    ) -> Result<u32> {
+        let not_exists = parents
+            .iter()
+            .map(|(t, c)| {
+                format!(
+                    "NOT EXISTS (SELECT 1 FROM {t} p WHERE p.{c} = x.{column})",
+                    t = t,
+                    c = c,
+                    column = column
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(" AND ");
+        let sql = format!(
+            "SELECT COUNT(*) FROM {table} x WHERE x.{column} IS NOT NULL AND {not_exists}",
+            table = table,
+            column = column,
+            not_exists = not_exists,
+        );
+        self.connection
+            .query_row(sql.as_str(), NO_PARAMS, |row| row.get(0))
+            .with_context(|| format!("Fail to count dangling references: {}.{}", table, column))
+    }
+
+    /// テーブルごとのレコード数 (テーブルを作成する順)
+    pub fn count_records(&self) -> Result<Vec<(&'static str, u32)>> {
+        schema()
+            .into_iter()
+            .map(|(table, _)| {
+                let count = self
+                    .connection
+                    .query_row(
+                        format!("SELECT COUNT(*) FROM {}", table).as_str(),
+                        NO_PARAMS,
+                        |row| row.get(0),
+                    )
+                    .with_context(|| format!("Fail to count records: {}", table))?;
+                Ok((table, count))
+            })
+            .collect()
+    }
+
+    /// table.columnのNULLを除いた値の種類数
+    pub fn count_distinct(&self, table: &str, column: &str) -> Result<u32> {
+        let sql = format!("SELECT COUNT(DISTINCT {}) FROM {}", column, table);
+        self.connection
+            .query_row(sql.as_str(), NO_PARAMS, |row| row.get(0))
+            .with_context(|| format!("Fail to count distinct values: {}.{}", table, column))
+    }
+
+    /// queryの条件に当てはまるレコード数. limitとoffsetも適用する
+    pub fn count_matches(&self, table: &str, query: &Query) -> Result<u32> {
+        count_where(&self.connection, table, None, NO_PARAMS, query)
+    }
+
+    /// 一括登録を高速化する設定にする. 無効にすると有効にする前の設定に戻す
+    pub fn set_fast_import(&mut self, enabled: bool) -> Result<()> {
+        // synchronous = OFF はクラッシュ時にデータベースが壊れる可能性がある
+        if enabled {
+            if self.saved_pragmas.is_none() {
+                self.saved_pragmas = Some(Pragmas::read(&self.connection)?);
+            }
+            self.connection.execute_batch(
+                "
+                PRAGMA journal_mode = WAL;
+                PRAGMA synchronous = OFF;
+                PRAGMA temp_store = MEMORY;
+                ",
+            )?;
+        } else if let Some(pragmas) = self.saved_pragmas.take() {
+            pragmas.apply(&self.connection)?;
+        }
+        debug!("Set fast import: {}", enabled);
+        Ok(())
+    }
+
+    /// 外部キーを検査し, 参照先のないレコードの登録を失敗させる
+    /// 有効にした後に作成するテーブルにはroutes, trips, stop_times, fare_rulesの外部キーも付ける
+    pub fn set_enforce_fk(&mut self, enabled: bool) -> Result<()> {
+        let sql = if enabled {
+            "PRAGMA foreign_keys = ON"
+        } else {
+            "PRAGMA foreign_keys = OFF"
+        };
+        self.connection.execute_batch(sql)?;
+        self.enforce_fk = enabled;
+        debug!("Set enforce foreign keys: {}", enabled);
+        Ok(())
+    }
+
+    /// SpatiaLite拡張 (mod_spatialite) を読み込む. 見つからなければfalseを返す
+    pub fn load_spatialite(&mut self) -> Result<bool> {
+        let _guard = LoadExtensionGuard::new(&self.connection)?;
+        match self.connection.load_extension(SPATIALITE, None) {
+            Ok(()) => {
+                debug!("Loaded {}", SPATIALITE);
+                Ok(true)
+            }
+            Err(err) => {
+                debug!("Fail to load {}: {}", SPATIALITE, err);
+                Ok(false)
+            }
+        }
+    }
+
+    /// stopsに位置のgeometryカラムと空間インデックスを作る. load_spatialiteで読み込んでから呼ぶ
+    pub fn create_stop_geometries(&mut self) -> Result<()> {
+        ensure_table::<Stop>(&self.connection)?;
+        if !call_spatialite(&self.connection, "SELECT CheckSpatialMetadata() > 0")? {
+            call_spatialite(&self.connection, "SELECT InitSpatialMetadata(1)")?;
+        }
+        let tx = self.connection.transaction()?;
+        if !has_stop_geometries(&tx)? {
+            // stopsを作り直した場合は前回のgeometryカラムの登録と空間インデックスが残っている
+            call_spatialite(&tx, "SELECT DisableSpatialIndex('stops', 'geom')")?;
+            tx.execute_batch("DROP TABLE IF EXISTS idx_stops_geom")?;
+            call_spatialite(&tx, "SELECT DiscardGeometryColumn('stops', 'geom')")?;
+            if !call_spatialite(
+                &tx,
+                "SELECT AddGeometryColumn('stops', 'geom', 4326, 'POINT', 'XY')",
+            )? {
+                bail!("stopsにgeometryカラムを追加できませんでした");
+            }
+            if !call_spatialite(&tx, "SELECT CreateSpatialIndex('stops', 'geom')")? {
+                bail!("stopsに空間インデックスを作成できませんでした");
+            }
+        }
+        // upsertで置き換えたstopはgeometryが空になるため, 空のものだけ埋める
+        let updated = tx.execute(
+            "UPDATE stops SET geom = MakePoint(stop_lon, stop_lat, 4326) WHERE geom IS NULL",
+            NO_PARAMS,
+        )?;
+        tx.commit()?;
+        debug!("Set geometries of {} stops", updated);
+        Ok(())
+    }
+
+    /// stopsにcreate_stop_geometriesで作ったgeometryカラムがあるか
+    pub fn has_stop_geometries(&mut self) -> Result<bool> {
+        has_stop_geometries(&self.connection)
+    }
+
+    pub fn set_on_conflict(&mut self, on_conflict: OnConflict) {
+        self.on_conflict = on_conflict;
+    }
+
+    /// 1つのINSERT文にまとめる行数. 全テーブルでバインド変数の上限を超えない値だけを受け付ける
+    pub fn set_batch_size(&mut self, rows: usize) -> Result<()> {
+        let max = max_batch_size();
+        if rows == 0 || rows > max {
+            bail!(
+                "1つのINSERT文にまとめる行数は1以上{}以下で指定してください (SQLiteのバインド変数の上限は{}個): {}",
+                max,
+                MAX_VARIABLES,
+                rows
+            );
+        }
+        self.batch_size = Some(rows);
+        debug!("Set batch size: {}", rows);
+        Ok(())
+    }
+
+    /// select_stops_by_route_idと同じ条件に当てはまる件数. limitとoffsetも適用する
+    pub fn count_stops_by_route_id(&self, route_id: &RouteId, query: &Query) -> Result<u32> {
+        count_where(
+            &self.connection,
+            Stop::table_name(),
+            Some(STOPS_BY_ROUTE_ID),
+            &[route_id],
+            query,
+        )
+        .with_context(|| format!("Fail to count stops by route_id: {}", route_id))
+    }
+}
 
-        Ok(GtfsDb { connection: conn })
+/// 登録が途中で失敗しても, set_fast_importで変更したPRAGMAを元に戻す
+impl Drop for GtfsDb {
+    fn drop(&mut self) {
+        if let Some(pragmas) = self.saved_pragmas.take() {
+            if let Err(err) = pragmas.apply(&self.connection) {
+                error!("PRAGMAを元に戻せませんでした: {}", err);
+            }
+        }
     }
 }
 
 impl Gtfs for GtfsDb {
+    fn exists(&self, table_name: &str) -> Result<bool> {
+        let count: i64 = self.connection.query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = ?",
+            &[table_name],
+            |row| row.get(0),
+        )?;
+        Ok(count > 0)
+    }
+
     fn create_all(&self) -> Result<()> {
-        create::<Agency>(&self.connection)?;
-        create::<Route>(&self.connection)?;
-        create::<Stop>(&self.connection)?;
-        create::<Trip>(&self.connection)?;
-        create::<StopTime>(&self.connection)?;
-        create::<Calendar>(&self.connection)?;
-        create::<CalendarDate>(&self.connection)?;
-        create::<FareAttribute>(&self.connection)?;
-        create::<FareRule>(&self.connection)?;
-        create::<Shape>(&self.connection)?;
-        create::<Frequency>(&self.connection)?;
-        create::<Transfer>(&self.connection)?;
-        create::<Feed>(&self.connection)?;
-        create::<Translation>(&self.connection)?;
+        // 外部キーで参照される側から作成する
+        self.create::<Agency>()?;
+        self.create::<OfficeJp>()?;
+        self.create::<Route>()?;
+        self.create::<Stop>()?;
+        self.create::<Trip>()?;
+        self.create::<StopTime>()?;
+        self.create::<Calendar>()?;
+        self.create::<CalendarDate>()?;
+        self.create::<FareAttribute>()?;
+        self.create::<FareRule>()?;
+        self.create::<Shape>()?;
+        self.create::<Frequency>()?;
+        self.create::<Transfer>()?;
+        self.create::<Level>()?;
+        self.create::<Pathway>()?;
+        self.create::<Attribution>()?;
+        self.create::<FeedInfo>()?;
+        self.create::<Translation>()?;
         Ok(())
     }
 
     fn drop_all(&self) -> Result<()> {
-        drop::<Agency>(&self.connection)?;
-        drop::<Route>(&self.connection)?;
-        drop::<Stop>(&self.connection)?;
-        drop::<Trip>(&self.connection)?;
-        drop::<StopTime>(&self.connection)?;
-        drop::<Calendar>(&self.connection)?;
-        drop::<CalendarDate>(&self.connection)?;
-        drop::<FareAttribute>(&self.connection)?;
-        drop::<FareRule>(&self.connection)?;
-        drop::<Shape>(&self.connection)?;
-        drop::<Frequency>(&self.connection)?;
-        drop::<Transfer>(&self.connection)?;
-        drop::<Feed>(&self.connection)?;
+        // 外部キーで参照する側から削除する
         drop::<Translation>(&self.connection)?;
+        drop::<FeedInfo>(&self.connection)?;
+        drop::<Attribution>(&self.connection)?;
+        drop::<Pathway>(&self.connection)?;
+        drop::<Level>(&self.connection)?;
+        drop::<Transfer>(&self.connection)?;
+        drop::<Frequency>(&self.connection)?;
+        drop::<Shape>(&self.connection)?;
+        drop::<FareRule>(&self.connection)?;
+        drop::<FareAttribute>(&self.connection)?;
+        drop::<CalendarDate>(&self.connection)?;
+        drop::<Calendar>(&self.connection)?;
+        drop::<StopTime>(&self.connection)?;
+        drop::<Trip>(&self.connection)?;
+        drop::<Stop>(&self.connection)?;
+        drop::<Route>(&self.connection)?;
+        drop::<OfficeJp>(&self.connection)?;
+        drop::<Agency>(&self.connection)?;
+        Ok(())
+    }
+
+    fn index_all(&self) -> Result<()> {
+        index::<Route>(&self.connection)?;
+        index::<Stop>(&self.connection)?;
+        index::<Trip>(&self.connection)?;
+        index::<StopTime>(&self.connection)?;
         Ok(())
     }
 
     fn insert_agencies(&mut self, agencies: &[Agency]) -> Result<()> {
-        insert(&mut self.connection, agencies)?;
+        insert(
+            &mut self.connection,
+            agencies,
+            self.on_conflict,
+            self.batch_size,
+        )?;
         Ok(())
     }
 
-    fn select_agencies(&mut self) -> Result<Vec<Agency>> {
-        select_all::<Agency>(&mut self.connection).context("Fail to select agency")
+    fn select_agencies(&mut self, query: &Query) -> Result<Vec<Agency>> {
+        select_all::<Agency>(&mut self.connection, query).context("Fail to select agency")
     }
 
     fn insert_stops(&mut self, stops: &[Stop]) -> Result<()> {
-        insert(&mut self.connection, stops)?;
+        insert(
+            &mut self.connection,
+            stops,
+            self.on_conflict,
+            self.batch_size,
+        )?;
         Ok(())
     }
 
-    fn select_stops(&mut self) -> Result<Vec<Stop>> {
-        select_all::<Stop>(&mut self.connection).context("Fail to select stops")
+    fn select_stops(&mut self, query: &Query) -> Result<Vec<Stop>> {
+        select_all::<Stop>(&mut self.connection, query).context("Fail to select stops")
+    }
+
+    fn select_stops_by_stop_id(&mut self, stop_id: &StopId, query: &Query) -> Result<Vec<Stop>> {
+        select_where::<Stop>(&mut self.connection, "stop_id = ?", &[stop_id], query)
+            .with_context(|| format!("Fail to select stops by stop_id: {}", stop_id))
+    }
+
+    fn select_stops_by_parent_station(
+        &mut self,
+        parent_station: &StopId,
+        query: &Query,
+    ) -> Result<Vec<Stop>> {
+        select_where::<Stop>(
+            &mut self.connection,
+            "parent_station = ?",
+            &[parent_station],
+            query,
+        )
+        .with_context(|| format!("Fail to select stops by parent_station: {}", parent_station))
+    }
+
+    fn select_stops_in_bbox(
+        &mut self,
+        south_west: (Latitude, Longitude),
+        north_east: (Latitude, Longitude),
+    ) -> Result<Vec<Stop>> {
+        let (south, west) = (f64::from(south_west.0), f64::from(south_west.1));
+        let (north, east) = (f64::from(north_east.0), f64::from(north_east.1));
+        select_where::<Stop>(
+            &mut self.connection,
+            "stop_lat BETWEEN ? AND ? AND stop_lon BETWEEN ? AND ?",
+            &[&south, &north, &west, &east],
+            &Query::default(),
+        )
+        .context("Fail to select stops in bbox")
+    }
+
+    fn select_stops_within(
+        &mut self,
+        center: (Latitude, Longitude),
+        south_west: (Latitude, Longitude),
+        north_east: (Latitude, Longitude),
+        radius: f64,
+    ) -> Result<Vec<Stop>> {
+        let (lat, lon) = (f64::from(center.0), f64::from(center.1));
+        let (south, west) = (f64::from(south_west.0), f64::from(south_west.1));
+        let (north, east) = (f64::from(north_east.0), f64::from(north_east.1));
+        // ST_Distanceの第3引数を1にすると楕円体上の距離 (メートル) になる
+        select_where_ordered::<Stop>(
+            &mut self.connection,
+            "
+            ROWID IN (
+                SELECT ROWID FROM SpatialIndex
+                WHERE f_table_name = 'stops'
+                AND f_geometry_column = 'geom'
+                AND search_frame = BuildMbr(?1, ?2, ?3, ?4, 4326)
+            )
+            AND ST_Distance(geom, MakePoint(?5, ?6, 4326), 1) <= ?7
+            ",
+            Some("ST_Distance(geom, MakePoint(?5, ?6, 4326), 1), stop_id"),
+            &[&west, &south, &east, &north, &lon, &lat, &radius],
+            &Query::default(),
+        )
+        .context("Fail to select stops within radius")
+    }
+
+    fn select_stops_by_route_id(&mut self, route_id: &RouteId, query: &Query) -> Result<Vec<Stop>> {
+        select_where::<Stop>(&mut self.connection, STOPS_BY_ROUTE_ID, &[route_id], query)
+            .with_context(|| format!("Fail to select stops by route_id: {}", route_id))
+    }
+
+    fn select_stops_by_name_like(&mut self, name: &str, query: &Query) -> Result<Vec<Stop>> {
+        // 入力中の % や _ はワイルドカードではなく文字として扱う
+        let escaped = name
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_");
+        select_where::<Stop>(
+            &mut self.connection,
+            "stop_name LIKE '%' || ? || '%' ESCAPE '\\'",
+            &[&escaped],
+            query,
+        )
+        .with_context(|| format!("Fail to select stops by name: {}", name))
     }
 
     fn insert_routes(&mut self, routes: &[Route]) -> Result<()> {
-        insert(&mut self.connection, routes)?;
+        insert(
+            &mut self.connection,
+            routes,
+            self.on_conflict,
+            self.batch_size,
+        )?;
         Ok(())
     }
 
-    fn select_routes(&mut self) -> Result<Vec<Route>> {
-        select_all::<Route>(&mut self.connection).context("Fail to select_routes")
+    fn select_routes(&mut self, query: &Query) -> Result<Vec<Route>> {
+        select_all::<Route>(&mut self.connection, query).context("Fail to select_routes")
+    }
+
+    fn select_routes_by_route_id(
+        &mut self,
+        route_id: &RouteId,
+        query: &Query,
+    ) -> Result<Vec<Route>> {
+        select_where::<Route>(&mut self.connection, "route_id = ?", &[route_id], query)
+            .with_context(|| format!("Fail to select routes by route_id: {}", route_id))
+    }
+
+    fn select_routes_by_name_like(&mut self, name: &str, query: &Query) -> Result<Vec<Route>> {
+        // 入力中の % や _ はワイルドカードではなく文字として扱う
+        let escaped = name
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_");
+        // SQLiteのLIKEは英字の大文字・小文字を区別しない
+        select_where::<Route>(
+            &mut self.connection,
+            "(
+                route_short_name LIKE '%' || ?1 || '%' ESCAPE '\\'
+                OR route_long_name LIKE '%' || ?1 || '%' ESCAPE '\\'
+            )",
+            &[&escaped],
+            query,
+        )
+        .with_context(|| format!("Fail to select routes by name: {}", name))
+    }
+
+    fn select_routes_by_agency_id(
+        &mut self,
+        agency_id: &AgencyId,
+        query: &Query,
+    ) -> Result<Vec<Route>> {
+        select_where::<Route>(&mut self.connection, "agency_id = ?", &[agency_id], query)
+            .with_context(|| format!("Fail to select routes by agency_id: {}", agency_id))
     }
 
     fn insert_trips(&mut self, trips: &[Trip]) -> Result<()> {
-        insert(&mut self.connection, trips)?;
+        insert(
+            &mut self.connection,
+            trips,
+            self.on_conflict,
+            self.batch_size,
+        )?;
         Ok(())
     }
 
-    fn select_trips(&mut self) -> Result<Vec<Trip>> {
-        select_all::<Trip>(&mut self.connection).context("Fail to select_trips")
+    fn select_trips(&mut self, query: &Query) -> Result<Vec<Trip>> {
+        select_all::<Trip>(&mut self.connection, query).context("Fail to select_trips")
+    }
+
+    fn select_trips_by_service_ids(
+        &mut self,
+        service_ids: &[ServiceId],
+        query: &Query,
+    ) -> Result<Vec<Trip>> {
+        if service_ids.is_empty() {
+            return Ok(vec![]);
+        }
+        let placeholders = vec!["?"; service_ids.len()].join(", ");
+        let params: Vec<&dyn ToSql> = service_ids.iter().map(|id| id as &dyn ToSql).collect();
+        select_where::<Trip>(
+            &mut self.connection,
+            &format!("service_id IN ({})", placeholders),
+            &params,
+            query,
+        )
+        .context("Fail to select trips by service_ids")
+    }
+
+    fn select_trips_by_route_and_direction(
+        &mut self,
+        route_id: Option<&RouteId>,
+        direction: Option<Direction>,
+        query: &Query,
+    ) -> Result<Vec<Trip>> {
+        let direction = direction.map(|x| x as u8);
+        let mut conditions = vec![];
+        let mut params: Vec<&dyn ToSql> = vec![];
+        if let Some(id) = &route_id {
+            conditions.push("route_id = ?");
+            params.push(id);
+        }
+        if let Some(d) = &direction {
+            conditions.push("direction_id = ?");
+            params.push(d);
+        }
+        if conditions.is_empty() {
+            return self.select_trips(query);
+        }
+        select_where::<Trip>(
+            &mut self.connection,
+            &conditions.join(" AND "),
+            &params,
+            query,
+        )
+        .context("Fail to select trips by route_id and direction_id")
+    }
+
+    fn select_block_trips(&mut self, block_id: &BlockId) -> Result<Vec<BlockTrip>> {
+        select_block_trips(&mut self.connection, "t.block_id = ?", block_id)
+            .with_context(|| format!("Fail to select block trips by block_id: {}", block_id))
+    }
+
+    fn select_block_trips_by_trip_id(&mut self, trip_id: &TripId) -> Result<Vec<BlockTrip>> {
+        select_block_trips(
+            &mut self.connection,
+            "t.block_id = (SELECT block_id FROM trips WHERE trip_id = ?)",
+            trip_id,
+        )
+        .with_context(|| format!("Fail to select block trips by trip_id: {}", trip_id))
     }
 
     fn insert_stop_times(&mut self, stop_times: &[StopTime]) -> Result<()> {
-        insert(&mut self.connection, stop_times)?;
+        insert(
+            &mut self.connection,
+            stop_times,
+            self.on_conflict,
+            self.batch_size,
+        )?;
         Ok(())
     }
 
-    fn select_stop_times(&mut self) -> Result<Vec<StopTime>> {
-        select_all::<StopTime>(&mut self.connection).context("Fail to select stop_times")
+    fn update_stop_times_dist_traveled(&mut self, stop_times: &[StopTime]) -> Result<()> {
+        update(
+            &mut self.connection,
+            stop_times,
+            &["shape_dist_traveled"],
+            &["trip_id", "stop_sequence"],
+        )
+    }
+
+    fn update_stop_times_times(&mut self, stop_times: &[StopTime]) -> Result<()> {
+        update(
+            &mut self.connection,
+            stop_times,
+            &["arrival_time", "departure_time"],
+            &["trip_id", "stop_sequence"],
+        )
+    }
+
+    fn select_stop_times(&mut self, query: &Query) -> Result<Vec<StopTime>> {
+        select_all::<StopTime>(&mut self.connection, query).context("Fail to select stop_times")
+    }
+
+    fn for_each_stop_times(
+        &mut self,
+        batch_size: usize,
+        each: &mut dyn FnMut(&[StopTime]) -> Result<()>,
+    ) -> Result<usize> {
+        select_batches(&mut self.connection, None, batch_size, each)
+            .context("Fail to select stop_times")
+    }
+
+    fn for_each_stop_times_by_trip(
+        &mut self,
+        batch_size: usize,
+        each: &mut dyn FnMut(&[StopTime]) -> Result<()>,
+    ) -> Result<usize> {
+        select_batches(
+            &mut self.connection,
+            Some("trip_id, stop_sequence"),
+            batch_size,
+            each,
+        )
+        .context("Fail to select stop_times")
+    }
+
+    fn select_stop_times_by_trip_id(
+        &mut self,
+        trip_id: &TripId,
+        query: &Query,
+    ) -> Result<Vec<StopTime>> {
+        select_where::<StopTime>(&mut self.connection, "trip_id = ?", &[trip_id], query)
+            .with_context(|| format!("Fail to select stop_times by trip_id: {}", trip_id))
+    }
+
+    fn select_stop_times_by_stop_id(
+        &mut self,
+        stop_id: &StopId,
+        query: &Query,
+    ) -> Result<Vec<StopTime>> {
+        select_where::<StopTime>(&mut self.connection, "stop_id = ?", &[stop_id], query)
+            .with_context(|| format!("Fail to select stop_times by stop_id: {}", stop_id))
+    }
+
+    fn select_timetable_by_route_id(&mut self, route_id: &RouteId) -> Result<Vec<TimetableRow>> {
+        ensure_table::<Trip>(&self.connection)?;
+        ensure_table::<StopTime>(&self.connection)?;
+        ensure_table::<Stop>(&self.connection)?;
+        // 標柱が欠けていても通過時刻は落とさないようにstopsは外部結合する
+        let mut stmt = self.connection.prepare(
+            "
+            SELECT
+                t.trip_id,
+                t.trip_headsign,
+                t.direction_id,
+                st.stop_sequence,
+                st.stop_id,
+                s.stop_name,
+                st.arrival_time,
+                st.departure_time
+            FROM trips t
+            JOIN stop_times st ON st.trip_id = t.trip_id
+            LEFT JOIN stops s ON s.stop_id = st.stop_id
+            WHERE t.route_id = ?
+            ORDER BY t.trip_id, st.stop_sequence
+            ",
+        )?;
+        let result: serde_rusqlite::Result<Vec<_>> =
+            from_rows::<TimetableRow>(stmt.query(&[route_id])?).collect();
+        result.with_context(|| format!("Fail to select timetable by route_id: {}", route_id))
+    }
+
+    fn select_trip_endpoints_by_route_id(
+        &mut self,
+        route_id: &RouteId,
+    ) -> Result<Vec<TripEndpoint>> {
+        ensure_table::<Trip>(&self.connection)?;
+        ensure_table::<StopTime>(&self.connection)?;
+        ensure_table::<Stop>(&self.connection)?;
+        // 通過順位が最小・最大のstop_timesをそれぞれ始発・終着とする
+        let mut stmt = self.connection.prepare(
+            "
+            SELECT
+                t.trip_id,
+                t.trip_headsign,
+                t.direction_id,
+                f.stop_id AS origin_stop_id,
+                fs.stop_name AS origin_stop_name,
+                f.departure_time AS first_departure_time,
+                l.stop_id AS destination_stop_id,
+                ls.stop_name AS destination_stop_name,
+                l.arrival_time AS last_arrival_time
+            FROM trips t
+            JOIN (
+                SELECT trip_id, MIN(stop_sequence) AS first_sequence, MAX(stop_sequence) AS last_sequence
+                FROM stop_times
+                WHERE trip_id IN (SELECT trip_id FROM trips WHERE route_id = ?1)
+                GROUP BY trip_id
+            ) r ON r.trip_id = t.trip_id
+            JOIN stop_times f ON f.trip_id = t.trip_id AND f.stop_sequence = r.first_sequence
+            JOIN stop_times l ON l.trip_id = t.trip_id AND l.stop_sequence = r.last_sequence
+            LEFT JOIN stops fs ON fs.stop_id = f.stop_id
+            LEFT JOIN stops ls ON ls.stop_id = l.stop_id
+            WHERE t.route_id = ?1
+            ORDER BY first_departure_time, t.trip_id
+            ",
+        )?;
+        let result: serde_rusqlite::Result<Vec<_>> =
+            from_rows::<TripEndpoint>(stmt.query(&[route_id])?).collect();
+        result.with_context(|| format!("Fail to select trip endpoints by route_id: {}", route_id))
+    }
+
+    fn select_trips_between(&mut self, from: &StopId, to: &StopId) -> Result<Vec<TripBetween>> {
+        ensure_table::<StopTime>(&self.connection)?;
+        ensure_table::<Trip>(&self.connection)?;
+        // 循環する便でfromを複数回通る場合も, それぞれ直後に通るtoと組み合わせる
+        let mut stmt = self.connection.prepare(
+            "
+            SELECT
+                t.trip_id,
+                t.route_id,
+                t.service_id,
+                t.trip_headsign,
+                t.direction_id,
+                a.stop_id AS from_stop_id,
+                a.departure_time,
+                b.stop_id AS to_stop_id,
+                b.arrival_time
+            FROM stop_times a
+            JOIN stop_times b ON b.trip_id = a.trip_id AND b.stop_sequence = (
+                SELECT MIN(stop_sequence) FROM stop_times
+                WHERE trip_id = a.trip_id AND stop_id = ?2 AND stop_sequence > a.stop_sequence
+            )
+            JOIN trips t ON t.trip_id = a.trip_id
+            WHERE a.stop_id = ?1
+            ORDER BY a.departure_time, t.trip_id
+            ",
+        )?;
+        let result: serde_rusqlite::Result<Vec<_>> =
+            from_rows::<TripBetween>(stmt.query(&[from, to])?).collect();
+        result.with_context(|| format!("Fail to select trips between {} and {}", from, to))
     }
 
     fn insert_calendars(&mut self, calendars: &[Calendar]) -> Result<()> {
-        insert(&mut self.connection, calendars)?;
+        insert(
+            &mut self.connection,
+            calendars,
+            self.on_conflict,
+            self.batch_size,
+        )?;
         Ok(())
     }
 
-    fn select_calendars(&mut self) -> Result<Vec<Calendar>> {
-        select_all::<Calendar>(&mut self.connection).context("Fail to select calendars")
+    fn select_calendars(&mut self, query: &Query) -> Result<Vec<Calendar>> {
+        select_all::<Calendar>(&mut self.connection, query).context("Fail to select calendars")
+    }
+
+    fn select_calendars_by_service_id(
+        &mut self,
+        service_id: &ServiceId,
+        query: &Query,
+    ) -> Result<Vec<Calendar>> {
+        select_where::<Calendar>(&mut self.connection, "service_id = ?", &[service_id], query)
+            .with_context(|| format!("Fail to select calendars by service_id: {}", service_id))
     }
 
     fn insert_calendar_dates(&mut self, calendar_dates: &[CalendarDate]) -> Result<()> {
-        insert(&mut self.connection, calendar_dates)?;
+        insert(
+            &mut self.connection,
+            calendar_dates,
+            self.on_conflict,
+            self.batch_size,
+        )?;
         Ok(())
     }
 
-    fn select_calendar_dates(&mut self) -> Result<Vec<CalendarDate>> {
-        select_all::<CalendarDate>(&mut self.connection).context("Fail to select calendar_dates")
+    fn select_calendar_dates(&mut self, query: &Query) -> Result<Vec<CalendarDate>> {
+        select_all::<CalendarDate>(&mut self.connection, query)
+            .context("Fail to select calendar_dates")
     }
 
     fn insert_fare_attributes(&mut self, fare_attributes: &[FareAttribute]) -> Result<()> {
-        insert(&mut self.connection, fare_attributes)?;
+        insert(
+            &mut self.connection,
+            fare_attributes,
+            self.on_conflict,
+            self.batch_size,
+        )?;
         Ok(())
     }
 
-    fn select_fare_attributes(&mut self) -> Result<Vec<FareAttribute>> {
-        select_all::<FareAttribute>(&mut self.connection).context("Fail to select fare_attributes")
+    fn select_fare_attributes(&mut self, query: &Query) -> Result<Vec<FareAttribute>> {
+        select_all::<FareAttribute>(&mut self.connection, query)
+            .context("Fail to select fare_attributes")
     }
 
     fn insert_fare_rules(&mut self, fare_rules: &[FareRule]) -> Result<()> {
-        insert(&mut self.connection, fare_rules)?;
+        insert(
+            &mut self.connection,
+            fare_rules,
+            self.on_conflict,
+            self.batch_size,
+        )?;
         Ok(())
     }
 
-    fn select_fare_rules(&mut self) -> Result<Vec<FareRule>> {
-        select_all::<FareRule>(&mut self.connection).context("Fail to select fare_rules")
+    fn select_fare_rules(&mut self, query: &Query) -> Result<Vec<FareRule>> {
+        select_all::<FareRule>(&mut self.connection, query).context("Fail to select fare_rules")
     }
 
     fn insert_shapes(&mut self, shapes: &[Shape]) -> Result<()> {
-        insert(&mut self.connection, shapes)?;
+        insert(
+            &mut self.connection,
+            shapes,
+            self.on_conflict,
+            self.batch_size,
+        )?;
         Ok(())
     }
 
-    fn select_shapes(&mut self) -> Result<Vec<Shape>> {
-        select_all::<Shape>(&mut self.connection).context("Fail to select shapes")
+    fn update_shapes_dist_traveled(&mut self, shapes: &[Shape]) -> Result<()> {
+        update(
+            &mut self.connection,
+            shapes,
+            &["shape_dist_traveled"],
+            &["shape_id", "shape_pt_sequence"],
+        )
+    }
+
+    fn select_shapes(&mut self, query: &Query) -> Result<Vec<Shape>> {
+        select_all::<Shape>(&mut self.connection, query).context("Fail to select shapes")
+    }
+
+    fn for_each_shapes(
+        &mut self,
+        batch_size: usize,
+        each: &mut dyn FnMut(&[Shape]) -> Result<()>,
+    ) -> Result<usize> {
+        select_batches(&mut self.connection, None, batch_size, each)
+            .context("Fail to select shapes")
+    }
+
+    fn select_route_shapes(&mut self) -> Result<Vec<RouteShape>> {
+        ensure_table::<Trip>(&self.connection)?;
+        let mut stmt = self.connection.prepare(
+            "
+            SELECT route_id, direction_id, shape_id, COUNT(*) AS trips
+            FROM trips
+            WHERE shape_id IS NOT NULL AND shape_id != ''
+            GROUP BY route_id, direction_id, shape_id
+            ORDER BY route_id, direction_id, trips DESC, shape_id
+            ",
+        )?;
+        let result: serde_rusqlite::Result<Vec<_>> =
+            from_rows::<RouteShape>(stmt.query(NO_PARAMS)?).collect();
+        result.context("Fail to select route shapes")
+    }
+
+    fn select_shapes_by_shape_id(
+        &mut self,
+        shape_id: &ShapeId,
+        query: &Query,
+    ) -> Result<Vec<Shape>> {
+        select_where::<Shape>(&mut self.connection, "shape_id = ?", &[shape_id], query)
+            .with_context(|| format!("Fail to select shapes by shape_id: {}", shape_id))
     }
 
     fn insert_frequencies(&mut self, frequencies: &[Frequency]) -> Result<()> {
-        insert(&mut self.connection, frequencies)?;
+        insert(
+            &mut self.connection,
+            frequencies,
+            self.on_conflict,
+            self.batch_size,
+        )?;
         Ok(())
     }
 
-    fn select_frequencies(&mut self) -> Result<Vec<Frequency>> {
-        select_all::<Frequency>(&mut self.connection).context("Fail to select frequencies")
+    fn select_frequencies(&mut self, query: &Query) -> Result<Vec<Frequency>> {
+        select_all::<Frequency>(&mut self.connection, query).context("Fail to select frequencies")
+    }
+
+    fn select_frequencies_by_trip_id(
+        &mut self,
+        trip_id: &TripId,
+        query: &Query,
+    ) -> Result<Vec<Frequency>> {
+        // GtfsTimeは時を2桁に揃えて登録するため, 主キー (trip_id, start_time) の順が時刻順になる
+        select_where::<Frequency>(&mut self.connection, "trip_id = ?", &[trip_id], query)
+            .with_context(|| format!("Fail to select frequencies by trip_id: {}", trip_id))
     }
 
     fn insert_transfers(&mut self, transfers: &[Transfer]) -> Result<()> {
-        insert(&mut self.connection, transfers)?;
+        insert(
+            &mut self.connection,
+            transfers,
+            self.on_conflict,
+            self.batch_size,
+        )?;
         Ok(())
     }
 
-    fn select_transfers(&mut self) -> Result<Vec<Transfer>> {
-        select_all::<Transfer>(&mut self.connection).context("Fail to select transfers")
+    fn select_transfers(&mut self, query: &Query) -> Result<Vec<Transfer>> {
+        select_all::<Transfer>(&mut self.connection, query).context("Fail to select transfers")
+    }
+
+    fn select_stop_transfers(
+        &mut self,
+        from_stop_id: Option<&StopId>,
+        to_stop_id: Option<&StopId>,
+    ) -> Result<Vec<StopTransfer>> {
+        ensure_table::<Transfer>(&self.connection)?;
+        ensure_table::<Stop>(&self.connection)?;
+        // 標柱が欠けていても乗換は落とさないようにstopsは外部結合する
+        let mut stmt = self.connection.prepare(
+            "
+            SELECT
+                t.from_stop_id,
+                fs.stop_name AS from_stop_name,
+                t.to_stop_id,
+                ts.stop_name AS to_stop_name,
+                t.transfer_type,
+                t.min_transfer_time
+            FROM transfers t
+            LEFT JOIN stops fs ON fs.stop_id = t.from_stop_id
+            LEFT JOIN stops ts ON ts.stop_id = t.to_stop_id
+            WHERE (?1 IS NULL OR t.from_stop_id = ?1)
+              AND (?2 IS NULL OR t.to_stop_id = ?2)
+            ORDER BY t.from_stop_id, t.to_stop_id
+            ",
+        )?;
+        let result: serde_rusqlite::Result<Vec<_>> =
+            from_rows::<StopTransfer>(stmt.query(&[&from_stop_id as &dyn ToSql, &to_stop_id])?)
+                .collect();
+        result.context("Fail to select stop transfers")
     }
 
-    fn insert_feeds(&mut self, feeds: &[Feed]) -> Result<()> {
-        insert(&mut self.connection, feeds)?;
+    fn insert_levels(&mut self, levels: &[Level]) -> Result<()> {
+        insert(
+            &mut self.connection,
+            levels,
+            self.on_conflict,
+            self.batch_size,
+        )?;
         Ok(())
     }
 
-    fn select_feeds(&mut self) -> Result<Vec<Feed>> {
-        select_all::<Feed>(&mut self.connection).context("Fail to select feed_info")
+    fn select_levels(&mut self, query: &Query) -> Result<Vec<Level>> {
+        select_all::<Level>(&mut self.connection, query).context("Fail to select levels")
+    }
+
+    fn insert_pathways(&mut self, pathways: &[Pathway]) -> Result<()> {
+        insert(
+            &mut self.connection,
+            pathways,
+            self.on_conflict,
+            self.batch_size,
+        )?;
+        Ok(())
+    }
+
+    fn select_pathways(&mut self, query: &Query) -> Result<Vec<Pathway>> {
+        select_all::<Pathway>(&mut self.connection, query).context("Fail to select pathways")
+    }
+
+    fn insert_attributions(&mut self, attributions: &[Attribution]) -> Result<()> {
+        insert(
+            &mut self.connection,
+            attributions,
+            self.on_conflict,
+            self.batch_size,
+        )?;
+        Ok(())
+    }
+
+    fn select_attributions(&mut self, query: &Query) -> Result<Vec<Attribution>> {
+        select_all::<Attribution>(&mut self.connection, query)
+            .context("Fail to select attributions")
+    }
+
+    fn insert_feed_info(&mut self, feed_info: &FeedInfo) -> Result<()> {
+        insert(
+            &mut self.connection,
+            std::slice::from_ref(feed_info),
+            self.on_conflict,
+            self.batch_size,
+        )?;
+        Ok(())
+    }
+
+    fn select_feed_info(&mut self) -> Result<Option<FeedInfo>> {
+        let feeds = select_all::<FeedInfo>(&mut self.connection, &Query::default())
+            .context("Fail to select feed_info")?;
+        Ok(feeds.into_iter().next())
+    }
+
+    fn insert_offices(&mut self, offices: &[OfficeJp]) -> Result<()> {
+        insert(
+            &mut self.connection,
+            offices,
+            self.on_conflict,
+            self.batch_size,
+        )?;
+        Ok(())
+    }
+
+    fn select_offices(&mut self, query: &Query) -> Result<Vec<OfficeJp>> {
+        select_all::<OfficeJp>(&mut self.connection, query).context("Fail to select office_jp")
+    }
+
+    fn select_offices_by_office_id(
+        &mut self,
+        office_id: &JpOfficeId,
+        query: &Query,
+    ) -> Result<Vec<OfficeJp>> {
+        select_where::<OfficeJp>(&mut self.connection, "office_id = ?", &[office_id], query)
+            .with_context(|| format!("Fail to select office_jp by office_id: {}", office_id))
     }
 
     fn insert_translations(&mut self, translations: &[Translation]) -> Result<()> {
-        insert(&mut self.connection, translations)?;
+        insert(
+            &mut self.connection,
+            translations,
+            self.on_conflict,
+            self.batch_size,
+        )?;
         Ok(())
     }
 
-    fn select_translations(&mut self) -> Result<Vec<Translation>> {
-        select_all::<Translation>(&mut self.connection).context("Fail to select translations")
+    fn select_translations(&mut self, query: &Query) -> Result<Vec<Translation>> {
+        select_all::<Translation>(&mut self.connection, query)
+            .context("Fail to select translations")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn new_accepts_non_utf8_path() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let name = OsStr::from_bytes(b"hibou-\xff.db");
+        assert!(GtfsDb::new(&dir.path().join(name)).is_ok());
+
+        // 開けない場合もパニックせず, パスを示すエラーになる
+        let missing = dir.path().join("missing").join(name);
+        let err = GtfsDb::new(&missing).err().unwrap();
+        assert!(format!("{:#}", err).contains("missing"), "{:#}", err);
+    }
+
+    #[test]
+    fn open_does_not_create_missing_database() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("hibou.db");
+        let err = open(&path).err().unwrap();
+        assert!(format!("{:#}", err).contains("make-db"), "{:#}", err);
+        assert!(!path.exists());
+
+        assert!(create(&path).is_ok());
+        assert!(open(&path).is_ok());
+    }
+
+    #[test]
+    fn select_sql_orders_filtered_rows_by_key() {
+        let query = Query {
+            condition: Some("location_type = 1".to_string()),
+            limit: Some(10),
+            ..Default::default()
+        };
+        assert_eq!(
+            select_sql::<Stop>(Some("stop_name = ?"), None, &query),
+            "SELECT * FROM stops WHERE (location_type = 1) AND (stop_name = ?) \
+             ORDER BY stop_id LIMIT 10 OFFSET 0"
+        );
+        assert_eq!(
+            select_sql::<Shape>(Some("shape_id = ?"), None, &Query::default()),
+            "SELECT * FROM shapes WHERE (shape_id = ?) ORDER BY shape_id, shape_pt_sequence"
+        );
+    }
+
+    #[test]
+    fn foreign_keys_are_off_unless_enforced() {
+        let foreign_keys = |db: &GtfsDb| -> i64 {
+            db.connection
+                .query_row("PRAGMA foreign_keys", NO_PARAMS, |row| row.get(0))
+                .unwrap()
+        };
+        let mut db = GtfsDb::new_in_memory().unwrap();
+        assert_eq!(foreign_keys(&db), 0);
+        db.set_enforce_fk(true).unwrap();
+        assert_eq!(foreign_keys(&db), 1);
+    }
+
+    #[test]
+    fn frequencies_are_ordered_by_time() {
+        let mut db = GtfsDb::new_in_memory().unwrap();
+        db.create_all().unwrap();
+        let frequencies: Vec<Frequency> = ["10:00:00", "25:30:00", "7:00:00"]
+            .iter()
+            .map(|start_time| {
+                serde_json::from_value(serde_json::json!({
+                    "trip_id": "T1",
+                    "start_time": start_time,
+                    "end_time": "26:00:00",
+                    "headway_secs": 600,
+                }))
+                .unwrap()
+            })
+            .collect();
+        db.insert_frequencies(&frequencies).unwrap();
+
+        let selected = db
+            .select_frequencies_by_trip_id(&"T1".to_string(), &Query::default())
+            .unwrap();
+        let start_times: Vec<String> = selected
+            .iter()
+            .map(|x| serde_json::to_value(x).unwrap()["start_time"].to_string())
+            .collect();
+        assert_eq!(
+            start_times,
+            ["\"07:00:00\"", "\"10:00:00\"", "\"25:30:00\""]
+        );
+    }
+
+    #[test]
+    fn fast_import_restores_previous_pragmas() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("hibou.db");
+        let journal_mode = |db: &GtfsDb| -> String {
+            db.connection
+                .query_row("PRAGMA journal_mode", NO_PARAMS, |row| row.get(0))
+                .unwrap()
+        };
+
+        let mut db = GtfsDb::new(&path).unwrap();
+        assert_eq!(journal_mode(&db), "delete");
+        db.set_fast_import(true).unwrap();
+        assert_eq!(journal_mode(&db), "wal");
+        db.set_fast_import(false).unwrap();
+        assert_eq!(journal_mode(&db), "delete");
+
+        // 元がWALなら無効にしてもWALのまま
+        db.connection
+            .execute_batch("PRAGMA journal_mode = WAL")
+            .unwrap();
+        db.set_fast_import(true).unwrap();
+        db.set_fast_import(false).unwrap();
+        assert_eq!(journal_mode(&db), "wal");
+
+        // 無効にせず破棄した場合も元に戻す
+        db.connection
+            .execute_batch("PRAGMA journal_mode = DELETE")
+            .unwrap();
+        db.set_fast_import(true).unwrap();
+        std::mem::drop(db);
+        let db = GtfsDb::new(&path).unwrap();
+        assert_eq!(journal_mode(&db), "delete");
     }
 }
@@ -0,0 +1,18 @@
+use hibou_derive::Table;
+use serde::{Deserialize, Serialize};
+
+/// make-dbで取り込んだファイルの記録. 変更のないファイルを再度取り込まないために使う
+/// GTFSのファイルではなくhibouが管理するテーブル
+#[derive(Debug, Deserialize, Serialize, Table)]
+#[table(name = "hibou_meta")]
+pub struct Meta {
+    /// 取り込み先のテーブル名
+    #[table(sql = "text primary key")]
+    pub table_name: String,
+    /// 取り込み元ファイルのSHA-256
+    #[table(sql = "text not null")]
+    pub file_hash: String,
+    /// 取り込んだ件数
+    #[table(sql = "int not null")]
+    pub records: u32,
+}
@@ -0,0 +1,49 @@
+use std::fs::File;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::external::gtfsdb::Table;
+
+/// GTFSのzipファイルを書き出す
+pub struct GtfsZip {
+    writer: ZipWriter<File>,
+}
+
+impl GtfsZip {
+    pub fn create(path: &Path) -> Result<Self> {
+        let file =
+            File::create(path).with_context(|| format!("{:?} が作成できませんでした", path))?;
+        Ok(GtfsZip {
+            writer: ZipWriter::new(file),
+        })
+    }
+
+    /// {table_name}.txt として書き込む. ヘッダはTable::column_namesの順
+    pub fn write<T>(&mut self, records: &[T]) -> Result<()>
+    where
+        T: Serialize + Table,
+    {
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+        self.writer
+            .start_file(format!("{}.txt", T::table_name()), options)?;
+
+        let mut wtr = csv::WriterBuilder::new()
+            .has_headers(false)
+            .from_writer(&mut self.writer);
+        wtr.write_record(T::column_names())?;
+        for r in records {
+            wtr.serialize(r)?;
+        }
+        wtr.flush()?;
+        Ok(())
+    }
+
+    pub fn finish(self) -> Result<()> {
+        self.writer.finish()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,30 @@
+use std::io;
+
+use anyhow::{bail, Context, Result};
+use log::info;
+use tempfile::NamedTempFile;
+
+/// urlのファイルを一時ファイルにダウンロードする
+/// 一時ファイルは戻り値がdropされると削除される
+pub fn download(url: &str) -> Result<NamedTempFile> {
+    info!("ℹ️ Download {}", url);
+    let mut response =
+        reqwest::blocking::get(url).with_context(|| format!("{} に接続できませんでした", url))?;
+    if !response.status().is_success() {
+        bail!(
+            "{} のダウンロードに失敗しました (status: {})",
+            url,
+            response.status()
+        );
+    }
+
+    let mut file = tempfile::Builder::new()
+        .prefix("hibou-")
+        .suffix(".zip")
+        .tempfile()?;
+    let size = io::copy(&mut response, &mut file)
+        .with_context(|| format!("{} のダウンロード中に問題が発生しました", url))?;
+    info!("  ✨ Success ({} bytes)", size);
+
+    Ok(file)
+}
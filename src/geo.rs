@@ -0,0 +1,174 @@
+//! 緯度経度を扱う計算
+
+use std::str::FromStr;
+
+use anyhow::{bail, Context, Result};
+
+use crate::external::gtfs::{Latitude, Longitude};
+
+/// 地球の平均半径 (メートル)
+const EARTH_RADIUS: f64 = 6_371_008.8;
+
+/// 2点間の大円距離 (メートル) をhaversine公式で求める
+pub fn haversine_meters(lat1: Latitude, lon1: Longitude, lat2: Latitude, lon2: Longitude) -> f64 {
+    let (lat1, lon1) = (f64::from(lat1).to_radians(), f64::from(lon1).to_radians());
+    let (lat2, lon2) = (f64::from(lat2).to_radians(), f64::from(lon2).to_radians());
+    let a = ((lat2 - lat1) / 2.0).sin().powi(2)
+        + lat1.cos() * lat2.cos() * ((lon2 - lon1) / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS * a.sqrt().asin()
+}
+
+/// 中心から半径radius (メートル) の円を含む緯度経度の範囲
+/// 戻り値は ((南端, 西端), (北端, 東端))
+pub fn bounding_box(
+    lat: Latitude,
+    lon: Longitude,
+    radius: f64,
+) -> ((Latitude, Longitude), (Latitude, Longitude)) {
+    let dlat = (radius / EARTH_RADIUS).to_degrees();
+    let dlon = dlat / f64::from(lat).to_radians().cos().max(f64::EPSILON);
+    let (lat, lon) = (f64::from(lat), f64::from(lon));
+    (
+        ((lat - dlat) as Latitude, (lon - dlon) as Longitude),
+        ((lat + dlat) as Latitude, (lon + dlon) as Longitude),
+    )
+}
+
+/// 経度・緯度の矩形範囲 (両端を含む)
+#[derive(Debug, Clone, Copy)]
+pub struct BoundingBox {
+    pub min_lon: f64,
+    pub min_lat: f64,
+    pub max_lon: f64,
+    pub max_lat: f64,
+}
+
+impl FromStr for BoundingBox {
+    type Err = anyhow::Error;
+
+    /// 最小経度,最小緯度,最大経度,最大緯度の順 (ex: 139.7,35.6,139.8,35.7)
+    fn from_str(s: &str) -> Result<Self> {
+        let values = s
+            .split(',')
+            .map(|x| {
+                x.trim()
+                    .parse::<f64>()
+                    .with_context(|| format!("数値ではありません: {}", x))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let (min_lon, min_lat, max_lon, max_lat) = match values.as_slice() {
+            [a, b, c, d] => (*a, *b, *c, *d),
+            _ => bail!(
+                "最小経度,最小緯度,最大経度,最大緯度の4つを指定してください: {}",
+                s
+            ),
+        };
+        if !(-180.0..=180.0).contains(&min_lon) || !(-180.0..=180.0).contains(&max_lon) {
+            bail!("経度は-180から180の範囲で指定してください: {}", s);
+        }
+        if !(-90.0..=90.0).contains(&min_lat) || !(-90.0..=90.0).contains(&max_lat) {
+            bail!("緯度は-90から90の範囲で指定してください: {}", s);
+        }
+        // 日付変更線をまたぐ範囲 (ex: 170,-20,-170,-10) は1つの経度の範囲で表せないため受け付けない
+        if min_lon > max_lon {
+            bail!(
+                "最小経度が最大経度より大きい範囲 (日付変更線をまたぐ範囲) には対応していません. 日付変更線の東西で分けて指定してください: {}",
+                s
+            );
+        }
+        if min_lon == max_lon || min_lat >= max_lat {
+            bail!("最小値は最大値より小さくしてください: {}", s);
+        }
+        Ok(Self {
+            min_lon,
+            min_lat,
+            max_lon,
+            max_lat,
+        })
+    }
+}
+
+/// 点pを線分abへ射影する
+/// 戻り値は (線分上の位置 0.0〜1.0, 射影点までの距離 (メートル))
+/// 線分は十分短い前提で, a付近を平面とみなして計算する
+pub fn project_onto_segment(
+    p: (Latitude, Longitude),
+    a: (Latitude, Longitude),
+    b: (Latitude, Longitude),
+) -> (f64, f64) {
+    let scale = f64::from(a.0).to_radians().cos();
+    let to_xy = |(lat, lon): (Latitude, Longitude)| {
+        (
+            (f64::from(lon) - f64::from(a.1)).to_radians() * scale * EARTH_RADIUS,
+            (f64::from(lat) - f64::from(a.0)).to_radians() * EARTH_RADIUS,
+        )
+    };
+    let (px, py) = to_xy(p);
+    let (bx, by) = to_xy(b);
+
+    let length2 = bx * bx + by * by;
+    let t = if length2 == 0.0 {
+        0.0
+    } else {
+        ((px * bx + py * by) / length2).clamp(0.0, 1.0)
+    };
+    let (dx, dy) = (px - t * bx, py - t * by);
+    (t, (dx * dx + dy * dy).sqrt())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 許容する誤差 (メートル). 座標をf32で持つため数メートルずれる
+    const TOLERANCE: f64 = 10.0;
+
+    #[test]
+    fn haversine_tokyo_to_osaka() {
+        // 東京駅 → 大阪駅 (約403km)
+        let meters = haversine_meters(35.6812, 139.7671, 34.7025, 135.496);
+        assert!((meters - 403_050.0).abs() < TOLERANCE, "{}", meters);
+    }
+
+    #[test]
+    fn haversine_same_point_is_zero() {
+        let meters = haversine_meters(35.6812, 139.7671, 35.6812, 139.7671);
+        assert!(meters.abs() < TOLERANCE, "{}", meters);
+    }
+
+    #[test]
+    fn haversine_antipodal_is_half_circumference() {
+        let meters = haversine_meters(0.0, 0.0, 0.0, 180.0);
+        let expected = std::f64::consts::PI * EARTH_RADIUS;
+        assert!((meters - expected).abs() < TOLERANCE, "{}", meters);
+    }
+
+    #[test]
+    fn bounding_box_from_str() {
+        let b: BoundingBox = " 139.7, 35.6,139.8,35.7".parse().unwrap();
+        assert_eq!(
+            (b.min_lon, b.min_lat, b.max_lon, b.max_lat),
+            (139.7, 35.6, 139.8, 35.7)
+        );
+    }
+
+    #[test]
+    fn bounding_box_rejects_antimeridian() {
+        let err = "170,-20,-170,-10".parse::<BoundingBox>().unwrap_err();
+        assert!(err.to_string().contains("日付変更線"), "{}", err);
+    }
+
+    #[test]
+    fn bounding_box_rejects_invalid_values() {
+        for s in [
+            "139.7,35.6,139.8",
+            "139.7,35.6,139.8,x",
+            "139.7,35.6,181,35.7",
+            "139.7,-91,139.8,35.7",
+            "139.7,35.7,139.8,35.6",
+            "139.7,35.6,139.7,35.7",
+        ] {
+            assert!(s.parse::<BoundingBox>().is_err(), "{}", s);
+        }
+    }
+}
@@ -1,53 +1,467 @@
 use std::io;
-use std::path::PathBuf;
+use std::io::Write;
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
+use encoding_rs_io::DecodeReaderBytesBuilder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use log::error;
+use rayon::prelude::*;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 use strum_macros::{EnumString, EnumVariantNames};
 
-#[derive(Debug, EnumString, EnumVariantNames)]
+use crate::external::gtfsdb::ColumnDescription;
+use crate::io::geojson::ToGeoFeature;
+
+pub mod geojson;
+pub mod parquet;
+pub mod projection;
+
+#[derive(Debug, Clone, Copy, EnumString, EnumVariantNames)]
 #[strum(serialize_all = "lowercase")]
 pub enum Format {
-    CSV,
-    TSV,
-    JSON,
-    PJSON,
-    YAML,
+    Csv,
+    Tsv,
+    Json,
+    Pjson,
+    Ndjson,
+    Yaml,
+    Geojson,
+    Parquet,
+}
+
+impl Format {
+    /// ファイルの拡張子から出力形式を判断する. .gzは除いて判断する (ex: stop_times.csv.gz)
+    pub fn from_path(path: &Path) -> Option<Format> {
+        let path = if is_gzip_path(path) {
+            Path::new(path.file_stem()?)
+        } else {
+            path
+        };
+        let extension = path.extension()?.to_str()?.to_lowercase();
+        match extension.as_str() {
+            "csv" => Some(Format::Csv),
+            "tsv" => Some(Format::Tsv),
+            "json" => Some(Format::Json),
+            "ndjson" => Some(Format::Ndjson),
+            "yaml" | "yml" => Some(Format::Yaml),
+            "geojson" => Some(Format::Geojson),
+            "parquet" => Some(Format::Parquet),
+            _ => None,
+        }
+    }
+}
+
+/// 拡張子が.gzのファイルか
+pub fn is_gzip_path(path: &Path) -> bool {
+    path.extension()
+        .and_then(|x| x.to_str())
+        .is_some_and(|x| x.eq_ignore_ascii_case("gz"))
+}
+
+/// gzipで圧縮して書き込む
+/// 書き終えたらfinishでgzipの末尾を書き出す. finishせずに破棄した場合も末尾を書き出すがエラーはログに出すだけになる
+pub struct GzipWriter<W: Write> {
+    encoder: Option<GzEncoder<W>>,
+}
+
+impl<W: Write> GzipWriter<W> {
+    pub fn new(out: W) -> Self {
+        Self {
+            encoder: Some(GzEncoder::new(out, Compression::default())),
+        }
+    }
+
+    /// gzipの末尾まで書き出して書き込み先を返す
+    pub fn finish(mut self) -> io::Result<W> {
+        match self.encoder.take() {
+            Some(x) => {
+                let mut out = x.finish()?;
+                out.flush()?;
+                Ok(out)
+            }
+            None => unreachable!("GzipWriter is finished only once"),
+        }
+    }
 }
 
-pub fn read<T>(path: &PathBuf) -> Result<Vec<T>>
+impl<W: Write> Write for GzipWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match &mut self.encoder {
+            Some(x) => x.write(buf),
+            None => Ok(0),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.encoder {
+            Some(x) => x.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<W: Write> Drop for GzipWriter<W> {
+    fn drop(&mut self) {
+        let result = self
+            .encoder
+            .take()
+            .map(|x| x.finish().and_then(|mut out| out.flush()));
+        if let Some(Err(err)) = result {
+            error!("gzipの書き込みに失敗しました: {}", err);
+        }
+    }
+}
+
+/// 出力先. gzipで圧縮する場合があるため, 書き終えたらfinishを呼ぶ
+pub enum Output {
+    Plain(Box<dyn Write>),
+    Gzip(Box<GzipWriter<Box<dyn Write>>>),
+}
+
+impl Output {
+    /// 書き込んだ内容を全て書き出す
+    pub fn finish(self) -> io::Result<()> {
+        match self {
+            Output::Plain(mut x) => x.flush(),
+            Output::Gzip(x) => x.finish().map(|_| ()),
+        }
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Output::Plain(x) => x.write(buf),
+            Output::Gzip(x) => x.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Output::Plain(x) => x.flush(),
+            Output::Gzip(x) => x.flush(),
+        }
+    }
+}
+
+/// 読み込むCSVの文字コード
+#[derive(Debug, Clone, Copy, EnumString, EnumVariantNames)]
+pub enum Encoding {
+    #[strum(serialize = "utf-8")]
+    Utf8,
+    #[strum(serialize = "shift_jis")]
+    ShiftJis,
+}
+
+impl Encoding {
+    /// UTF-8に変換しながら読み込むreaderにする
+    pub fn decode<'a, R: io::Read + 'a>(self, reader: R) -> Box<dyn io::Read + 'a> {
+        match self {
+            Encoding::Utf8 => Box::new(reader),
+            Encoding::ShiftJis => Box::new(
+                DecodeReaderBytesBuilder::new()
+                    .encoding(Some(encoding_rs::SHIFT_JIS))
+                    .build(reader),
+            ),
+        }
+    }
+}
+
+/// 並列に変換する行数
+const PARSE_CHUNK: usize = 10000;
+
+/// パースできずに読み飛ばした行
+#[derive(Debug, Serialize)]
+pub struct InvalidRow {
+    pub file: String,
+    pub line: u64,
+    pub message: String,
+}
+
+impl InvalidRow {
+    fn new(file: &str, position: Option<&csv::Position>, err: &csv::Error) -> Self {
+        Self {
+            file: file.to_string(),
+            line: position.map_or(0, |x| x.line()),
+            message: err.to_string(),
+        }
+    }
+}
+
+/// check_headersでヘッダーを検査してからレコードを読み込む
+/// nameはエラーメッセージに使うファイル名
+/// invalid_rowsを渡すとパースできない行でエラーにせず, そこに記録して読み飛ばす
+pub fn read_from<R, T, F>(
+    reader: R,
+    name: &str,
+    check_headers: F,
+    invalid_rows: Option<&mut Vec<InvalidRow>>,
+) -> Result<Vec<T>>
+where
+    R: io::Read,
+    T: DeserializeOwned + Send + 'static,
+    F: FnOnce(&csv::StringRecord) -> Result<()>,
+{
+    let mut records = Vec::new();
+    read_batches_from(
+        reader,
+        name,
+        check_headers,
+        invalid_rows,
+        PARSE_CHUNK,
+        |batch| {
+            records.extend(batch);
+            Ok(())
+        },
+    )?;
+    Ok(records)
+}
+
+/// 全件をメモリに載せず, batch_size件読み込むごとにeachに渡す. 戻り値は読み込んだ件数
+/// 読み込んだ行の変換はrayonのスレッドで行い, 前の塊をeachで処理している間に次の塊を変換する
+pub fn read_batches_from<R, T, F, G>(
+    reader: R,
+    name: &str,
+    check_headers: F,
+    mut invalid_rows: Option<&mut Vec<InvalidRow>>,
+    batch_size: usize,
+    mut each: G,
+) -> Result<usize>
 where
-    T: DeserializeOwned,
+    R: io::Read,
+    T: DeserializeOwned + Send + 'static,
+    F: FnOnce(&csv::StringRecord) -> Result<()>,
+    G: FnMut(Vec<T>) -> Result<()>,
 {
-    let r: Result<Vec<_>, _> = csv::Reader::from_path(path)
-        .with_context(|| format!("{:?} が読み込めませんでした", &path.to_str()))?
-        .deserialize()
+    // 出力元によってはヘッダーにBOMや空白が付くため取り除いてから照合する
+    let mut rdr = csv::ReaderBuilder::new()
+        .trim(csv::Trim::Headers)
+        .from_reader(reader);
+    let headers: csv::StringRecord = rdr
+        .headers()
+        .with_context(|| format!("{:?} のヘッダーが読み込めませんでした", name))?
+        .iter()
+        .map(|x| x.trim_start_matches('\u{feff}'))
         .collect();
-    r.with_context(|| format!("{:?} のパースに問題が発生しました", &path.to_str()))
+    check_headers(&headers)?;
+    let headers = Arc::new(headers);
+
+    // 行の読み込みとeachは順にしかできないため, その間に読み込んだ行の変換を並列に行う
+    let batch_size = batch_size.max(1);
+    let first_invalid = invalid_rows.as_ref().map_or(0, |x| x.len());
+    let mut count = 0;
+    let mut chunk = Vec::with_capacity(batch_size);
+    // 変換中の塊. 順序を保つため一度に1つだけ変換する
+    let mut parsing: Option<Receiver<ParsedChunk<T>>> = None;
+    loop {
+        let mut row = csv::StringRecord::new();
+        let has_row = match (rdr.read_record(&mut row), &mut invalid_rows) {
+            (Ok(x), _) => x,
+            // 列数の不一致などは次の行から読み直せるが, 読み込み自体の失敗は続けられない
+            (Err(err), Some(rows)) if !matches!(err.kind(), csv::ErrorKind::Io(_)) => {
+                rows.push(InvalidRow::new(name, err.position(), &err));
+                continue;
+            }
+            (Err(err), _) => {
+                return Err(err).with_context(|| format!("{:?} のパースに問題が発生しました", name))
+            }
+        };
+        if has_row {
+            chunk.push(row);
+        }
+        if chunk.len() == batch_size || (!has_row && !chunk.is_empty()) {
+            let next = parse_chunk(std::mem::take(&mut chunk), Arc::clone(&headers));
+            chunk.reserve(batch_size);
+            if let Some(previous) = parsing.replace(next) {
+                count += receive_chunk(previous, name, &mut invalid_rows, &mut each)?;
+            }
+        }
+        if !has_row {
+            if let Some(last) = parsing.take() {
+                count += receive_chunk(last, name, &mut invalid_rows, &mut each)?;
+            }
+            // 列数の不一致は変換より先に見つかるため行番号順に並べ直す
+            if let Some(rows) = invalid_rows {
+                rows[first_invalid..].sort_by_key(|x| x.line);
+            }
+            return Ok(count);
+        }
+    }
+}
+
+/// 変換前の行と, 行ごとの変換結果
+type ParsedChunk<T> = (Vec<csv::StringRecord>, Vec<csv::Result<T>>);
+
+/// rayonのスレッドで行を変換し始める. 結果は戻り値から受け取る
+fn parse_chunk<T>(
+    chunk: Vec<csv::StringRecord>,
+    headers: Arc<csv::StringRecord>,
+) -> Receiver<ParsedChunk<T>>
+where
+    T: DeserializeOwned + Send + 'static,
+{
+    let (sender, receiver) = mpsc::channel();
+    rayon::spawn(move || {
+        let parsed = chunk
+            .par_iter()
+            .map(|x| x.deserialize(Some(&headers)))
+            .collect();
+        // 受け取る側がエラーで終了していれば結果は捨てる
+        let _ = sender.send((chunk, parsed));
+    });
+    receiver
 }
 
-pub fn write<T>(records: &[T], format: &Format) -> Result<()>
+/// 変換の終わった塊をeachに渡す. 戻り値は変換できた件数
+fn receive_chunk<T, G>(
+    receiver: Receiver<ParsedChunk<T>>,
+    name: &str,
+    invalid_rows: &mut Option<&mut Vec<InvalidRow>>,
+    each: &mut G,
+) -> Result<usize>
 where
+    G: FnMut(Vec<T>) -> Result<()>,
+{
+    let (chunk, parsed) = receiver
+        .recv()
+        .with_context(|| format!("{:?} の変換が途中で終了しました", name))?;
+    let mut records = Vec::with_capacity(parsed.len());
+    for (row, result) in chunk.iter().zip(parsed) {
+        match (result, &mut *invalid_rows) {
+            (Ok(x), _) => records.push(x),
+            (Err(err), Some(rows)) => rows.push(InvalidRow::new(name, row.position(), &err)),
+            (Err(err), None) => {
+                return Err(err).with_context(|| format!("{:?} のパースに問題が発生しました", name))
+            }
+        }
+    }
+    let count = records.len();
+    each(records)?;
+    Ok(count)
+}
+
+/// headersはcsv, tsv形式でヘッダー行を出力するか. それ以外の形式では無視する
+pub fn write<W, T>(mut out: W, records: &[T], format: &Format, headers: bool) -> Result<()>
+where
+    W: Write,
     T: Serialize,
 {
     match format {
-        Format::CSV => write_csv(records, b','),
-        Format::TSV => write_csv(records, b'\t'),
-        Format::JSON => write_json(records),
-        Format::PJSON => write_pretty_json(records),
-        Format::YAML => write_yaml(records),
+        Format::Csv => write_csv(&mut out, records, b',', headers),
+        Format::Tsv => write_csv(&mut out, records, b'\t', headers),
+        Format::Json => write_json(&mut out, records),
+        Format::Pjson => write_pretty_json(&mut out, records),
+        Format::Ndjson => write_ndjson(&mut out, records),
+        Format::Yaml => write_yaml(&mut out, records),
+        Format::Geojson => bail!("geojson形式はこのデータに対応していません"),
+        Format::Parquet => parquet::write(&mut out, records),
     }?;
+    out.flush()?;
+    Ok(())
+}
+
+/// テーブルのレコード用. parquet形式ではカラムの定義からスキーマを決める
+pub fn write_table<W, T>(
+    mut out: W,
+    records: &[T],
+    format: &Format,
+    headers: bool,
+    columns: &[ColumnDescription],
+) -> Result<()>
+where
+    W: Write,
+    T: Serialize,
+{
+    match format {
+        Format::Parquet => {
+            parquet::write_table(&mut out, records, columns)?;
+            out.flush()?;
+            Ok(())
+        }
+        _ => write(out, records, format, headers),
+    }
+}
+
+/// 座標を持つレコード用. geojson形式にも対応する
+pub fn write_geo<W, T>(mut out: W, records: &[T], format: &Format, headers: bool) -> Result<()>
+where
+    W: Write,
+    T: ToGeoFeature,
+{
+    match format {
+        Format::Geojson => {
+            write_geojson(&mut out, records)?;
+            out.flush()?;
+            Ok(())
+        }
+        _ => write(out, records, format, headers),
+    }
+}
+
+/// --countで出力する件数
+#[derive(Debug, Serialize)]
+struct Count {
+    count: u32,
+}
+
+/// csv, tsvでは件数だけを, それ以外の形式では {"count": N} を出力する
+pub fn write_count<W>(mut out: W, count: u32, format: &Format) -> Result<()>
+where
+    W: Write,
+{
+    let record = Count { count };
+    match format {
+        Format::Csv | Format::Tsv => writeln!(out, "{}", count)?,
+        Format::Json | Format::Ndjson => {
+            serde_json::to_writer(&mut out, &record)?;
+            writeln!(out)?;
+        }
+        Format::Pjson => {
+            serde_json::to_writer_pretty(&mut out, &record)?;
+            writeln!(out)?;
+        }
+        Format::Yaml => serde_yaml::to_writer(&mut out, &record)?,
+        Format::Parquet => parquet::write(&mut out, &[record])?,
+        Format::Geojson => bail!("geojson形式は件数の出力に対応していません"),
+    }
+    out.flush()?;
     Ok(())
 }
 
-fn write_csv<T>(records: &[T], delimiter: u8) -> Result<()>
+pub fn write_stdout<T>(records: &[T], format: &Format) -> Result<()>
+where
+    T: Serialize,
+{
+    if let Format::Parquet = format {
+        bail!("parquet形式は標準出力に対応していません");
+    }
+    write(io::stdout().lock(), records, format, true)
+}
+
+pub fn write_geo_stdout<T>(records: &[T], format: &Format) -> Result<()>
+where
+    T: ToGeoFeature,
+{
+    if let Format::Parquet = format {
+        bail!("parquet形式は標準出力に対応していません");
+    }
+    write_geo(io::stdout().lock(), records, format, true)
+}
+
+fn write_csv<W, T>(out: W, records: &[T], delimiter: u8, headers: bool) -> Result<()>
 where
+    W: Write,
     T: Serialize,
 {
     let mut wtr = csv::WriterBuilder::new()
         .delimiter(delimiter)
-        .from_writer(io::stdout());
+        .has_headers(headers)
+        .from_writer(out);
     for r in records {
         wtr.serialize(r)?;
         wtr.flush()?;
@@ -55,26 +469,139 @@ where
     Ok(())
 }
 
-fn write_json<T>(records: &[T]) -> Result<()>
+fn write_json<W, T>(mut out: W, records: &[T]) -> Result<()>
 where
+    W: Write,
     T: Serialize,
 {
-    serde_json::to_writer(io::stdout(), records)?;
+    serde_json::to_writer(&mut out, records)?;
+    writeln!(out)?;
     Ok(())
 }
 
-fn write_pretty_json<T>(records: &[T]) -> Result<()>
+fn write_pretty_json<W, T>(mut out: W, records: &[T]) -> Result<()>
 where
+    W: Write,
     T: Serialize,
 {
-    serde_json::to_writer_pretty(io::stdout(), records)?;
+    serde_json::to_writer_pretty(&mut out, records)?;
+    writeln!(out)?;
     Ok(())
 }
 
-fn write_yaml<T>(records: &[T]) -> Result<()>
+fn write_ndjson<W, T>(mut out: W, records: &[T]) -> Result<()>
 where
+    W: Write,
     T: Serialize,
 {
-    serde_yaml::to_writer(io::stdout(), records)?;
+    for r in records {
+        serde_json::to_writer(&mut out, r)?;
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+fn write_geojson<W, T>(mut out: W, records: &[T]) -> Result<()>
+where
+    W: Write,
+    T: ToGeoFeature,
+{
+    serde_json::to_writer(&mut out, &geojson::to_feature_collection(records)?)?;
+    writeln!(out)?;
     Ok(())
 }
+
+fn write_yaml<W, T>(out: W, records: &[T]) -> Result<()>
+where
+    W: Write,
+    T: Serialize,
+{
+    serde_yaml::to_writer(out, records)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Debug, Deserialize, Serialize)]
+    struct Row {
+        stop_id: String,
+        stop_name: String,
+    }
+
+    #[test]
+    fn read_strips_bom_from_first_header() {
+        let data = "\u{feff}stop_id,stop_name\nS1,東京駅\n";
+        let mut headers = Vec::new();
+        let rows: Vec<Row> = read_from(
+            data.as_bytes(),
+            "stops.txt",
+            |x| {
+                headers = x.iter().map(String::from).collect();
+                Ok(())
+            },
+            None,
+        )
+        .unwrap();
+        assert_eq!(headers[0], "stop_id");
+        assert_eq!(rows[0].stop_id, "S1");
+        assert_eq!(rows[0].stop_name, "東京駅");
+    }
+
+    #[test]
+    fn read_reports_file_and_line_of_invalid_utf8() {
+        // 2行目の停留所名がShift_JIS (東京)
+        let mut data = b"stop_id,stop_name\nS1,".to_vec();
+        data.extend([0x93, 0x8c, 0x8b, 0x9e]);
+        data.extend(b"\n");
+        let err =
+            read_from::<_, Row, _>(data.as_slice(), "stops.txt", |_| Ok(()), None).unwrap_err();
+        let message = format!("{:#}", err);
+        assert!(message.contains("stops.txt"), "{}", message);
+        assert!(message.contains("line 2"), "{}", message);
+    }
+
+    fn rows() -> Vec<Row> {
+        vec![Row {
+            stop_id: "S1".to_string(),
+            stop_name: "東京駅".to_string(),
+        }]
+    }
+
+    #[test]
+    fn write_csv_into_buffer() {
+        let mut out = Vec::new();
+        write(&mut out, &rows(), &Format::Csv, true).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "stop_id,stop_name\nS1,東京駅\n"
+        );
+    }
+
+    #[test]
+    fn write_json_into_buffer() {
+        let mut out = Vec::new();
+        write(&mut out, &rows(), &Format::Json, true).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "[{\"stop_id\":\"S1\",\"stop_name\":\"東京駅\"}]\n"
+        );
+    }
+
+    #[test]
+    fn gzip_finish_writes_whole_stream() {
+        let mut out = GzipWriter::new(Vec::new());
+        write(&mut out, &rows(), &Format::Csv, true).unwrap();
+        let compressed = out.finish().unwrap();
+        let mut decoded = String::new();
+        io::Read::read_to_string(
+            &mut flate2::read::GzDecoder::new(compressed.as_slice()),
+            &mut decoded,
+        )
+        .unwrap();
+        assert_eq!(decoded, "stop_id,stop_name\nS1,東京駅\n");
+    }
+}
@@ -0,0 +1,63 @@
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::external::gtfs::{Latitude, Longitude};
+
+/// 経度・緯度の順で並べた座標
+pub type Position = [f32; 2];
+
+pub fn position(lat: Latitude, lon: Longitude) -> Position {
+    [lon, lat]
+}
+
+/// GeoJSONのgeometry
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", content = "coordinates")]
+pub enum Geometry {
+    Point(Position),
+    LineString(Vec<Position>),
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type")]
+pub struct Feature {
+    geometry: Geometry,
+    properties: Map<String, Value>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type")]
+pub struct FeatureCollection {
+    features: Vec<Feature>,
+}
+
+/// GeoJSONのFeatureに変換できるレコード
+pub trait ToGeoFeature: Serialize {
+    /// geometryで表現するためpropertiesからは除くフィールド
+    fn geometry_fields() -> &'static [&'static str];
+    fn geometry(&self) -> Geometry;
+}
+
+pub fn to_feature_collection<T>(records: &[T]) -> Result<FeatureCollection>
+where
+    T: ToGeoFeature,
+{
+    let features = records.iter().map(to_feature).collect::<Result<Vec<_>>>()?;
+    Ok(FeatureCollection { features })
+}
+
+fn to_feature<T>(record: &T) -> Result<Feature>
+where
+    T: ToGeoFeature,
+{
+    // Valueへ直接変換するとf32がf64に広げられて桁が崩れるため文字列を経由する
+    let mut properties: Map<String, Value> = serde_json::from_str(&serde_json::to_string(record)?)?;
+    for field in T::geometry_fields() {
+        properties.remove(*field);
+    }
+    Ok(Feature {
+        geometry: record.geometry(),
+        properties,
+    })
+}
@@ -0,0 +1,703 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::io::Write;
+use std::sync::Arc;
+
+use anyhow::{bail, Context, Result};
+use parquet::basic::{LogicalType, Repetition, Type as PhysicalType};
+use parquet::data_type::{BoolType, ByteArray, ByteArrayType, DataType, DoubleType};
+use parquet::data_type::{FloatType, Int64Type};
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::{SerializedColumnWriter, SerializedFileWriter};
+use parquet::schema::types::Type;
+use serde::de::value::Error;
+use serde::ser::{self, Impossible, SerializeMap, SerializeStruct};
+use serde::{Serialize, Serializer};
+
+use crate::external::gtfsdb::ColumnDescription;
+
+/// 1レコード分の (カラム名, 値) の並び
+type Row = Vec<(String, Value)>;
+
+/// レコードをparquet形式で出力する
+/// カラムの型は値から決めるため, 全てnullのカラムは文字列になり, 0件では出力できない
+/// テーブルのレコードはwrite_tableでテーブルの定義から型を決める
+pub fn write<W, T>(out: W, records: &[T]) -> Result<()>
+where
+    W: Write,
+    T: Serialize,
+{
+    let rows = serialize_rows(records)?;
+    let names: Vec<String> = match rows.first() {
+        Some(row) => row.iter().map(|(name, _)| name.clone()).collect(),
+        None => bail!("出力するレコードがないためparquet形式のカラムを決められません"),
+    };
+    let columns = to_columns(rows, &names);
+    let kinds = names
+        .iter()
+        .zip(&columns)
+        .map(|(name, values)| column_kind(name, values))
+        .collect::<Result<Vec<_>>>()?;
+    write_columns(out, &names, &kinds, &columns)
+}
+
+/// テーブルのレコードをparquet形式で出力する
+/// カラムの並びと型はテーブルの定義から決めるため, 値によらず同じスキーマになり0件でも出力できる
+pub fn write_table<W, T>(out: W, records: &[T], definitions: &[ColumnDescription]) -> Result<()>
+where
+    W: Write,
+    T: Serialize,
+{
+    let names: Vec<String> = definitions.iter().map(|x| x.name.to_string()).collect();
+    let kinds: Vec<Kind> = definitions.iter().map(|x| sql_kind(&x.sql_type)).collect();
+    let mut columns = to_columns(serialize_rows(records)?, &names);
+    for ((name, kind), values) in names.iter().zip(&kinds).zip(columns.iter_mut()) {
+        for value in values.iter_mut() {
+            *value = coerce(std::mem::replace(value, Value::Null), *kind)
+                .with_context(|| format!("{} の値をparquet形式の型に変換できません", name))?;
+        }
+    }
+    write_columns(out, &names, &kinds, &columns)
+}
+
+fn serialize_rows<T: Serialize>(records: &[T]) -> Result<Vec<Row>> {
+    Ok(records
+        .iter()
+        .map(|x| x.serialize(RowSerializer))
+        .collect::<Result<Vec<_>, _>>()?)
+}
+
+/// 行の並びをカラムごとの値の並びにする. 行にないカラムはnullにする
+fn to_columns(rows: Vec<Row>, names: &[String]) -> Vec<Vec<Value>> {
+    let mut columns: Vec<Vec<Value>> = vec![Vec::with_capacity(rows.len()); names.len()];
+    for row in rows {
+        let mut row: HashMap<String, Value> = row.into_iter().collect();
+        for (column, name) in columns.iter_mut().zip(names) {
+            column.push(row.remove(name).unwrap_or(Value::Null));
+        }
+    }
+    columns
+}
+
+fn write_columns<W: Write>(
+    mut out: W,
+    names: &[String],
+    kinds: &[Kind],
+    columns: &[Vec<Value>],
+) -> Result<()> {
+    let fields = names
+        .iter()
+        .zip(kinds)
+        .map(|(name, kind)| field(name, *kind).map(Arc::new))
+        .collect::<Result<Vec<_>>>()?;
+    let schema = Type::group_type_builder("schema")
+        .with_fields(fields)
+        .build()?;
+
+    // 書き込み先にSendを要求されるため, 一度メモリ上に出力してから書き出す
+    let mut buf = Vec::new();
+    let mut writer = SerializedFileWriter::new(
+        &mut buf,
+        Arc::new(schema),
+        Arc::new(WriterProperties::builder().build()),
+    )?;
+    let mut row_group = writer.next_row_group()?;
+    for (values, kind) in columns.iter().zip(kinds) {
+        let mut column = match row_group.next_column()? {
+            Some(x) => x,
+            None => bail!("parquet形式のカラム数が一致しません"),
+        };
+        write_column(&mut column, values, *kind)?;
+        column.close()?;
+    }
+    row_group.close()?;
+    writer.close()?;
+
+    out.write_all(&buf)?;
+    Ok(())
+}
+
+/// parquet形式のカラムの型
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Bool,
+    Int,
+    Float,
+    Double,
+    Str,
+}
+
+/// 1つのセルの値. f32をf64に変換すると誤差が出るため分けて持つ
+#[derive(Debug, Clone)]
+enum Value {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f32),
+    Double(f64),
+    Str(String),
+}
+
+impl Value {
+    fn kind(&self) -> Option<Kind> {
+        match self {
+            Value::Null => None,
+            Value::Bool(_) => Some(Kind::Bool),
+            Value::Int(_) => Some(Kind::Int),
+            Value::Float(_) => Some(Kind::Float),
+            Value::Double(_) => Some(Kind::Double),
+            Value::Str(_) => Some(Kind::Str),
+        }
+    }
+}
+
+/// SQLの型に対応するparquet形式の型. 時刻などの文字列で持つカラムは文字列にする
+fn sql_kind(sql_type: &str) -> Kind {
+    match sql_type {
+        "int" | "integer" | "bool" | "boolean" => Kind::Int,
+        "double" | "real" | "float" => Kind::Double,
+        _ => Kind::Str,
+    }
+}
+
+/// 値をカラムの型に揃える. f32は10進の表記を保ったままf64にする
+fn coerce(value: Value, kind: Kind) -> Result<Value> {
+    Ok(match (value, kind) {
+        (Value::Null, _) => Value::Null,
+        (Value::Bool(v), Kind::Int) => Value::Int(v.into()),
+        (Value::Int(v), Kind::Double) => Value::Double(v as f64),
+        (Value::Float(v), Kind::Double) => Value::Double(v.to_string().parse()?),
+        (Value::Bool(v), Kind::Str) => Value::Str(v.to_string()),
+        (Value::Int(v), Kind::Str) => Value::Str(v.to_string()),
+        (Value::Float(v), Kind::Str) => Value::Str(v.to_string()),
+        (Value::Double(v), Kind::Str) => Value::Str(v.to_string()),
+        (value, kind) if value.kind() == Some(kind) => value,
+        (value, kind) => bail!("{:?} は {:?} にできません", value, kind),
+    })
+}
+
+/// 値の型が揃っているか検査する. 全てnullのカラムは文字列として扱う
+fn column_kind(name: &str, values: &[Value]) -> Result<Kind> {
+    let mut kinds = values.iter().filter_map(Value::kind);
+    let kind = kinds.next().unwrap_or(Kind::Str);
+    if kinds.any(|x| x != kind) {
+        bail!(
+            "{} の値の型が揃っていないためparquet形式に出力できません",
+            name
+        );
+    }
+    Ok(kind)
+}
+
+fn field(name: &str, kind: Kind) -> Result<Type> {
+    let physical = match kind {
+        Kind::Bool => PhysicalType::BOOLEAN,
+        Kind::Int => PhysicalType::INT64,
+        Kind::Float => PhysicalType::FLOAT,
+        Kind::Double => PhysicalType::DOUBLE,
+        Kind::Str => PhysicalType::BYTE_ARRAY,
+    };
+    let logical = match kind {
+        Kind::Str => Some(LogicalType::String),
+        _ => None,
+    };
+    Ok(Type::primitive_type_builder(name, physical)
+        .with_repetition(Repetition::OPTIONAL)
+        .with_logical_type(logical)
+        .build()?)
+}
+
+fn write_column(column: &mut SerializedColumnWriter, values: &[Value], kind: Kind) -> Result<()> {
+    // nullは定義レベル0として値を書かない
+    let levels: Vec<i16> = values.iter().map(|x| x.kind().map_or(0, |_| 1)).collect();
+    match kind {
+        Kind::Bool => write_values::<BoolType, _>(column, values, &levels, |x| match x {
+            Value::Bool(v) => Some(*v),
+            _ => None,
+        }),
+        Kind::Int => write_values::<Int64Type, _>(column, values, &levels, |x| match x {
+            Value::Int(v) => Some(*v),
+            _ => None,
+        }),
+        Kind::Float => write_values::<FloatType, _>(column, values, &levels, |x| match x {
+            Value::Float(v) => Some(*v),
+            _ => None,
+        }),
+        Kind::Double => write_values::<DoubleType, _>(column, values, &levels, |x| match x {
+            Value::Double(v) => Some(*v),
+            _ => None,
+        }),
+        Kind::Str => write_values::<ByteArrayType, _>(column, values, &levels, |x| match x {
+            Value::Str(v) => Some(ByteArray::from(v.as_str())),
+            _ => None,
+        }),
+    }
+}
+
+fn write_values<D, F>(
+    column: &mut SerializedColumnWriter,
+    values: &[Value],
+    levels: &[i16],
+    get: F,
+) -> Result<()>
+where
+    D: DataType,
+    F: Fn(&Value) -> Option<D::T>,
+{
+    let values: Vec<D::T> = values.iter().filter_map(get).collect();
+    column
+        .typed::<D>()
+        .write_batch(&values, Some(levels), None)?;
+    Ok(())
+}
+
+macro_rules! unsupported {
+    ($($method:ident($($arg:ty),*);)*) => {
+        $(
+            fn $method(self, $(_: $arg),*) -> Result<Self::Ok, Self::Error> {
+                Err(ser::Error::custom("only structs can be written as parquet"))
+            }
+        )*
+    };
+}
+
+/// 構造体の各フィールドを (名前, 値) の並びにするSerializer
+struct RowSerializer;
+
+/// フィールドを順に集める
+struct RowFields(Row);
+
+/// #[serde(flatten)]を含む構造体はmapとして出力されるため, キーと値を順に集める
+struct RowEntries {
+    entries: Row,
+    key: Option<String>,
+}
+
+impl Serializer for RowSerializer {
+    type Ok = Row;
+    type Error = Error;
+    type SerializeSeq = Impossible<Self::Ok, Error>;
+    type SerializeTuple = Impossible<Self::Ok, Error>;
+    type SerializeTupleStruct = Impossible<Self::Ok, Error>;
+    type SerializeTupleVariant = Impossible<Self::Ok, Error>;
+    type SerializeMap = RowEntries;
+    type SerializeStruct = RowFields;
+    type SerializeStructVariant = Impossible<Self::Ok, Error>;
+
+    unsupported! {
+        serialize_bool(bool);
+        serialize_i8(i8);
+        serialize_i16(i16);
+        serialize_i32(i32);
+        serialize_i64(i64);
+        serialize_u8(u8);
+        serialize_u16(u16);
+        serialize_u32(u32);
+        serialize_u64(u64);
+        serialize_f32(f32);
+        serialize_f64(f64);
+        serialize_char(char);
+        serialize_str(&str);
+        serialize_bytes(&[u8]);
+        serialize_none();
+        serialize_unit();
+        serialize_unit_struct(&'static str);
+        serialize_unit_variant(&'static str, u32, &'static str);
+    }
+
+    fn serialize_some<V: ?Sized + Serialize>(self, _: &V) -> Result<Self::Ok, Error> {
+        Err(ser::Error::custom("only structs can be written as parquet"))
+    }
+
+    fn serialize_newtype_struct<V: ?Sized + Serialize>(
+        self,
+        _: &'static str,
+        _: &V,
+    ) -> Result<Self::Ok, Error> {
+        Err(ser::Error::custom("only structs can be written as parquet"))
+    }
+
+    fn serialize_newtype_variant<V: ?Sized + Serialize>(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: &V,
+    ) -> Result<Self::Ok, Error> {
+        Err(ser::Error::custom("only structs can be written as parquet"))
+    }
+
+    fn serialize_seq(self, _: Option<usize>) -> Result<Self::SerializeSeq, Error> {
+        Err(ser::Error::custom("only structs can be written as parquet"))
+    }
+
+    fn serialize_tuple(self, _: usize) -> Result<Self::SerializeTuple, Error> {
+        Err(ser::Error::custom("only structs can be written as parquet"))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleStruct, Error> {
+        Err(ser::Error::custom("only structs can be written as parquet"))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleVariant, Error> {
+        Err(ser::Error::custom("only structs can be written as parquet"))
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, Error> {
+        Ok(RowEntries {
+            entries: Vec::with_capacity(len.unwrap_or_default()),
+            key: None,
+        })
+    }
+
+    fn serialize_struct(self, _: &'static str, len: usize) -> Result<RowFields, Error> {
+        Ok(RowFields(Vec::with_capacity(len)))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStructVariant, Error> {
+        Err(ser::Error::custom("only structs can be written as parquet"))
+    }
+}
+
+impl SerializeStruct for RowFields {
+    type Ok = Row;
+    type Error = Error;
+
+    fn serialize_field<V: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &V,
+    ) -> Result<(), Error> {
+        self.0
+            .push((key.to_string(), value.serialize(CellSerializer)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok, Error> {
+        Ok(self.0)
+    }
+}
+
+impl SerializeMap for RowEntries {
+    type Ok = Row;
+    type Error = Error;
+
+    fn serialize_key<K: ?Sized + Serialize>(&mut self, key: &K) -> Result<(), Error> {
+        match key.serialize(CellSerializer)? {
+            Value::Str(x) => self.key = Some(x),
+            _ => return Err(ser::Error::custom("map keys must be strings for parquet")),
+        }
+        Ok(())
+    }
+
+    fn serialize_value<V: ?Sized + Serialize>(&mut self, value: &V) -> Result<(), Error> {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| ser::Error::custom("map value without key"))?;
+        self.entries.push((key, value.serialize(CellSerializer)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok, Error> {
+        Ok(self.entries)
+    }
+}
+
+/// フィールドの値を1つのValueにするSerializer
+struct CellSerializer;
+
+impl Serializer for CellSerializer {
+    type Ok = Value;
+    type Error = Error;
+    type SerializeSeq = Impossible<Value, Error>;
+    type SerializeTuple = Impossible<Value, Error>;
+    type SerializeTupleStruct = Impossible<Value, Error>;
+    type SerializeTupleVariant = Impossible<Value, Error>;
+    type SerializeMap = Impossible<Value, Error>;
+    type SerializeStruct = Impossible<Value, Error>;
+    type SerializeStructVariant = Impossible<Value, Error>;
+
+    fn serialize_bool(self, v: bool) -> Result<Value, Error> {
+        Ok(Value::Bool(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Value, Error> {
+        Ok(Value::Int(v.into()))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Value, Error> {
+        Ok(Value::Int(v.into()))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Value, Error> {
+        Ok(Value::Int(v.into()))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Value, Error> {
+        Ok(Value::Int(v))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Value, Error> {
+        Ok(Value::Int(v.into()))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Value, Error> {
+        Ok(Value::Int(v.into()))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Value, Error> {
+        Ok(Value::Int(v.into()))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Value, Error> {
+        i64::try_from(v)
+            .map(Value::Int)
+            .map_err(|_| ser::Error::custom(format!("{} is too large for parquet INT64", v)))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Value, Error> {
+        Ok(Value::Float(v))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Value, Error> {
+        Ok(Value::Double(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Value, Error> {
+        Ok(Value::Str(v.to_string()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Value, Error> {
+        Ok(Value::Str(v.to_string()))
+    }
+
+    fn serialize_bytes(self, _: &[u8]) -> Result<Value, Error> {
+        Err(ser::Error::custom("bytes cannot be written as parquet"))
+    }
+
+    fn serialize_none(self) -> Result<Value, Error> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_some<V: ?Sized + Serialize>(self, value: &V) -> Result<Value, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Value, Error> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_unit_struct(self, _: &'static str) -> Result<Value, Error> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+    ) -> Result<Value, Error> {
+        Ok(Value::Str(variant.to_string()))
+    }
+
+    fn serialize_newtype_struct<V: ?Sized + Serialize>(
+        self,
+        _: &'static str,
+        value: &V,
+    ) -> Result<Value, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<V: ?Sized + Serialize>(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: &V,
+    ) -> Result<Value, Error> {
+        Err(ser::Error::custom(
+            "nested values cannot be written as parquet",
+        ))
+    }
+
+    fn serialize_seq(self, _: Option<usize>) -> Result<Self::SerializeSeq, Error> {
+        Err(ser::Error::custom(
+            "nested values cannot be written as parquet",
+        ))
+    }
+
+    fn serialize_tuple(self, _: usize) -> Result<Self::SerializeTuple, Error> {
+        Err(ser::Error::custom(
+            "nested values cannot be written as parquet",
+        ))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleStruct, Error> {
+        Err(ser::Error::custom(
+            "nested values cannot be written as parquet",
+        ))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleVariant, Error> {
+        Err(ser::Error::custom(
+            "nested values cannot be written as parquet",
+        ))
+    }
+
+    fn serialize_map(self, _: Option<usize>) -> Result<Self::SerializeMap, Error> {
+        Err(ser::Error::custom(
+            "nested values cannot be written as parquet",
+        ))
+    }
+
+    fn serialize_struct(self, _: &'static str, _: usize) -> Result<Self::SerializeStruct, Error> {
+        Err(ser::Error::custom(
+            "nested values cannot be written as parquet",
+        ))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStructVariant, Error> {
+        Err(ser::Error::custom(
+            "nested values cannot be written as parquet",
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::io::{Seek, SeekFrom};
+
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+
+    use super::*;
+
+    #[derive(Serialize)]
+    struct Stop {
+        stop_id: String,
+        stop_lat: Option<f32>,
+    }
+
+    #[derive(Serialize)]
+    struct Line {
+        #[serde(flatten)]
+        stop: Stop,
+        distance: i32,
+    }
+
+    fn definitions() -> Vec<ColumnDescription> {
+        vec![
+            ColumnDescription {
+                name: "stop_id",
+                sql_type: "text".to_string(),
+                nullable: false,
+                values: None,
+            },
+            ColumnDescription {
+                name: "stop_lat",
+                sql_type: "double".to_string(),
+                nullable: true,
+                values: None,
+            },
+        ]
+    }
+
+    fn read_back(mut file: File) -> SerializedFileReader<File> {
+        file.seek(SeekFrom::Start(0)).unwrap();
+        SerializedFileReader::new(file).unwrap()
+    }
+
+    fn physical_types(reader: &SerializedFileReader<File>) -> Vec<(String, PhysicalType)> {
+        reader
+            .metadata()
+            .file_metadata()
+            .schema_descr()
+            .columns()
+            .iter()
+            .map(|x| (x.name().to_string(), x.physical_type()))
+            .collect()
+    }
+
+    #[test]
+    fn write_table_without_records() {
+        let mut file = tempfile::tempfile().unwrap();
+        write_table::<_, Stop>(&mut file, &[], &definitions()).unwrap();
+        let reader = read_back(file);
+        assert_eq!(reader.metadata().file_metadata().num_rows(), 0);
+        assert_eq!(
+            physical_types(&reader),
+            vec![
+                ("stop_id".to_string(), PhysicalType::BYTE_ARRAY),
+                ("stop_lat".to_string(), PhysicalType::DOUBLE),
+            ]
+        );
+    }
+
+    #[test]
+    fn write_table_keeps_type_of_null_column() {
+        let stops = [Stop {
+            stop_id: "S1".to_string(),
+            stop_lat: None,
+        }];
+        let mut file = tempfile::tempfile().unwrap();
+        write_table(&mut file, &stops, &definitions()).unwrap();
+        let reader = read_back(file);
+        assert_eq!(reader.metadata().file_metadata().num_rows(), 1);
+        assert_eq!(physical_types(&reader)[1].1, PhysicalType::DOUBLE);
+    }
+
+    #[test]
+    fn write_flattened_struct() {
+        let lines = [Line {
+            stop: Stop {
+                stop_id: "S1".to_string(),
+                stop_lat: Some(35.681),
+            },
+            distance: 10,
+        }];
+        let mut file = tempfile::tempfile().unwrap();
+        write(&mut file, &lines).unwrap();
+        let reader = read_back(file);
+        assert_eq!(
+            physical_types(&reader),
+            vec![
+                ("stop_id".to_string(), PhysicalType::BYTE_ARRAY),
+                ("stop_lat".to_string(), PhysicalType::FLOAT),
+                ("distance".to_string(), PhysicalType::INT64),
+            ]
+        );
+    }
+}
@@ -0,0 +1,358 @@
+use serde::ser::{self, Impossible, SerializeStruct};
+use serde::{Serialize, Serializer};
+
+/// レコードの一部のカラムだけを指定した順に出力する
+/// 一度serde_json::Valueに変換するとf32の値に誤差が出るため, 元のレコードから直接出力する
+pub struct Projected<'a, T> {
+    record: &'a T,
+    columns: &'a [&'static str],
+}
+
+pub fn project<'a, T>(records: &'a [T], columns: &'a [&'static str]) -> Vec<Projected<'a, T>> {
+    records
+        .iter()
+        .map(|record| Projected { record, columns })
+        .collect()
+}
+
+impl<T: Serialize> Serialize for Projected<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("Projected", self.columns.len())?;
+        for column in self.columns {
+            s.serialize_field(
+                column,
+                &Field {
+                    record: self.record,
+                    name: column,
+                },
+            )?;
+        }
+        s.end()
+    }
+}
+
+/// レコードの一部のフィールドを別の値に置き換えて出力する
+/// フィールドの順序やそれ以外の値は元のレコードのまま
+pub struct Replaced<'a, T, V> {
+    record: &'a T,
+    values: Vec<(&'static str, V)>,
+}
+
+pub fn replace<'a, T, V>(record: &'a T, values: Vec<(&'static str, V)>) -> Replaced<'a, T, V> {
+    Replaced { record, values }
+}
+
+impl<T: Serialize, V: Serialize> Serialize for Replaced<'_, T, V> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.record.serialize(Replace {
+            values: &self.values,
+            inner: serializer,
+        })
+    }
+}
+
+/// レコードのうちnameのフィールドだけ
+struct Field<'a, T> {
+    record: &'a T,
+    name: &'static str,
+}
+
+impl<T: Serialize> Serialize for Field<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.record.serialize(Pick {
+            name: self.name,
+            inner: serializer,
+        })
+    }
+}
+
+/// 構造体のnameのフィールドだけをinnerへ出力するSerializer
+struct Pick<S> {
+    name: &'static str,
+    inner: S,
+}
+
+/// 構造体のフィールドを順に調べ, 見つかれば出力結果を持つ
+struct PickStruct<S: Serializer> {
+    name: &'static str,
+    inner: Option<S>,
+    picked: Option<S::Ok>,
+}
+
+macro_rules! unsupported {
+    ($($method:ident($($arg:ty),*);)*) => {
+        $(
+            fn $method(self, $(_: $arg),*) -> Result<Self::Ok, Self::Error> {
+                Err(ser::Error::custom("only structs can be projected"))
+            }
+        )*
+    };
+}
+
+impl<S: Serializer> Serializer for Pick<S> {
+    type Ok = S::Ok;
+    type Error = S::Error;
+    type SerializeSeq = Impossible<S::Ok, S::Error>;
+    type SerializeTuple = Impossible<S::Ok, S::Error>;
+    type SerializeTupleStruct = Impossible<S::Ok, S::Error>;
+    type SerializeTupleVariant = Impossible<S::Ok, S::Error>;
+    type SerializeMap = Impossible<S::Ok, S::Error>;
+    type SerializeStruct = PickStruct<S>;
+    type SerializeStructVariant = Impossible<S::Ok, S::Error>;
+
+    unsupported! {
+        serialize_bool(bool);
+        serialize_i8(i8);
+        serialize_i16(i16);
+        serialize_i32(i32);
+        serialize_i64(i64);
+        serialize_u8(u8);
+        serialize_u16(u16);
+        serialize_u32(u32);
+        serialize_u64(u64);
+        serialize_f32(f32);
+        serialize_f64(f64);
+        serialize_char(char);
+        serialize_str(&str);
+        serialize_bytes(&[u8]);
+        serialize_none();
+        serialize_unit();
+        serialize_unit_struct(&'static str);
+        serialize_unit_variant(&'static str, u32, &'static str);
+    }
+
+    fn serialize_some<V: ?Sized + Serialize>(self, _: &V) -> Result<S::Ok, S::Error> {
+        Err(ser::Error::custom("only structs can be projected"))
+    }
+
+    fn serialize_newtype_struct<V: ?Sized + Serialize>(
+        self,
+        _: &'static str,
+        _: &V,
+    ) -> Result<S::Ok, S::Error> {
+        Err(ser::Error::custom("only structs can be projected"))
+    }
+
+    fn serialize_newtype_variant<V: ?Sized + Serialize>(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: &V,
+    ) -> Result<S::Ok, S::Error> {
+        Err(ser::Error::custom("only structs can be projected"))
+    }
+
+    fn serialize_seq(self, _: Option<usize>) -> Result<Self::SerializeSeq, S::Error> {
+        Err(ser::Error::custom("only structs can be projected"))
+    }
+
+    fn serialize_tuple(self, _: usize) -> Result<Self::SerializeTuple, S::Error> {
+        Err(ser::Error::custom("only structs can be projected"))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleStruct, S::Error> {
+        Err(ser::Error::custom("only structs can be projected"))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleVariant, S::Error> {
+        Err(ser::Error::custom("only structs can be projected"))
+    }
+
+    fn serialize_map(self, _: Option<usize>) -> Result<Self::SerializeMap, S::Error> {
+        Err(ser::Error::custom("only structs can be projected"))
+    }
+
+    fn serialize_struct(
+        self,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStruct, S::Error> {
+        Ok(PickStruct {
+            name: self.name,
+            inner: Some(self.inner),
+            picked: None,
+        })
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStructVariant, S::Error> {
+        Err(ser::Error::custom("only structs can be projected"))
+    }
+}
+
+impl<S: Serializer> SerializeStruct for PickStruct<S> {
+    type Ok = S::Ok;
+    type Error = S::Error;
+
+    fn serialize_field<V: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &V,
+    ) -> Result<(), S::Error> {
+        if key == self.name {
+            if let Some(inner) = self.inner.take() {
+                self.picked = Some(value.serialize(inner)?);
+            }
+        }
+        Ok(())
+    }
+
+    fn end(self) -> Result<S::Ok, S::Error> {
+        let name = self.name;
+        self.picked
+            .ok_or_else(|| ser::Error::custom(format!("no such field: {}", name)))
+    }
+}
+
+/// 構造体のフィールドのうちvaluesにあるものを置き換えてinnerへ出力するSerializer
+struct Replace<'a, S, V> {
+    values: &'a [(&'static str, V)],
+    inner: S,
+}
+
+struct ReplaceStruct<'a, S, V> {
+    values: &'a [(&'static str, V)],
+    inner: S,
+}
+
+impl<'a, S: Serializer, V: Serialize> Serializer for Replace<'a, S, V> {
+    type Ok = S::Ok;
+    type Error = S::Error;
+    type SerializeSeq = Impossible<S::Ok, S::Error>;
+    type SerializeTuple = Impossible<S::Ok, S::Error>;
+    type SerializeTupleStruct = Impossible<S::Ok, S::Error>;
+    type SerializeTupleVariant = Impossible<S::Ok, S::Error>;
+    type SerializeMap = Impossible<S::Ok, S::Error>;
+    type SerializeStruct = ReplaceStruct<'a, S::SerializeStruct, V>;
+    type SerializeStructVariant = Impossible<S::Ok, S::Error>;
+
+    unsupported! {
+        serialize_bool(bool);
+        serialize_i8(i8);
+        serialize_i16(i16);
+        serialize_i32(i32);
+        serialize_i64(i64);
+        serialize_u8(u8);
+        serialize_u16(u16);
+        serialize_u32(u32);
+        serialize_u64(u64);
+        serialize_f32(f32);
+        serialize_f64(f64);
+        serialize_char(char);
+        serialize_str(&str);
+        serialize_bytes(&[u8]);
+        serialize_none();
+        serialize_unit();
+        serialize_unit_struct(&'static str);
+        serialize_unit_variant(&'static str, u32, &'static str);
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, _: &T) -> Result<S::Ok, S::Error> {
+        Err(ser::Error::custom("only structs can be projected"))
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _: &'static str,
+        _: &T,
+    ) -> Result<S::Ok, S::Error> {
+        Err(ser::Error::custom("only structs can be projected"))
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: &T,
+    ) -> Result<S::Ok, S::Error> {
+        Err(ser::Error::custom("only structs can be projected"))
+    }
+
+    fn serialize_seq(self, _: Option<usize>) -> Result<Self::SerializeSeq, S::Error> {
+        Err(ser::Error::custom("only structs can be projected"))
+    }
+
+    fn serialize_tuple(self, _: usize) -> Result<Self::SerializeTuple, S::Error> {
+        Err(ser::Error::custom("only structs can be projected"))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleStruct, S::Error> {
+        Err(ser::Error::custom("only structs can be projected"))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleVariant, S::Error> {
+        Err(ser::Error::custom("only structs can be projected"))
+    }
+
+    fn serialize_map(self, _: Option<usize>) -> Result<Self::SerializeMap, S::Error> {
+        Err(ser::Error::custom("only structs can be projected"))
+    }
+
+    fn serialize_struct(
+        self,
+        name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStruct, S::Error> {
+        Ok(ReplaceStruct {
+            values: self.values,
+            inner: self.inner.serialize_struct(name, len)?,
+        })
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStructVariant, S::Error> {
+        Err(ser::Error::custom("only structs can be projected"))
+    }
+}
+
+impl<S: SerializeStruct, V: Serialize> SerializeStruct for ReplaceStruct<'_, S, V> {
+    type Ok = S::Ok;
+    type Error = S::Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), S::Error> {
+        match self.values.iter().find(|(name, _)| *name == key) {
+            Some((_, replaced)) => self.inner.serialize_field(key, replaced),
+            None => self.inner.serialize_field(key, value),
+        }
+    }
+
+    fn end(self) -> Result<S::Ok, S::Error> {
+        self.inner.end()
+    }
+}
@@ -1,10 +1,14 @@
+use std::time::Duration;
+
 use anyhow::Result;
 use clap::Clap;
 use env_logger::Env;
+use log::LevelFilter;
 
 mod app;
 mod cmd;
 mod external;
+mod geo;
 mod io;
 mod serde_chrono_custom;
 
@@ -12,31 +16,98 @@ mod serde_chrono_custom;
 #[clap(version = "0.1", author = "tadashi-aikawa")]
 struct Opts {
     /// The path of the config file to load
+    #[allow(dead_code)]
     #[clap(short, long, default_value = ".hibou.yaml")]
     config: String,
+    /// エラー以外のログを表示しない
+    #[clap(short, long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+    /// ログを詳しく表示する (-v: info, -vv: debug, -vvv: trace)
+    #[clap(short, long, parse(from_occurrences), global = true)]
+    verbose: i32,
+    /// データベースが他のプロセスにロックされている場合に待つ時間 (ミリ秒)
+    /// 更新中のデータベースを同時に参照する場合はWALモード (make-db --fast) との併用を推奨
+    #[clap(long, default_value = "5000", global = true)]
+    busy_timeout_ms: u64,
     #[clap(subcommand)]
     subcmd: SubCommand,
-    // #[clap(short, long, parse(from_occurrences), global = true)]
-    // verbose: i32,
+}
+
+impl Opts {
+    /// 指定がなければRUST_LOG (未設定ならwarn) に従う
+    fn log_level(&self) -> Option<LevelFilter> {
+        match (self.quiet, self.verbose) {
+            (true, _) => Some(LevelFilter::Error),
+            (false, 0) => None,
+            (false, 1) => Some(LevelFilter::Info),
+            (false, 2) => Some(LevelFilter::Debug),
+            (false, _) => Some(LevelFilter::Trace),
+        }
+    }
 }
 
 #[derive(Clap, Debug)]
 enum SubCommand {
     /// GTFS-JPからデータベースを作成します
     MakeDb(cmd::make_db::Opts),
+    /// 標準入力のCSVを1テーブル分データベースに登録します
+    ImportTable(cmd::import_table::Opts),
     /// データベースからデータを取得します
     Get(cmd::get::Opts),
+    /// 停留所・標柱間の直線距離を求めます
+    Distance(cmd::distance::Opts),
+    /// データベースからデータを検索します
+    Find(cmd::find::Opts),
+    /// 指定した地点の近くにある停留所・標柱を近い順に取得します
+    Nearby(cmd::nearby::Opts),
+    /// 停留所・標柱に発車する便の運行間隔を求めます
+    Headway(cmd::headway::Opts),
+    /// データベースからGTFSのzipファイルを作成します
+    Export(cmd::export::Opts),
+    /// 指定した経路と参照先のレコードだけを別のデータベースに書き出します
+    Extract(cmd::extract::Opts),
+    /// 複数のデータベースをIDに名前空間を付けて1つに合成します
+    Merge(cmd::merge::Opts),
+    /// データベースの値を計算して補完します
+    Compute(cmd::compute::Opts),
+    /// データベースのテーブル定義 (DDL) を表示します
+    Schema(cmd::schema::Opts),
+    /// テーブルのカラム定義をJSONなどの機械可読な形式で表示します
+    Describe(cmd::describe::Opts),
+    /// データベースのレコード数などの概要を表示します
+    Stats(cmd::stats::Opts),
+    /// データベースの参照整合性を検査します
+    Validate(cmd::validate::Opts),
     /// 開発用のテストコマンド
     Test(cmd::test::Opts),
 }
 
 fn main() -> Result<()> {
-    env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
-
     let opts: Opts = Opts::parse();
+
+    let mut logger = env_logger::Builder::from_env(Env::default().default_filter_or("warn"));
+    if let Some(level) = opts.log_level() {
+        logger.filter_level(level);
+    }
+    logger.init();
+    external::gtfsdb::set_busy_timeout(Duration::from_millis(opts.busy_timeout_ms));
+
     match opts.subcmd {
         SubCommand::MakeDb(op) => cmd::make_db::run(&op)?,
+        SubCommand::ImportTable(op) => cmd::import_table::run(&op)?,
         SubCommand::Get(op) => cmd::get::run(&op)?,
+        SubCommand::Distance(op) => cmd::distance::run(&op)?,
+        SubCommand::Find(op) => cmd::find::run(&op)?,
+        SubCommand::Nearby(op) => cmd::nearby::run(&op)?,
+        SubCommand::Headway(op) => cmd::headway::run(&op)?,
+        SubCommand::Export(op) => cmd::export::run(&op)?,
+        SubCommand::Extract(op) => cmd::extract::run(&op)?,
+        SubCommand::Merge(op) => cmd::merge::run(&op)?,
+        SubCommand::Compute(op) => cmd::compute::run(&op)?,
+        SubCommand::Schema(op) => cmd::schema::run(&op)?,
+        SubCommand::Describe(op) => cmd::describe::run(&op)?,
+        SubCommand::Stats(op) => cmd::stats::run(&op)?,
+        SubCommand::Validate(op) => cmd::validate::run(&op)?,
         SubCommand::Test(op) => cmd::test::run(&op)?,
     }
 